@@ -61,12 +61,13 @@ fn cache_key_generation_benchmark(c: &mut Criterion) {
 
 fn cache_set_benchmark(c: &mut Criterion) {
     let rt = Runtime::new().unwrap();
+    let shared_manager = rt.block_on(CacheManager::new());
     let data = create_benchmark_data();
     
     c.bench_function("cache_set", |b| {
         b.iter(|| {
             rt.block_on(async {
-                let mut cache_manager = CacheManager::new().await;
+                let mut cache_manager = shared_manager.clone();
                 let cached_response = shared_redis::cache::CachedResponse::new(
                     data.clone(),
                     "benchmark_key".to_string()
@@ -79,6 +80,7 @@ fn cache_set_benchmark(c: &mut Criterion) {
 
 fn cache_get_benchmark(c: &mut Criterion) {
     let rt = Runtime::new().unwrap();
+    let shared_manager = rt.block_on(CacheManager::new());
     let data = create_benchmark_data();
     
     // Pre-populate cache
@@ -94,7 +96,7 @@ fn cache_get_benchmark(c: &mut Criterion) {
     c.bench_function("cache_get", |b| {
         b.iter(|| {
             rt.block_on(async {
-                let mut cache_manager = CacheManager::new().await;
+                let mut cache_manager = shared_manager.clone();
                 let _ = cache_manager.get::<BenchmarkData>("benchmark_get_key").await;
             });
         });
@@ -103,6 +105,7 @@ fn cache_get_benchmark(c: &mut Criterion) {
 
 fn cache_hit_benchmark(c: &mut Criterion) {
     let rt = Runtime::new().unwrap();
+    let shared_manager = rt.block_on(CacheManager::new());
     let request = create_benchmark_request();
     let data = create_benchmark_data();
     
@@ -119,7 +122,7 @@ fn cache_hit_benchmark(c: &mut Criterion) {
     c.bench_function("cache_hit", |b| {
         b.iter(|| {
             rt.block_on(async {
-                let mut cache_manager = CacheManager::new().await;
+                let mut cache_manager = shared_manager.clone();
                 let _ = cache_manager.get_cached_response::<BenchmarkData, BenchmarkRequest>(
                     "benchmark_hit_test",
                     &request
@@ -131,12 +134,13 @@ fn cache_hit_benchmark(c: &mut Criterion) {
 
 fn cache_miss_benchmark(c: &mut Criterion) {
     let rt = Runtime::new().unwrap();
+    let shared_manager = rt.block_on(CacheManager::new());
     let request = create_benchmark_request();
     
     c.bench_function("cache_miss", |b| {
         b.iter(|| {
             rt.block_on(async {
-                let mut cache_manager = CacheManager::new().await;
+                let mut cache_manager = shared_manager.clone();
                 let _ = cache_manager.get_cached_response::<BenchmarkData, BenchmarkRequest>(
                     "benchmark_miss_test",
                     &request
@@ -148,11 +152,12 @@ fn cache_miss_benchmark(c: &mut Criterion) {
 
 fn bulk_cache_operations_benchmark(c: &mut Criterion) {
     let rt = Runtime::new().unwrap();
+    let shared_manager = rt.block_on(CacheManager::new());
     
     c.bench_function("bulk_cache_operations", |b| {
         b.iter(|| {
             rt.block_on(async {
-                let mut cache_manager = CacheManager::new().await;
+                let mut cache_manager = shared_manager.clone();
                 
                 // Set multiple cache entries
                 for i in 0..100 {
@@ -180,6 +185,49 @@ fn bulk_cache_operations_benchmark(c: &mut Criterion) {
     });
 }
 
+fn looped_get_vs_get_many_benchmark(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let shared_manager = rt.block_on(CacheManager::new());
+    let keys: Vec<String> = (0..100).map(|i| format!("batch_key_{}", i)).collect();
+    let key_refs: Vec<&str> = keys.iter().map(|k| k.as_str()).collect();
+    
+    // Pre-populate cache
+    rt.block_on(async {
+        let mut cache_manager = CacheManager::new().await;
+        for key in &keys {
+            let cached_response = shared_redis::cache::CachedResponse::new(
+                create_benchmark_data(),
+                key.clone()
+            );
+            let _ = cache_manager.set(key, &cached_response).await;
+        }
+    });
+    
+    let mut group = c.benchmark_group("batch_get_100_keys");
+    
+    group.bench_function("looped_get", |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                let mut cache_manager = shared_manager.clone();
+                for key in &key_refs {
+                    let _ = cache_manager.get::<BenchmarkData>(black_box(key)).await;
+                }
+            });
+        });
+    });
+    
+    group.bench_function("get_many", |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                let mut cache_manager = shared_manager.clone();
+                let _ = cache_manager.get_many::<BenchmarkData>(black_box(&key_refs)).await;
+            });
+        });
+    });
+    
+    group.finish();
+}
+
 criterion_group!(
     benches,
     cache_key_generation_benchmark,
@@ -187,7 +235,8 @@ criterion_group!(
     cache_get_benchmark,
     cache_hit_benchmark,
     cache_miss_benchmark,
-    bulk_cache_operations_benchmark
+    bulk_cache_operations_benchmark,
+    looped_get_vs_get_many_benchmark
 );
 
 criterion_main!(benches);
@@ -0,0 +1,71 @@
+//! Compression Benchmarks
+//! 
+//! This benchmark suite measures encoding time and stored size for each cache
+//! compression codec on 1MB of JSON.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use shared_redis::cache::CacheCompression;
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct LargeRecord {
+    id: u32,
+    name: String,
+    tags: Vec<String>,
+}
+
+fn create_large_payload() -> Vec<u8> {
+    let mut records = Vec::new();
+    let mut payload = serde_json::to_vec(&records).unwrap();
+    let mut i = 0;
+    
+    // Grow the payload until it reaches roughly 1MB of JSON
+    while payload.len() < 1024 * 1024 {
+        for _ in 0..1000 {
+            records.push(LargeRecord {
+                id: i,
+                name: format!("Record {}", i),
+                tags: vec!["cached".to_string(), format!("group_{}", i % 10)],
+            });
+            i += 1;
+        }
+        payload = serde_json::to_vec(&records).unwrap();
+    }
+    
+    payload
+}
+
+fn compression_benchmark(c: &mut Criterion) {
+    let payload = create_large_payload();
+    let codecs = [
+        ("none", CacheCompression::None),
+        ("gzip", CacheCompression::Gzip),
+        ("zstd", CacheCompression::Zstd),
+    ];
+    
+    let mut group = c.benchmark_group("compress_1mb");
+    
+    for (name, codec) in codecs {
+        let encoded = codec.encode(&payload, 1024).unwrap();
+        println!(
+            "{}: {} bytes -> {} bytes ({:.1}% of original)",
+            name,
+            payload.len(),
+            encoded.len(),
+            encoded.len() as f64 / payload.len() as f64 * 100.0
+        );
+        
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let encoded = codec.encode(black_box(&payload), 1024).unwrap();
+                CacheCompression::decode(black_box(&encoded)).unwrap();
+            });
+        });
+    }
+    
+    group.finish();
+}
+
+criterion_group!(benches, compression_benchmark);
+
+criterion_main!(benches);
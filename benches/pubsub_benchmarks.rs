@@ -0,0 +1,71 @@
+//! Pub/Sub Performance Benchmarks
+//! 
+//! This benchmark suite compares publishing throughput when opening a new
+//! connection per message versus reusing the shared connection manager.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use redis::AsyncCommands;
+use shared_redis::config::{create_redis_pool, init_shared_conn_manager};
+use shared_redis::operations::broadcasting_data;
+use tokio::runtime::Runtime;
+
+const BENCH_CHANNEL: &str = "benchmark_channel";
+const BENCH_PAYLOAD: &str = "{\"id\":12345,\"event\":\"benchmark\"}";
+
+fn redis_url() -> String {
+    std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string())
+}
+
+fn per_call_publish_benchmark(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let url = redis_url();
+
+    // Fail loudly instead of timing connection errors
+    if let Err(e) = rt.block_on(create_redis_pool(&url)) {
+        panic!("pub/sub benchmarks need a Redis server at {}: {}", url, e);
+    }
+    
+    c.bench_function("publish_per_call_connection", |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                let mut conn = create_redis_pool(&url).await.expect("failed to connect to Redis");
+                let _: () = conn
+                    .publish(black_box(BENCH_CHANNEL), black_box(BENCH_PAYLOAD))
+                    .await
+                    .expect("failed to publish");
+            });
+        });
+    });
+}
+
+fn pooled_publish_benchmark(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    std::env::set_var("REDIS_URL", redis_url());
+    
+    // Warm the shared connection outside of the measured loop, and fail loudly instead of
+    // timing connection errors
+    if let Err(e) = rt.block_on(init_shared_conn_manager()) {
+        panic!("pub/sub benchmarks need a Redis server at {}: {}", redis_url(), e);
+    }
+    
+    c.bench_function("publish_pooled_connection", |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                broadcasting_data(
+                    black_box(BENCH_CHANNEL.to_string()),
+                    black_box(BENCH_PAYLOAD.to_string())
+                )
+                .await
+                .expect("failed to publish");
+            });
+        });
+    });
+}
+
+criterion_group!(
+    benches,
+    per_call_publish_benchmark,
+    pooled_publish_benchmark
+);
+
+criterion_main!(benches);
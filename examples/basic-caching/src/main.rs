@@ -3,7 +3,7 @@
 //! This example demonstrates how to use shared-redis for basic caching operations
 //! including cache hits, misses, and automatic key generation.
 
-use shared_redis::cache::CacheManager;
+use shared_redis::cache::{CacheError, CacheManager};
 use serde::{Deserialize, Serialize};
 use log::{info, warn};
 
@@ -100,9 +100,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         info!("Successfully set manual cache entry");
     }
     
-    // Retrieve manual cache entry
-    if let Ok(Some(cached)) = cache_manager.get::<UserProfile>(cache_key).await {
-        info!("Retrieved manual cache entry: {:?}", cached.data);
+    // Retrieve manual cache entry, telling a miss apart from a broken Redis
+    match cache_manager.get::<UserProfile>(cache_key).await {
+        Ok(Some(cached)) => info!("Retrieved manual cache entry: {:?}", cached.data),
+        Ok(None) => info!("Manual cache entry not found"),
+        Err(CacheError::Connection(e)) => warn!("Redis connection problem: {}", e),
+        Err(CacheError::Serialization(e)) => warn!("Cached entry was corrupted and removed: {}", e),
+        Err(CacheError::Redis(e)) => warn!("Redis rejected the request: {}", e),
     }
     
     // Example 3: Cache statistics
@@ -0,0 +1,61 @@
+//! OpenTelemetry Example
+//! 
+//! This example demonstrates the cache metrics exported by shared-redis when the
+//! `opentelemetry` feature is enabled, sent over OTLP to a collector on localhost:4317.
+
+use opentelemetry_otlp::MetricExporter;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use shared_redis::cache::CacheManager;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Product {
+    id: u32,
+    name: String,
+    price_cents: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ProductRequest {
+    product_id: u32,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Install the meter provider before the first cache call; shared-redis creates its
+    // instruments from the global provider on first use
+    let exporter = MetricExporter::builder().with_tonic().build()?;
+    let provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(exporter)
+        .build();
+    opentelemetry::global::set_meter_provider(provider.clone());
+    
+    println!("Starting shared-redis OpenTelemetry example");
+    
+    // Set up Redis connection
+    std::env::set_var("REDIS_URL", "redis://localhost:6379");
+    
+    let mut cache_manager = CacheManager::new().await;
+    let request = ProductRequest { product_id: 42 };
+    
+    // First lookup counts cache.operations{operation="get", result="miss"}
+    let product = cache_manager.get_or_set("product", &request, || async {
+        Ok(Product {
+            id: 42,
+            name: "Mechanical Keyboard".to_string(),
+            price_cents: 12_900,
+        })
+    }).await?;
+    println!("Fetched product: {:?}", product.data);
+    
+    // Repeated lookups count as hits and feed the cache.duration histogram
+    for _ in 0..5 {
+        cache_manager.get_cached_response::<Product, ProductRequest>("product", &request).await?;
+    }
+    
+    // Flush the last batch of metrics before exiting
+    provider.shutdown()?;
+    
+    println!("OpenTelemetry example completed");
+    Ok(())
+}
@@ -0,0 +1,41 @@
+//! Pipeline Example
+//! 
+//! This example demonstrates sending several commands in a single round trip with
+//! the general-purpose pipeline builder: a page-view counter is incremented and a
+//! greeting is read back together.
+
+use shared_redis::config::get_redis_conn;
+use log::info;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Initialize logging
+    env_logger::init();
+    
+    info!("Starting shared-redis pipeline example");
+    
+    // Set up Redis connection
+    std::env::set_var("REDIS_URL", "redis://localhost:6379");
+    
+    let conn = get_redis_conn().await?;
+    
+    // Seed the key read back below
+    conn.pipeline()
+        .add_ignored(redis::cmd("SET").arg("example:greeting").arg("hello"))
+        .execute::<()>()
+        .await?;
+    
+    // Increment a counter and read a key in one round trip
+    let (views, greeting): (i64, Option<String>) = conn
+        .pipeline()
+        .add(redis::cmd("INCR").arg("example:page_views"))
+        .add(redis::cmd("GET").arg("example:greeting"))
+        .execute()
+        .await?;
+    
+    info!("Page views: {}", views);
+    info!("Greeting: {:?}", greeting);
+    
+    info!("Pipeline example completed");
+    Ok(())
+}
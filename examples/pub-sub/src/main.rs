@@ -3,7 +3,7 @@
 //! This example demonstrates how to use shared-redis for real-time messaging
 //! between microservices using Redis pub/sub capabilities.
 
-use shared_redis::operations::{broadcasting_data, subscribe_data};
+use shared_redis::operations::{broadcast_json, subscribe_typed};
 use serde::{Deserialize, Serialize};
 use log::{info, warn, error};
 use futures::StreamExt;
@@ -81,9 +81,7 @@ async fn publisher_task() -> Result<(), Box<dyn std::error::Error>> {
             timestamp: chrono::Utc::now().to_rfc3339(),
         };
         
-        let event_json = serde_json::to_string(&user_event)?;
-        
-        match broadcasting_data("user_events".to_string(), event_json).await {
+        match broadcast_json("user_events", &user_event).await {
             Ok(_) => info!("Published user event for user {}", i),
             Err(e) => warn!("Failed to publish user event: {}", e),
         }
@@ -100,9 +98,7 @@ async fn publisher_task() -> Result<(), Box<dyn std::error::Error>> {
             created_at: chrono::Utc::now().to_rfc3339(),
         };
         
-        let notification_json = serde_json::to_string(&notification)?;
-        
-        match broadcasting_data("notifications".to_string(), notification_json).await {
+        match broadcast_json("notifications", &notification).await {
             Ok(_) => info!("Published notification for user {}", i),
             Err(e) => warn!("Failed to publish notification: {}", e),
         }
@@ -117,28 +113,21 @@ async fn publisher_task() -> Result<(), Box<dyn std::error::Error>> {
 async fn subscribe_to_user_events() -> Result<(), Box<dyn std::error::Error>> {
     info!("Starting user events subscriber");
     
-    let mut pubsub = subscribe_data("user_events".to_string()).await?;
+    let mut events = subscribe_typed::<UserEvent>("user_events".to_string()).await?;
     
     let mut message_count = 0;
-    while let Some(msg) = pubsub.on_message().next().await {
+    while let Some(result) = events.next().await {
         message_count += 1;
         
-        match msg.get_payload::<String>() {
-            Ok(payload) => {
-                match serde_json::from_str::<UserEvent>(&payload) {
-                    Ok(event) => {
-                        info!("Received user event: {:?}", event);
-                        
-                        // Process the event (e.g., update analytics, send notifications)
-                        process_user_event(&event).await;
-                    }
-                    Err(e) => {
-                        warn!("Failed to deserialize user event: {}", e);
-                    }
-                }
+        match result {
+            Ok(event) => {
+                info!("Received user event: {:?}", event);
+                
+                // Process the event (e.g., update analytics, send notifications)
+                process_user_event(&event).await;
             }
             Err(e) => {
-                warn!("Failed to get message payload: {}", e);
+                warn!("Failed to decode user event: {}", e);
             }
         }
         
@@ -155,28 +144,21 @@ async fn subscribe_to_user_events() -> Result<(), Box<dyn std::error::Error>> {
 async fn subscribe_to_notifications() -> Result<(), Box<dyn std::error::Error>> {
     info!("Starting notifications subscriber");
     
-    let mut pubsub = subscribe_data("notifications".to_string()).await?;
+    let mut notifications = subscribe_typed::<NotificationMessage>("notifications".to_string()).await?;
     
     let mut message_count = 0;
-    while let Some(msg) = pubsub.on_message().next().await {
+    while let Some(result) = notifications.next().await {
         message_count += 1;
         
-        match msg.get_payload::<String>() {
-            Ok(payload) => {
-                match serde_json::from_str::<NotificationMessage>(&payload) {
-                    Ok(notification) => {
-                        info!("Received notification: {:?}", notification);
-                        
-                        // Process the notification (e.g., send email, push notification)
-                        process_notification(&notification).await;
-                    }
-                    Err(e) => {
-                        warn!("Failed to deserialize notification: {}", e);
-                    }
-                }
+        match result {
+            Ok(notification) => {
+                info!("Received notification: {:?}", notification);
+                
+                // Process the notification (e.g., send email, push notification)
+                process_notification(&notification).await;
             }
             Err(e) => {
-                warn!("Failed to get message payload: {}", e);
+                warn!("Failed to decode notification: {}", e);
             }
         }
         
@@ -0,0 +1,83 @@
+//! Redis Streams Example
+//! 
+//! This example demonstrates at-least-once event delivery with Redis Streams and
+//! consumer groups. Unlike pub/sub, events published while no consumer is running
+//! are kept, and events a consumer read but never acknowledged are redelivered
+//! when it restarts.
+
+use shared_redis::stream::{xack, xadd, xread_group};
+use serde::{Deserialize, Serialize};
+use log::{info, warn};
+use std::time::Duration;
+
+const STREAM: &str = "order_events";
+const GROUP: &str = "billing";
+const CONSUMER: &str = "billing-worker-1";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct OrderEvent {
+    order_id: u32,
+    event_type: String,
+    amount_cents: u64,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Initialize logging
+    env_logger::init();
+    
+    info!("Starting shared-redis streams example");
+    
+    // Set up Redis connection
+    std::env::set_var("REDIS_URL", "redis://localhost:6379");
+    
+    // Publish events before any consumer is running; they are not lost
+    for i in 1..=3 {
+        let event = OrderEvent {
+            order_id: i,
+            event_type: "order_placed".to_string(),
+            amount_cents: 1_000 * i as u64,
+        };
+        let id = xadd(STREAM, &event).await?;
+        info!("Published order event {} as entry {}", i, id);
+    }
+    
+    // First consumer run reads events but "crashes" before acknowledging them
+    let messages = xread_group::<OrderEvent>(STREAM, GROUP, CONSUMER, 10).await?;
+    info!("Consumer read {} events, then crashed before acknowledging", messages.len());
+    
+    // Restarted consumer with the same name receives the unacknowledged events again
+    run_consumer().await?;
+    
+    info!("Streams example completed");
+    Ok(())
+}
+
+async fn run_consumer() -> Result<(), Box<dyn std::error::Error>> {
+    info!("Starting consumer {}", CONSUMER);
+    
+    loop {
+        let messages = xread_group::<OrderEvent>(STREAM, GROUP, CONSUMER, 10).await?;
+        if messages.is_empty() {
+            info!("No more events to process");
+            break;
+        }
+        
+        for message in messages {
+            match message.data {
+                Ok(event) => {
+                    info!("Processing event {}: {:?}", message.id, event);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+                Err(e) => {
+                    warn!("Skipping undecodable event {}: {}", message.id, e);
+                }
+            }
+            
+            // Acknowledge so the event is not redelivered
+            xack(STREAM, GROUP, &[message.id.as_str()]).await?;
+        }
+    }
+    
+    Ok(())
+}
@@ -0,0 +1,56 @@
+//! Tracing Example
+//! 
+//! This example demonstrates the spans emitted by shared-redis when the `tracing`
+//! feature is enabled, printed through a `tracing_subscriber` fmt layer.
+
+use shared_redis::cache::CacheManager;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Product {
+    id: u32,
+    name: String,
+    price_cents: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ProductRequest {
+    product_id: u32,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Log every span on close so the recorded fields (key, operation, result, latency) are visible
+    tracing_subscriber::fmt()
+        .with_span_events(FmtSpan::CLOSE)
+        .with_max_level(tracing::Level::DEBUG)
+        .init();
+    
+    info!("Starting shared-redis tracing example");
+    
+    // Set up Redis connection
+    std::env::set_var("REDIS_URL", "redis://localhost:6379");
+    
+    let mut cache_manager = CacheManager::new().await;
+    let request = ProductRequest { product_id: 42 };
+    
+    // First lookup misses and emits `cache.get` with cache.result=miss, then `cache.set`
+    let product = cache_manager.get_or_set("product", &request, || async {
+        Ok(Product {
+            id: 42,
+            name: "Mechanical Keyboard".to_string(),
+            price_cents: 12_900,
+        })
+    }).await?;
+    info!("Fetched product: {:?}", product.data);
+    
+    // Second lookup emits `cache.get` with cache.result=hit
+    if let Some(cached) = cache_manager.get_cached_response::<Product, ProductRequest>("product", &request).await? {
+        info!("Fetched product from cache: {:?}", cached.data);
+    }
+    
+    info!("Tracing example completed");
+    Ok(())
+}
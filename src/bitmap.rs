@@ -0,0 +1,208 @@
+use crate::trace::record_latency;
+use anyhow::Result as AnyResult;
+use redis::aio::ConnectionLike;
+use redis::AsyncCommands;
+use std::time::Instant;
+
+/// Set or clear the bit at `offset`, growing the string as needed. Returns the previous bit.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "SETBIT", redis.latency_ms)
+))]
+pub async fn setbit<C>(key: String, offset: usize, value: bool, mut conn: C) -> AnyResult<bool>
+where
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let previous = conn.setbit(key, offset, value).await?;
+    record_latency(start);
+
+    Ok(previous)
+}
+
+/// The bit at `offset`. Bits past the end of the string, and bits of a missing key, are 0.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "GETBIT", redis.latency_ms)
+))]
+pub async fn getbit<C>(key: String, offset: usize, mut conn: C) -> AnyResult<bool>
+where
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let bit = conn.getbit(key, offset).await?;
+    record_latency(start);
+
+    Ok(bit)
+}
+
+/// Number of set bits in the whole value
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "BITCOUNT", redis.latency_ms)
+))]
+pub async fn bitcount<C>(key: String, mut conn: C) -> AnyResult<u64>
+where
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let count = conn.bitcount(key).await?;
+    record_latency(start);
+
+    Ok(count)
+}
+
+/// What `BitField::incr_by` and `BitField::set` do when a value leaves its type's range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitFieldOverflow {
+    /// Wrap around, modulo the type's range (the Redis default)
+    Wrap,
+    /// Saturate at the type's minimum or maximum
+    Sat,
+    /// Leave the field unchanged and return `None` for that operation
+    Fail,
+}
+
+impl BitFieldOverflow {
+    fn as_arg(&self) -> &'static str {
+        match self {
+            BitFieldOverflow::Wrap => "WRAP",
+            BitFieldOverflow::Sat => "SAT",
+            BitFieldOverflow::Fail => "FAIL",
+        }
+    }
+}
+
+/// Builder for a `BITFIELD` command, applying several integer field operations atomically.
+///
+/// Field types use the Redis notation: `u8`, `i16` and so on, up to `i64` or `u63`. Offsets
+/// are in bits. `overflow` applies to the operations queued after it.
+#[derive(Clone)]
+pub struct BitField {
+    cmd: redis::Cmd,
+    operations: usize,
+}
+
+impl BitField {
+    pub fn new(key: &str) -> Self {
+        let mut cmd = redis::cmd("BITFIELD");
+        cmd.arg(key);
+        Self { cmd, operations: 0 }
+    }
+
+    /// Read the field of type `field_type` at bit `offset`
+    pub fn get(&mut self, field_type: &str, offset: u64) -> &mut Self {
+        self.cmd.arg("GET").arg(field_type).arg(offset);
+        self.operations += 1;
+        self
+    }
+
+    /// Write `value` to the field, returning its previous value
+    pub fn set(&mut self, field_type: &str, offset: u64, value: i64) -> &mut Self {
+        self.cmd.arg("SET").arg(field_type).arg(offset).arg(value);
+        self.operations += 1;
+        self
+    }
+
+    /// Add `increment` (which may be negative) to the field, returning its new value
+    pub fn incr_by(&mut self, field_type: &str, offset: u64, increment: i64) -> &mut Self {
+        self.cmd.arg("INCRBY").arg(field_type).arg(offset).arg(increment);
+        self.operations += 1;
+        self
+    }
+
+    /// Overflow behavior for the `set` and `incr_by` operations queued after this
+    pub fn overflow(&mut self, overflow: BitFieldOverflow) -> &mut Self {
+        self.cmd.arg("OVERFLOW").arg(overflow.as_arg());
+        self
+    }
+
+    /// Send the command. Returns one value per queued `get`, `set` and `incr_by`, in order,
+    /// with `None` where `BitFieldOverflow::Fail` prevented the operation.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        skip_all,
+        fields(redis.operation = "BITFIELD", redis.commands = self.operations, redis.latency_ms)
+    ))]
+    pub async fn execute<C>(&self, mut conn: C) -> AnyResult<Vec<Option<i64>>>
+    where
+        C: ConnectionLike + Send,
+    {
+        if self.operations == 0 {
+            return Ok(Vec::new());
+        }
+
+        let start = Instant::now();
+        let values = self.cmd.query_async(&mut conn).await?;
+        record_latency(start);
+
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::live_conn;
+
+    #[test]
+    fn overflow_modes_use_the_redis_keywords() {
+        let modes = [BitFieldOverflow::Wrap, BitFieldOverflow::Sat, BitFieldOverflow::Fail];
+        assert_eq!(modes.map(|mode| mode.as_arg()), ["WRAP", "SAT", "FAIL"]);
+    }
+
+    #[test]
+    fn the_builder_queues_operations_in_order() {
+        let mut field = BitField::new("counters");
+        field.get("u8", 0).overflow(BitFieldOverflow::Sat).incr_by("i16", 8, -3).set("u4", 24, 9);
+
+        let expected = redis::cmd("BITFIELD")
+            .arg("counters")
+            .arg("GET").arg("u8").arg(0)
+            .arg("OVERFLOW").arg("SAT")
+            .arg("INCRBY").arg("i16").arg(8).arg(-3)
+            .arg("SET").arg("u4").arg(24).arg(9)
+            .get_packed_command();
+        assert_eq!(field.cmd.get_packed_command(), expected);
+        assert_eq!(field.operations, 3);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn bits_are_toggled_and_counted() {
+        let (mut conn, prefix) = live_conn().await;
+        let key = format!("{}:flags", prefix);
+
+        assert!(!setbit(key.clone(), 3, true, conn.clone()).await.unwrap());
+        assert!(!setbit(key.clone(), 100, true, conn.clone()).await.unwrap());
+        assert!(getbit(key.clone(), 3, conn.clone()).await.unwrap());
+        assert!(!getbit(key.clone(), 4, conn.clone()).await.unwrap());
+        assert_eq!(bitcount(key.clone(), conn.clone()).await.unwrap(), 2);
+
+        assert!(setbit(key.clone(), 3, false, conn.clone()).await.unwrap());
+        assert_eq!(bitcount(key.clone(), conn.clone()).await.unwrap(), 1);
+        let _: () = conn.del(&key).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn bitfield_increments_wrap_saturate_or_fail_on_overflow() {
+        let (mut conn, prefix) = live_conn().await;
+        let key = format!("{}:counters", prefix);
+
+        let mut field = BitField::new(&key);
+        field.set("u8", 0, 250).set("u8", 8, 250).set("u8", 16, 250);
+        assert_eq!(field.execute(conn.clone()).await.unwrap(), [Some(0), Some(0), Some(0)]);
+
+        let mut field = BitField::new(&key);
+        field
+            .incr_by("u8", 0, 10)
+            .overflow(BitFieldOverflow::Sat)
+            .incr_by("u8", 8, 10)
+            .overflow(BitFieldOverflow::Fail)
+            .incr_by("u8", 16, 10)
+            .get("u8", 16);
+        assert_eq!(field.execute(conn.clone()).await.unwrap(), [Some(4), Some(255), None, Some(250)]);
+        assert!(BitField::new(&key).execute(conn.clone()).await.unwrap().is_empty());
+        let _: () = conn.del(&key).await.unwrap();
+    }
+}
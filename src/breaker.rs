@@ -0,0 +1,298 @@
+use crate::config::{get_breaker_cooldown, get_breaker_threshold, RedisConfig};
+use crate::retry::RetryPolicy;
+use log::{info, warn};
+use redis::{ErrorKind, RedisError, RedisResult};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const OPEN_ERROR: &str = "Circuit breaker open";
+
+/// Position of a `CircuitBreaker`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Commands flow normally
+    Closed,
+    /// Too many consecutive failures; commands are rejected until the cooldown ends
+    Open,
+    /// The cooldown has ended and a single probe command is allowed through
+    HalfOpen,
+}
+
+/// Change in the health of the connection to Redis, as seen by the commands it carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// A command succeeded again after connection errors
+    Connected,
+    /// A command failed with a connection error. The connection is re-established on the
+    /// next command.
+    Reconnecting,
+    /// Failures reached the breaker threshold and commands now fail fast
+    Failed,
+}
+
+type Listener = Arc<dyn Fn(ConnectionEvent) + Send + Sync>;
+
+#[derive(Default)]
+struct Listeners(Mutex<Vec<Listener>>);
+
+impl fmt::Debug for Listeners {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = self.0.lock().unwrap_or_else(|e| e.into_inner()).len();
+        write!(f, "Listeners({})", count)
+    }
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    probe_started_at: Option<Instant>,
+    disconnected: bool,
+}
+
+impl BreakerState {
+    /// Whether commands are rejected: the cooldown is running, or a probe is in flight. A
+    /// probe whose outcome was never recorded, e.g. because its future was dropped, stops
+    /// counting after one more cooldown so another probe can go out.
+    fn rejecting(&self, opened_at: Instant, cooldown: Duration, now: Instant) -> bool {
+        now.duration_since(opened_at) < cooldown || self.probe_started_at.is_some_and(|started| now.duration_since(started) < cooldown)
+    }
+}
+
+/// Fails fast while Redis is down instead of waiting on every command.
+///
+/// After `threshold` consecutive connection errors the breaker opens and rejects commands
+/// for `cooldown`. The first command after the cooldown is let through as a probe: success
+/// closes the breaker, failure opens it for another cooldown. Only connection-level errors
+/// count as failures; a `WRONGTYPE` reply still proves the server is up.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+    listeners: Listeners,
+    /// Clock for the cooldown, replaceable in tests
+    now: fn() -> Instant,
+}
+
+impl CircuitBreaker {
+    /// Open after `threshold` consecutive failures (0 disables the breaker) for `cooldown`
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+            listeners: Listeners::default(),
+            now: Instant::now,
+        }
+    }
+
+    /// Read `REDIS_BREAKER_THRESHOLD` and `REDIS_BREAKER_COOLDOWN_MS`
+    pub fn from_env() -> Self {
+        Self::new(get_breaker_threshold(), get_breaker_cooldown())
+    }
+
+    /// Use the breaker settings from `config`
+    pub fn from_config(config: &RedisConfig) -> Self {
+        Self::new(config.breaker_threshold, config.breaker_cooldown)
+    }
+
+    /// A breaker that never opens
+    pub fn disabled() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+
+    pub fn state(&self) -> CircuitState {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if state.rejecting(opened_at, self.cooldown, (self.now)()) => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Whether a command may be sent now. Once the cooldown has passed this claims the single
+    /// probe slot, so the caller must report the outcome with `record`. A probe that is never
+    /// reported holds the slot for one cooldown, after which another probe is allowed.
+    pub fn allow(&self) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.opened_at {
+            None => true,
+            Some(opened_at) if state.rejecting(opened_at, self.cooldown, (self.now)()) => false,
+            Some(_) => {
+                state.probe_started_at = Some((self.now)());
+                true
+            }
+        }
+    }
+
+    /// Feed the result of a command that `allow` let through
+    pub fn record<T>(&self, result: &RedisResult<T>) {
+        match result {
+            Err(e) if RetryPolicy::is_retryable(e) => self.record_failure(),
+            _ => self.record_success(),
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.opened_at.is_some() {
+            info!("Redis reachable again, closing circuit breaker");
+        }
+        let reconnected = state.disconnected;
+        *state = BreakerState::default();
+        drop(state);
+
+        if reconnected {
+            self.notify(ConnectionEvent::Connected);
+        }
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let reconnecting = !state.disconnected;
+        state.disconnected = true;
+        let mut failed = false;
+        if self.threshold > 0 {
+            state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+            if state.probe_started_at.is_some() || (state.opened_at.is_none() && state.consecutive_failures >= self.threshold) {
+                warn!(
+                    "Circuit breaker open after {} consecutive Redis failures, failing fast for {:?}",
+                    state.consecutive_failures, self.cooldown
+                );
+                state.opened_at = Some((self.now)());
+                state.probe_started_at = None;
+                failed = true;
+            }
+        }
+        drop(state);
+
+        if reconnecting {
+            self.notify(ConnectionEvent::Reconnecting);
+        }
+        if failed {
+            self.notify(ConnectionEvent::Failed);
+        }
+    }
+
+    /// Call `listener` on every connection state change recorded from now on. It runs
+    /// inline on the task whose command observed the change, so it should return quickly.
+    pub fn on_event<F>(&self, listener: F)
+    where
+        F: Fn(ConnectionEvent) + Send + Sync + 'static,
+    {
+        self.listeners.0.lock().unwrap_or_else(|e| e.into_inner()).push(Arc::new(listener));
+    }
+
+    fn notify(&self, event: ConnectionEvent) {
+        let listeners = self.listeners.0.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        for listener in listeners {
+            listener(event);
+        }
+    }
+
+    /// Error returned in place of a command the breaker rejected
+    pub fn open_error() -> RedisError {
+        RedisError::from((ErrorKind::IoError, OPEN_ERROR))
+    }
+
+    /// Whether `error` came from `open_error` rather than from Redis
+    pub fn is_open_error(error: &RedisError) -> bool {
+        error.kind() == ErrorKind::IoError && error.to_string().starts_with(OPEN_ERROR)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::sync::OnceLock;
+
+    const COOLDOWN: Duration = Duration::from_secs(30);
+
+    thread_local! {
+        static ELAPSED: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+    }
+
+    /// Each test runs on its own thread, so each gets its own clock
+    fn fake_now() -> Instant {
+        static START: OnceLock<Instant> = OnceLock::new();
+        *START.get_or_init(Instant::now) + ELAPSED.with(Cell::get)
+    }
+
+    fn advance(by: Duration) {
+        ELAPSED.with(|elapsed| elapsed.set(elapsed.get() + by));
+    }
+
+    fn opened_breaker() -> CircuitBreaker {
+        let breaker = CircuitBreaker {
+            now: fake_now,
+            ..CircuitBreaker::new(1, COOLDOWN)
+        };
+        breaker.record_failure();
+        breaker
+    }
+
+    #[test]
+    fn opens_after_threshold_and_half_opens_after_cooldown() {
+        let breaker = opened_breaker();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.allow());
+
+        advance(COOLDOWN - Duration::from_millis(1));
+        assert!(!breaker.allow());
+        advance(Duration::from_millis(1));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(breaker.allow());
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn only_one_probe_at_a_time() {
+        let breaker = opened_breaker();
+        advance(COOLDOWN);
+        assert!(breaker.allow());
+        assert!(!breaker.allow());
+        assert_eq!(breaker.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn abandoned_probe_half_opens_again() {
+        let breaker = opened_breaker();
+        advance(COOLDOWN);
+        // The probe's caller goes away without calling `record`
+        assert!(breaker.allow());
+
+        advance(COOLDOWN);
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(breaker.allow());
+    }
+
+    #[test]
+    fn failed_probe_reopens() {
+        let breaker = opened_breaker();
+        advance(COOLDOWN);
+        assert!(breaker.allow());
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn connection_events_follow_failures_and_recovery() {
+        let breaker = CircuitBreaker::new(2, COOLDOWN);
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+        breaker.on_event(move |event| seen.lock().unwrap().push(event));
+
+        breaker.record_failure();
+        breaker.record_failure();
+        breaker.record_failure();
+        breaker.record_success();
+        breaker.record_success();
+
+        use ConnectionEvent::*;
+        assert_eq!(*events.lock().unwrap(), [Reconnecting, Failed, Connected]);
+    }
+}
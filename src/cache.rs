@@ -1,16 +1,397 @@
-use crate::config::{get_redis_conn_manager_optional, get_cache_ttl, AsyncConnManager};
+use crate::breaker::{CircuitBreaker, CircuitState, ConnectionEvent};
+use crate::codec::{CacheCodec, CacheFormat, SerializationOptions};
+use crate::local::{Invalidations, LocalCache};
+use crate::metrics::{CacheCounters, CacheMetrics};
+use crate::operations::set_nx_with_expiry;
+use crate::otel::record_duration;
+use crate::retry::RetryPolicy;
+use crate::trace::{record_latency, record_result};
+use crate::config::{
+    get_key_hash_threshold, get_connect_timeout, AsyncConnManager, Client, RedisConfig, RedisConn,
+    ENV_CACHE_COMPRESSION, ENV_CACHE_ENCRYPTION_KEY, ENV_CACHE_OVERSIZE_POLICY,
+};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
 use anyhow::Result as AnyResult;
+use async_trait::async_trait;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use futures::{Stream, StreamExt};
 use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
-use log::{info, error, debug};
-use std::collections::HashMap;
+use log::{info, error, debug, warn};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long `ping` waits for a reply before treating Redis as unreachable
+const PING_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How often `get_or_set` re-checks for a value another caller is computing
+const STAMPEDE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Marks a stored value as carrying a compression header rather than a raw payload
+const COMPRESSION_MAGIC: u8 = 0xC7;
+const COMPRESSION_VERSION: u8 = 1;
+
+/// Compression applied to serialized cache values before they are stored.
+///
+/// Compressed values are prefixed with a three byte header (magic, version, codec) so
+/// entries written without compression continue to deserialize as-is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheCompression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl CacheCompression {
+    /// Read the codec from `REDIS_CACHE_COMPRESSION` (`none`, `gzip` or `zstd`)
+    pub fn from_env() -> Self {
+        match std::env::var(ENV_CACHE_COMPRESSION)
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "gzip" => CacheCompression::Gzip,
+            "zstd" => CacheCompression::Zstd,
+            _ => CacheCompression::None,
+        }
+    }
+
+    fn id(&self) -> u8 {
+        match self {
+            CacheCompression::None => 0,
+            CacheCompression::Gzip => 1,
+            CacheCompression::Zstd => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> AnyResult<Self> {
+        match id {
+            0 => Ok(CacheCompression::None),
+            1 => Ok(CacheCompression::Gzip),
+            2 => Ok(CacheCompression::Zstd),
+            other => Err(anyhow::anyhow!("Unknown cache compression codec: {}", other)),
+        }
+    }
+
+    /// Encode a payload for storage, compressing it only when it exceeds `threshold` bytes
+    pub fn encode(&self, payload: &[u8], threshold: usize) -> AnyResult<Vec<u8>> {
+        if *self == CacheCompression::None || payload.len() <= threshold {
+            return Ok(payload.to_vec());
+        }
+
+        let compressed = match self {
+            CacheCompression::None => unreachable!(),
+            CacheCompression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(payload)?;
+                encoder.finish()?
+            }
+            CacheCompression::Zstd => zstd::encode_all(payload, 0)?,
+        };
+
+        let mut stored = Vec::with_capacity(compressed.len() + 3);
+        stored.extend_from_slice(&[COMPRESSION_MAGIC, COMPRESSION_VERSION, self.id()]);
+        stored.extend_from_slice(&compressed);
+        Ok(stored)
+    }
+
+    /// Decode a stored value, decompressing it if it carries a compression header
+    pub fn decode(stored: &[u8]) -> AnyResult<Vec<u8>> {
+        match stored {
+            [COMPRESSION_MAGIC, COMPRESSION_VERSION, codec, body @ ..] => match Self::from_id(*codec)? {
+                CacheCompression::None => Ok(body.to_vec()),
+                CacheCompression::Gzip => {
+                    let mut decoded = Vec::new();
+                    GzDecoder::new(body).read_to_end(&mut decoded)?;
+                    Ok(decoded)
+                }
+                CacheCompression::Zstd => Ok(zstd::decode_all(body)?),
+            },
+            _ => Ok(stored.to_vec()),
+        }
+    }
+}
+
+/// Marks a stored value as encrypted; followed by a version byte and the nonce
+const ENCRYPTION_MAGIC: u8 = 0xC9;
+const ENCRYPTION_VERSION: u8 = 1;
+const ENCRYPTION_NONCE_LEN: usize = 12;
+
+/// AES-256-GCM encryption of cache values at rest.
+///
+/// Applied after compression, since ciphertext does not compress. Each value gets a fresh
+/// random nonce, stored in its header next to the ciphertext.
+#[derive(Clone)]
+pub struct CacheEncryption {
+    cipher: Aes256Gcm,
+}
+
+impl fmt::Debug for CacheEncryption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CacheEncryption(..)")
+    }
+}
+
+/// A stored value could not be decrypted, e.g. because it was written with another key
+#[derive(Debug)]
+struct DecryptionError;
+
+impl fmt::Display for DecryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("failed to decrypt cached value")
+    }
+}
+
+impl std::error::Error for DecryptionError {}
+
+impl CacheEncryption {
+    /// Build from a 32-byte key
+    pub fn new(key: &[u8]) -> AnyResult<Self> {
+        let cipher = Aes256Gcm::new_from_slice(key)
+            .map_err(|_| anyhow::anyhow!("Cache encryption key must be 32 bytes, got {}", key.len()))?;
+        Ok(Self { cipher })
+    }
+
+    /// Read a hex-encoded 32-byte key from `REDIS_CACHE_ENCRYPTION_KEY`.
+    ///
+    /// Returns `Ok(None)` when the variable is unset, and an error when it is set but invalid.
+    pub fn from_env() -> AnyResult<Option<Self>> {
+        match std::env::var(ENV_CACHE_ENCRYPTION_KEY) {
+            Ok(key) if !key.is_empty() => {
+                let key = hex::decode(key.trim())
+                    .map_err(|e| anyhow::anyhow!("{} is not valid hex: {}", ENV_CACHE_ENCRYPTION_KEY, e))?;
+                Ok(Some(Self::new(&key)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub fn encrypt(&self, payload: &[u8]) -> AnyResult<Vec<u8>> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, payload)
+            .map_err(|_| anyhow::anyhow!("failed to encrypt cache value"))?;
+
+        let mut stored = Vec::with_capacity(2 + ENCRYPTION_NONCE_LEN + ciphertext.len());
+        stored.extend_from_slice(&[ENCRYPTION_MAGIC, ENCRYPTION_VERSION]);
+        stored.extend_from_slice(&nonce);
+        stored.extend_from_slice(&ciphertext);
+        Ok(stored)
+    }
+
+    pub fn decrypt(&self, stored: &[u8]) -> AnyResult<Vec<u8>> {
+        match stored {
+            [ENCRYPTION_MAGIC, ENCRYPTION_VERSION, rest @ ..] if rest.len() >= ENCRYPTION_NONCE_LEN => {
+                let (nonce, ciphertext) = rest.split_at(ENCRYPTION_NONCE_LEN);
+                self.cipher
+                    .decrypt(Nonce::from_slice(nonce), ciphertext)
+                    .map_err(|_| DecryptionError.into())
+            }
+            _ => Err(DecryptionError.into()),
+        }
+    }
+
+    fn is_encrypted(stored: &[u8]) -> bool {
+        stored.first() == Some(&ENCRYPTION_MAGIC)
+    }
+}
+
+/// Marks a value written by `set_if_version`; followed by the version as 8 big-endian bytes
+/// and then the encoded value. It is the outermost header so the script can read it.
+const VERSION_MAGIC: u8 = 0xCA;
+const VERSION_LEN: usize = 8;
+
+/// What a write does with a value whose encoded size exceeds the configured maximum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OversizePolicy {
+    /// Fail the write with `CacheError::ValueTooLarge`
+    #[default]
+    Reject,
+    /// Log a warning and report the write as skipped (`Ok(false)`)
+    Skip,
+}
+
+impl OversizePolicy {
+    /// Read the policy from `REDIS_CACHE_OVERSIZE_POLICY` (`reject` or `skip`)
+    pub fn from_env() -> Self {
+        match std::env::var(ENV_CACHE_OVERSIZE_POLICY)
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "skip" => OversizePolicy::Skip,
+            _ => OversizePolicy::Reject,
+        }
+    }
+}
+
+/// Error returned by cache reads and writes when Redis is reachable but the operation fails.
+///
+/// A cache miss is not an error; it is reported as `Ok(None)`.
+#[derive(Debug)]
+pub enum CacheError {
+    /// The connection to Redis failed, dropped or timed out
+    Connection(redis::RedisError),
+    /// The value could not be serialized or the stored value could not be deserialized
+    Serialization(anyhow::Error),
+    /// Redis rejected the write because `maxmemory` is reached and the policy is `noeviction`
+    OutOfMemory(redis::RedisError),
+    /// The encoded value exceeds the configured maximum size and was not written
+    ValueTooLarge { size: usize, max: usize },
+    /// `set_if_version` found a different version stored than the caller expected
+    VersionConflict { expected: u64, actual: u64 },
+    /// Redis rejected the command
+    Redis(redis::RedisError),
+    /// The manager was shut down with `shutdown`
+    Closed,
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::Connection(e) => write!(f, "Redis connection error: {}", e),
+            CacheError::Serialization(e) => write!(f, "cache serialization error: {}", e),
+            CacheError::OutOfMemory(e) => write!(f, "Redis out of memory: {}", e),
+            CacheError::ValueTooLarge { size, max } => {
+                write!(f, "cache value of {} bytes exceeds the {} byte limit", size, max)
+            }
+            CacheError::VersionConflict { expected, actual } => {
+                write!(f, "cache entry is at version {}, expected {}", actual, expected)
+            }
+            CacheError::Redis(e) => write!(f, "Redis error: {}", e),
+            CacheError::Closed => write!(f, "cache manager is closed"),
+        }
+    }
+}
+
+impl std::error::Error for CacheError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CacheError::Connection(e) | CacheError::OutOfMemory(e) | CacheError::Redis(e) => Some(e),
+            CacheError::Serialization(e) => Some(e.as_ref()),
+            CacheError::ValueTooLarge { .. } | CacheError::VersionConflict { .. } | CacheError::Closed => None,
+        }
+    }
+}
+
+impl From<redis::RedisError> for CacheError {
+    fn from(e: redis::RedisError) -> Self {
+        if e.is_io_error() || e.is_connection_dropped() || e.is_connection_refusal() || e.is_timeout() {
+            CacheError::Connection(e)
+        } else if e.code() == Some("OOM") {
+            CacheError::OutOfMemory(e)
+        } else {
+            CacheError::Redis(e)
+        }
+    }
+}
+
+/// Result of a `CacheManager::health` check, suitable for readiness/liveness probes.
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthStatus {
+    /// Whether Redis answered the PING in time
+    pub healthy: bool,
+    /// Round-trip time of the PING, if it completed
+    pub latency: Option<Duration>,
+    /// Why the check failed, if it did
+    pub error: Option<String>,
+}
+
+/// Server-wide statistics parsed from `INFO`, returned by `CacheManager::server_stats`.
+///
+/// These cover every client of the server, unlike `CacheMetrics` which counts only this
+/// process. Fields the server does not report are left at zero.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RedisStats {
+    pub used_memory_bytes: u64,
+    pub connected_clients: u64,
+    pub keyspace_hits: u64,
+    pub keyspace_misses: u64,
+    pub evicted_keys: u64,
+    pub uptime: Duration,
+}
+
+impl RedisStats {
+    fn from_info(info: &str) -> Self {
+        let fields: HashMap<&str, &str> = info
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once(':'))
+            .collect();
+        let number = |name: &str| fields.get(name).and_then(|value| value.trim().parse().ok()).unwrap_or(0);
+        Self {
+            used_memory_bytes: number("used_memory"),
+            connected_clients: number("connected_clients"),
+            keyspace_hits: number("keyspace_hits"),
+            keyspace_misses: number("keyspace_misses"),
+            evicted_keys: number("evicted_keys"),
+            uptime: Duration::from_secs(number("uptime_in_seconds")),
+        }
+    }
+
+    /// Fraction of server-side key lookups that were hits, or 0.0 if there were none
+    pub fn hit_ratio(&self) -> f64 {
+        let lookups = self.keyspace_hits + self.keyspace_misses;
+        if lookups == 0 {
+            return 0.0;
+        }
+        self.keyspace_hits as f64 / lookups as f64
+    }
+}
+
+/// One recorded cache mutation, read back with `CacheManager::audit_log`
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    /// Stream entry ID
+    pub id: String,
+    /// Namespaced key, or the pattern for `clear_pattern`
+    pub key: String,
+    /// `set`, `delete`, `clear_pattern`, `rename`, `copy` or `restore`
+    pub op: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Encoded value size for sets, payload size for restores, number of keys removed for
+    /// `clear_pattern`, 0 otherwise
+    pub size: usize,
+}
+
+impl AuditEntry {
+    fn from_stream(entry: &redis::streams::StreamId) -> Self {
+        let text = |field: &str| entry.get::<String>(field).unwrap_or_default();
+        Self {
+            id: entry.id.clone(),
+            key: text("key"),
+            op: text("op"),
+            timestamp: text("ts").parse().unwrap_or_default(),
+            size: text("size").parse().unwrap_or(0),
+        }
+    }
+}
+
+/// Where cache mutations are recorded when auditing is enabled
+#[derive(Debug, Clone)]
+struct AuditLog {
+    stream: String,
+    max_len: usize,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CachedResponse<T> {
     pub data: T,
     pub cached_at: chrono::DateTime<chrono::Utc>,
     pub cache_key: String,
+    /// Set by `set_if_version`; `None` for entries written without versioning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
 }
 
 impl<T> CachedResponse<T> {
@@ -19,188 +400,3596 @@ impl<T> CachedResponse<T> {
             data,
             cached_at: chrono::Utc::now(),
             cache_key,
+            version: None,
+        }
+    }
+
+    /// Freshness of this entry at `now`. Entries stamped in the future (clock skew between
+    /// writers) count as fresh.
+    pub fn state_at(&self, max_age: Duration, now: chrono::DateTime<chrono::Utc>) -> CacheState {
+        match now.signed_duration_since(self.cached_at).to_std() {
+            Ok(age) if age > max_age => CacheState::Stale,
+            _ => CacheState::Fresh,
         }
     }
+
+    /// Whether this entry is older than `max_age`
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        self.state_at(max_age, chrono::Utc::now()) == CacheState::Stale
+    }
 }
 
-pub struct CacheManager {
-    conn: Option<AsyncConnManager>,
+/// Freshness of a cache lookup, for stale-while-revalidate.
+///
+/// An entry is `Stale` once it is older than the caller's `max_age` but has not yet
+/// expired from Redis. Store entries with a TTL longer than `max_age` so there is a
+/// window in which stale data can be served while a refresh runs in the background.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheState {
+    Fresh,
+    Stale,
+    Miss,
 }
 
-impl CacheManager {
-    pub async fn new() -> Self {
-        let conn = get_redis_conn_manager_optional().await;
-        Self { conn }
+/// How cache values are serialized, compressed and encrypted before storage
+#[derive(Debug, Clone)]
+struct ValueEncoding {
+    format: CacheFormat,
+    json_style: SerializationOptions,
+    compression: CacheCompression,
+    compression_threshold: usize,
+    encryption: Option<CacheEncryption>,
+}
+
+impl ValueEncoding {
+    fn from_config(config: &RedisConfig) -> AnyResult<Self> {
+        Ok(Self {
+            format: CacheFormat::from_env(),
+            json_style: SerializationOptions::from_env(),
+            compression: CacheCompression::from_env(),
+            compression_threshold: config.compression_threshold,
+            encryption: CacheEncryption::from_env()?,
+        })
     }
 
-    pub fn is_available(&self) -> bool {
-        self.conn.is_some()
+    fn encode<T: Serialize>(&self, data: &T) -> AnyResult<Vec<u8>> {
+        let serialized = match self.format {
+            CacheFormat::Json => self.json_style.to_json(data)?,
+            #[cfg(feature = "msgpack")]
+            CacheFormat::MessagePack => self.format.serialize(data)?,
+        };
+        let compressed = self.compression.encode(&serialized, self.compression_threshold)?;
+        let sealed = match &self.encryption {
+            Some(encryption) => encryption.encrypt(&compressed)?,
+            None => compressed,
+        };
+        Ok(self.format.wrap(sealed))
     }
 
-    /// Generate a cache key from request data using SHA256 hash
-    pub fn generate_cache_key<T: Serialize>(prefix: &str, request_data: &T) -> AnyResult<String> {
-        let serialized = serde_json::to_string(request_data)?;
-        let mut hasher = Sha256::new();
-        hasher.update(serialized.as_bytes());
-        let hash = hex::encode(hasher.finalize());
-        Ok(format!("{}:{}", prefix, hash))
+    /// `encode`, prefixed with a header carrying `version` for `set_if_version`
+    fn encode_versioned<T: Serialize>(&self, data: &T, version: u64) -> AnyResult<Vec<u8>> {
+        let encoded = self.encode(data)?;
+        let mut stored = Vec::with_capacity(1 + VERSION_LEN + encoded.len());
+        stored.push(VERSION_MAGIC);
+        stored.extend_from_slice(&version.to_be_bytes());
+        stored.extend_from_slice(&encoded);
+        Ok(stored)
     }
 
-    /// Get cached response by key
-    pub async fn get<T>(&mut self, key: &str) -> AnyResult<Option<CachedResponse<T>>>
+    /// Decode a stored value using the format recorded with it, regardless of the
+    /// format this manager writes with. Encrypted values fail with `DecryptionError`
+    /// when no key is configured or the key does not match.
+    fn decode<T>(&self, stored: &[u8]) -> AnyResult<T>
     where
         T: for<'de> Deserialize<'de>,
     {
-        if let Some(ref mut conn) = self.conn {
-            match conn.get::<&str, String>(key).await {
-                Ok(cached_data) => {
-                    debug!("Cache HIT for key: {}", key);
-                    match serde_json::from_str::<CachedResponse<T>>(&cached_data) {
-                        Ok(response) => Ok(Some(response)),
-                        Err(e) => {
-                            error!("Failed to deserialize cached data for key {}: {}", key, e);
-                            // Clean up corrupted cache entry
-                            let _: Result<(), redis::RedisError> = conn.del(key).await;
-                            Ok(None)
-                        }
-                    }
-                }
-                Err(e) => {
-                    if e.to_string().contains("nil") || e.to_string().contains("not found") {
-                        debug!("Cache MISS for key: {}", key);
-                        Ok(None)
-                    } else {
-                        error!("Redis error while getting key {}: {}", key, e);
-                        Ok(None)
-                    }
-                }
+        let (_, stored) = split_version(stored);
+        let (format, body) = CacheFormat::unwrap(stored)?;
+        let opened = if CacheEncryption::is_encrypted(body) {
+            match &self.encryption {
+                Some(encryption) => encryption.decrypt(body)?,
+                None => return Err(DecryptionError.into()),
             }
         } else {
-            debug!("Redis not available, returning cache miss for key: {}", key);
-            Ok(None)
+            body.to_vec()
+        };
+        let decoded = CacheCompression::decode(&opened)?;
+        format.deserialize(&decoded)
+    }
+}
+
+/// Split the `set_if_version` header off a stored value, giving the version it carries (0
+/// when there is none) and the encoded value. Must agree with the check in `set_if_version`'s
+/// script.
+fn split_version(stored: &[u8]) -> (u64, &[u8]) {
+    match stored {
+        [VERSION_MAGIC, rest @ ..] if rest.len() >= VERSION_LEN => {
+            let (version, body) = rest.split_at(VERSION_LEN);
+            (u64::from_be_bytes(version.try_into().expect("VERSION_LEN bytes")), body)
         }
+        _ => (0, stored),
     }
+}
 
-    /// Set cached response with TTL
-    pub async fn set<T>(&mut self, key: &str, data: &CachedResponse<T>) -> AnyResult<bool>
-    where
-        T: Serialize,
-    {
-        if let Some(ref mut conn) = self.conn {
-            let serialized = serde_json::to_string(data)?;
-            let ttl = get_cache_ttl() as usize;
-            
-            match conn.set_ex::<&str, String, ()>(key, serialized, ttl).await {
-                Ok(_) => {
-                    debug!("Cache SET for key: {} with TTL: {}s", key, ttl);
-                    Ok(true)
-                }
-                Err(e) => {
-                    error!("Failed to set cache for key {}: {}", key, e);
-                    Ok(false)
-                }
-            }
+/// Prefix of the Redis sets holding tagged keys. It is reserved as a cache prefix, so tag
+/// sets stay apart from generated cache keys while remaining readable in `redis-cli`.
+const TAG_SET_PREFIX: &str = "__tag__";
+
+/// Redis set holding the keys tagged with `tag`
+fn tag_set_key(tag: &str) -> String {
+    format!("{}:{}", TAG_SET_PREFIX, tag)
+}
+
+fn build_cache_key<T: Serialize>(prefix: &str, request_data: &T, hash_threshold: usize) -> AnyResult<String> {
+    if prefix == TAG_SET_PREFIX {
+        return Err(anyhow::anyhow!("Cache prefix {} is reserved for tag sets", TAG_SET_PREFIX));
+    }
+    let serialized = serde_json::to_string(request_data)?;
+    if serialized.len() <= hash_threshold && is_readable_key_part(&serialized) {
+        return Ok(format!("{}:{}", prefix, serialized));
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(serialized.as_bytes());
+    let hash = hex::encode(hasher.finalize());
+    Ok(format!("{}:{}", prefix, hash))
+}
+
+/// Whether a serialized request can be embedded in a key without escaping. Glob characters
+/// are excluded so readable keys can't be mistaken for patterns by `clear_pattern`.
+fn is_readable_key_part(serialized: &str) -> bool {
+    !serialized.is_empty()
+        && serialized
+            .chars()
+            .all(|c| !c.is_whitespace() && !c.is_control() && !matches!(c, '*' | '?' | '[' | ']' | '\\'))
+}
+
+/// Prefix a key with a namespace, if any
+fn namespaced_key(namespace: &Option<String>, key: &str) -> String {
+    match namespace {
+        Some(namespace) => format!("{}:{}", namespace, key),
+        None => key.to_string(),
+    }
+}
+
+/// A key as it appears in log lines: unchanged, or the first 12 hex digits of its SHA-256
+/// when redaction is on
+pub(crate) fn log_key(key: &str, redact: bool) -> String {
+    if redact {
+        let digest = hex::encode(Sha256::digest(key.as_bytes()));
+        format!("#{}", &digest[..12])
+    } else {
+        key.to_string()
+    }
+}
+
+/// Whether the server rejected the command itself, as servers before 4.0 do for `UNLINK`
+fn is_unknown_command(e: &redis::RedisError) -> bool {
+    e.code() == Some("ERR") && e.detail().is_some_and(|detail| detail.starts_with("unknown command"))
+}
+
+/// An error as it appears in log lines. Decoding errors can quote the value being decoded,
+/// so redaction drops the detail.
+fn log_error(e: &dyn fmt::Display, redact: bool) -> String {
+    if redact {
+        "<redacted>".to_string()
+    } else {
+        e.to_string()
+    }
+}
+
+/// Cursor state behind `CacheManager::scan_keys`
+struct ScanState {
+    conn: Option<RedisConn>,
+    closed: Arc<AtomicBool>,
+    breaker: Arc<CircuitBreaker>,
+    retry: RetryPolicy,
+    cursor: u64,
+    buffered: VecDeque<String>,
+    finished: bool,
+}
+
+/// Redis-backed response cache.
+///
+/// Cloning is cheap: clones share the underlying multiplexed connection and metrics
+/// counters, so create one manager at startup and hand clones to request handlers rather
+/// than calling `new` per request. Each clone keeps its own replica rotation and can be
+/// reconfigured with the `with_*` methods independently.
+#[derive(Clone)]
+pub struct CacheManager {
+    conn: Option<RedisConn>,
+    config: Arc<RedisConfig>,
+    replicas: Vec<RedisConn>,
+    next_replica: usize,
+    fallback: Option<RedisConn>,
+    mirror_writes: bool,
+    local: Option<Arc<LocalCache>>,
+    invalidations: Option<Arc<Invalidations>>,
+    audit: Option<AuditLog>,
+    enabled: Arc<AtomicBool>,
+    closed: Arc<AtomicBool>,
+    encoding: ValueEncoding,
+    counters: Arc<CacheCounters>,
+    namespace: Option<String>,
+    retry: RetryPolicy,
+    breaker: Arc<CircuitBreaker>,
+    ttl_seconds: u64,
+    scan_count: usize,
+    key_hash_threshold: usize,
+    skip_cache_on_key_error: bool,
+    redact_logs: bool,
+    max_value_bytes: usize,
+    oversize_policy: OversizePolicy,
+    stampede_lock_ttl: Duration,
+}
+
+impl CacheManager {
+    pub async fn new() -> Self {
+        Self::from_config(RedisConfig::from_env()).await
+    }
+
+    /// Build a manager from explicit settings instead of the process environment.
+    ///
+    /// The serialization format, compression codec and oversize policy still default from the
+    /// environment; use `with_format`, `with_compression` and `with_oversize_policy` to
+    /// override them.
+    pub async fn from_config(config: RedisConfig) -> Self {
+        let mut conn = config.connect_optional().await;
+        let mut fallback = if config.cache_enabled {
+            config.connect_fallback().await
         } else {
-            debug!("Redis not available, skipping cache set for key: {}", key);
-            Ok(false)
+            None
+        };
+        if conn.is_none() && fallback.is_some() {
+            warn!("Primary Redis unreachable at startup, using the fallback for reads and writes");
+            conn = fallback.take();
         }
+        let replicas = if conn.is_some() {
+            config.connect_replicas().await
+        } else {
+            Vec::new()
+        };
+        let manager = Self::from_conn(conn, &config);
+        if !manager.is_available() {
+            return manager;
+        }
+        let mut manager = Self {
+            replicas,
+            fallback,
+            mirror_writes: config.mirror_writes_to_fallback,
+            ..manager
+        };
+        if config.client_tracking {
+            manager.track_local_cache();
+        }
+        manager
     }
 
-    /// Cache a response
-    pub async fn cache_response<T, R>(
-        &mut self,
-        cache_prefix: &str,
-        request_data: &R,
-        response_data: T,
-    ) -> AnyResult<CachedResponse<T>>
-    where
-        T: Serialize + Clone,
-        R: Serialize,
-    {
-        let cache_key = Self::generate_cache_key(cache_prefix, request_data)?;
-        let cached_response = CachedResponse::new(response_data.clone(), cache_key.clone());
-        
-        if self.set(&cache_key, &cached_response).await? {
-            info!("Successfully cached response for key: {}", cache_key);
+    /// Build a manager around an existing connection instead of `REDIS_URL`.
+    ///
+    /// Useful for tests and embedding, where the caller already owns a connection to a
+    /// specific server. Read replicas are not used. Encoding, namespace and retry settings
+    /// still default from the environment and can be overridden with the `with_*` methods.
+    pub fn with_connection(conn: AsyncConnManager) -> Self {
+        Self::from_conn(Some(RedisConn::Single(conn)), &RedisConfig::from_env())
+    }
+
+    /// Build a manager from a pre-configured `redis::Client`
+    pub async fn from_client(client: Client) -> AnyResult<Self> {
+        let timeout = get_connect_timeout();
+        let conn = tokio::time::timeout(timeout, AsyncConnManager::new(client))
+            .await
+            .map_err(|_| anyhow::anyhow!("Timed out connecting to Redis after {}ms", timeout.as_millis()))??;
+        Ok(Self::with_connection(conn))
+    }
+
+    fn from_conn(conn: Option<RedisConn>, config: &RedisConfig) -> Self {
+        // An invalid encryption key must not fall back to storing plaintext, so the cache
+        // is disabled instead
+        let (conn, encoding) = match ValueEncoding::from_config(config) {
+            Ok(encoding) => (conn, encoding),
+            Err(e) => {
+                error!("Invalid cache encryption settings, disabling cache: {}", e);
+                let encoding = ValueEncoding {
+                    format: CacheFormat::from_env(),
+                    json_style: SerializationOptions::default(),
+                    compression: CacheCompression::None,
+                    compression_threshold: config.compression_threshold,
+                    encryption: None,
+                };
+                (None, encoding)
+            }
+        };
+        Self {
+            conn,
+            config: Arc::new(config.clone()),
+            replicas: Vec::new(),
+            next_replica: 0,
+            fallback: None,
+            mirror_writes: false,
+            local: LocalCache::new(config.local_cache_capacity, config.local_cache_ttl).map(Arc::new),
+            invalidations: None,
+            audit: config.audit_key.as_ref().map(|stream| AuditLog {
+                stream: stream.clone(),
+                max_len: config.audit_max_len,
+            }),
+            enabled: Arc::new(AtomicBool::new(true)),
+            closed: Arc::new(AtomicBool::new(false)),
+            encoding,
+            counters: Arc::new(CacheCounters::default()),
+            namespace: config.key_namespace.clone(),
+            retry: RetryPolicy::from_config(config),
+            breaker: Arc::new(CircuitBreaker::from_config(config)),
+            ttl_seconds: config.cache_ttl_seconds,
+            scan_count: config.scan_count,
+            key_hash_threshold: config.key_hash_threshold,
+            skip_cache_on_key_error: config.skip_cache_on_key_error,
+            redact_logs: config.log_redact,
+            max_value_bytes: config.max_value_bytes,
+            oversize_policy: OversizePolicy::from_env(),
+            stampede_lock_ttl: config.stampede_lock_ttl,
         }
-        
-        Ok(cached_response)
     }
 
-    /// Get cached response
-    pub async fn get_cached_response<T, R>(
-        &mut self,
-        cache_prefix: &str,
-        request_data: &R,
-    ) -> AnyResult<Option<CachedResponse<T>>>
-    where
-        T: for<'de> Deserialize<'de>,
-        R: Serialize,
-    {
-        let cache_key = Self::generate_cache_key(cache_prefix, request_data)?;
-        self.get(&cache_key).await
+    /// Override the namespace prepended to every key this manager reads, writes or deletes
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = if namespace.is_empty() { None } else { Some(namespace.to_string()) };
+        if self.config.client_tracking && self.is_available() {
+            self.track_local_cache();
+        }
+        self
     }
 
-    /// Delete cache entry by key
-    pub async fn delete(&mut self, key: &str) -> AnyResult<bool> {
-        if let Some(ref mut conn) = self.conn {
-            match conn.del::<&str, u32>(key).await {
-                Ok(deleted_count) => {
-                    debug!("Deleted {} cache entries for key: {}", deleted_count, key);
-                    Ok(deleted_count > 0)
-                }
-                Err(e) => {
-                    error!("Failed to delete cache for key {}: {}", key, e);
-                    Ok(false)
-                }
+    /// Override the compression codec used for values written by this manager
+    pub fn with_compression(mut self, compression: CacheCompression) -> Self {
+        self.encoding.compression = compression;
+        self
+    }
+
+    /// Encrypt values written by this manager, and decrypt values read, with `encryption`
+    pub fn with_encryption(mut self, encryption: CacheEncryption) -> Self {
+        self.encoding.encryption = Some(encryption);
+        self
+    }
+
+    /// Override the serialization format used for values written by this manager
+    pub fn with_format(mut self, format: CacheFormat) -> Self {
+        self.encoding.format = format;
+        self
+    }
+
+    /// Override the JSON layout of values written by this manager. Ignored for other formats.
+    pub fn with_serialization_options(mut self, options: SerializationOptions) -> Self {
+        self.encoding.json_style = options;
+        self
+    }
+
+    /// Override the retry policy applied to transient command failures
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Keep up to `capacity` recently read entries in process memory for at most `ttl`.
+    ///
+    /// `get` is answered from this L1 before asking Redis. Sets and deletes through this
+    /// manager and its clones update it, but writes from other instances do not, so another
+    /// process's change can take up to `ttl` to show up here unless `REDIS_CLIENT_TRACKING`
+    /// is on. A `capacity` of 0 disables it.
+    pub fn with_local_cache(mut self, capacity: usize, ttl: Duration) -> Self {
+        self.local = LocalCache::new(capacity, ttl).map(Arc::new);
+        if self.config.client_tracking && self.is_available() {
+            self.track_local_cache();
+        }
+        self
+    }
+
+    /// (Re)start `CLIENT TRACKING` invalidation of the L1 for keys in the current namespace.
+    ///
+    /// Only single-node deployments with `REDIS_URL` are supported; elsewhere the L1 keeps
+    /// relying on its TTL.
+    fn track_local_cache(&mut self) {
+        self.invalidations = None;
+        let Some(local) = self.local.clone() else {
+            return;
+        };
+        if tokio::runtime::Handle::try_current().is_err() {
+            warn!("Client tracking needs a Tokio runtime, the local cache relies on its TTL instead");
+            return;
+        }
+        let client = match &self.config.redis_url {
+            Some(url) if !self.config.cluster_enabled && self.config.sentinel_master.is_none() => Client::open(url.as_str()),
+            _ => {
+                warn!("Client tracking needs a single-node REDIS_URL, the local cache relies on its TTL instead");
+                return;
             }
-        } else {
-            debug!("Redis not available, skipping cache delete for key: {}", key);
-            Ok(false)
+        };
+        match client {
+            Ok(client) => {
+                let prefix = self.namespace.as_ref().map(|namespace| format!("{}:", namespace));
+                self.invalidations = Some(Arc::new(Invalidations::spawn(client, prefix, local)));
+            }
+            Err(e) => warn!("Invalid REDIS_URL for client tracking: {}", e),
         }
     }
 
-    /// Clear cache entries matching a pattern
-    pub async fn clear_pattern(&mut self, pattern: &str) -> AnyResult<u32> {
-        if let Some(ref mut conn) = self.conn {
-            let keys: Vec<String> = conn.keys(pattern).await.unwrap_or_default();
-            let mut deleted_count = 0;
-            
-            for key in keys {
-                if let Ok(count) = conn.del::<String, u32>(key.clone()).await {
-                    deleted_count += count;
+    /// Record every write, delete and `clear_pattern` in the Redis stream `stream`, trimmed
+    /// to roughly `max_len` entries. Off unless enabled here or with `REDIS_AUDIT_KEY`.
+    pub fn with_audit_log(mut self, stream: &str, max_len: usize) -> Self {
+        self.audit = Some(AuditLog {
+            stream: stream.to_string(),
+            max_len,
+        });
+        self
+    }
+
+    /// Append a mutation to the audit stream. Failures are logged and never fail the mutation.
+    async fn audit(&mut self, op: &str, key: &str, size: usize) {
+        self.audit_many(op, &[(key, size)]).await;
+    }
+
+    /// Append one audit entry per key in a single pipelined round trip
+    async fn audit_many<K: AsRef<str>>(&mut self, op: &str, entries: &[(K, usize)]) {
+        if let (Some(audit), Some(conn)) = (&self.audit, self.conn.as_mut()) {
+            if entries.is_empty() {
+                return;
+            }
+            let maxlen = redis::streams::StreamMaxlen::Approx(audit.max_len);
+            let ts = chrono::Utc::now().to_rfc3339();
+            let mut pipe = redis::pipe();
+            for (key, size) in entries {
+                let fields = [
+                    ("key", key.as_ref().to_string()),
+                    ("op", op.to_string()),
+                    ("ts", ts.clone()),
+                    ("size", size.to_string()),
+                ];
+                pipe.xadd_maxlen(&audit.stream, maxlen, "*", &fields).ignore();
+            }
+            let appended: redis::RedisResult<()> = pipe.query_async(conn).await;
+            if let Err(e) = appended {
+                match entries {
+                    [(key, _)] => warn!("Failed to record {} of key {} in audit stream {}: {}", op, log_key(key.as_ref(), self.redact_logs), audit.stream, e),
+                    _ => warn!("Failed to record {} of {} keys in audit stream {}: {}", op, entries.len(), audit.stream, e),
                 }
             }
-            
-            info!("Cleared {} cache entries matching pattern: {}", deleted_count, pattern);
-            Ok(deleted_count)
-        } else {
-            debug!("Redis not available, skipping pattern clear for: {}", pattern);
-            Ok(0)
         }
     }
 
-    /// Get cache statistics
-    pub async fn get_cache_info(&mut self) -> AnyResult<HashMap<String, String>> {
-        if let Some(ref mut conn) = self.conn {
-            let info: String = redis::cmd("INFO")
-                .arg("memory")
-                .query_async(conn)
-                .await
-                .unwrap_or_default();
-            
-            let mut stats = HashMap::new();
-            for line in info.lines() {
-                if let Some((key, value)) = line.split_once(':') {
-                    stats.insert(key.to_string(), value.to_string());
-                }
+    /// The `count` most recent audited mutations, newest first
+    pub async fn audit_log(&mut self, count: usize) -> AnyResult<Vec<AuditEntry>> {
+        self.ensure_open()?;
+        match (&self.audit, self.conn.as_mut()) {
+            (Some(audit), Some(conn)) => {
+                let reply: redis::streams::StreamRangeReply = conn.xrevrange_count(&audit.stream, "+", "-", count).await?;
+                Ok(reply.ids.iter().map(AuditEntry::from_stream).collect())
             }
-            
-            Ok(stats)
+            (None, _) => Err(anyhow::anyhow!("Cache auditing is not enabled")),
+            (_, None) => Err(anyhow::anyhow!("Redis not available")),
+        }
+    }
+
+    /// Drop `keys` (already namespaced) from the L1, if there is one
+    fn forget_local<K: AsRef<str>>(&self, keys: &[K]) {
+        if let Some(local) = &self.local {
+            keys.iter().for_each(|key| local.remove(key.as_ref()));
+        }
+    }
+
+    /// When a request cannot be turned into a cache key, skip the cache and return the fresh
+    /// value instead of failing the call. Off by default, so key bugs surface as errors.
+    pub fn with_skip_on_key_error(mut self, skip: bool) -> Self {
+        self.skip_cache_on_key_error = skip;
+        self
+    }
+
+    /// Log a short hash in place of each key, and leave out error details that can quote
+    /// cached values. Hashes are stable, so one key's log lines can still be correlated.
+    pub fn with_log_redaction(mut self, redact: bool) -> Self {
+        self.redact_logs = redact;
+        self
+    }
+
+    /// Refuse to cache values whose encoded size exceeds `max_bytes`; 0 removes the limit
+    pub fn with_max_value_size(mut self, max_bytes: usize) -> Self {
+        self.max_value_bytes = max_bytes;
+        self
+    }
+
+    /// Choose whether oversized values fail the write or are skipped with a warning
+    pub fn with_oversize_policy(mut self, policy: OversizePolicy) -> Self {
+        self.oversize_policy = policy;
+        self
+    }
+
+    /// Apply the oversize policy to a value of `size` encoded bytes that exceeds the limit
+    fn reject_oversized(&self, key: &str, size: usize) -> Result<bool, CacheError> {
+        match self.oversize_policy {
+            OversizePolicy::Reject => {
+                debug!("Not caching key {}: {} bytes exceeds the limit", log_key(key, self.redact_logs), size);
+                Err(CacheError::ValueTooLarge { size, max: self.max_value_bytes })
+            }
+            OversizePolicy::Skip => {
+                warn!("Not caching key {}: {} bytes exceeds the {} byte limit", log_key(key, self.redact_logs), size, self.max_value_bytes);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Read from `fallback` when the primary fails with a connection error.
+    ///
+    /// Writes still go to the primary only. With `mirror_writes`, sets and deletes are also
+    /// sent to the fallback in the background, so it holds warm entries when it takes over.
+    pub fn with_fallback(mut self, fallback: RedisConn, mirror_writes: bool) -> Self {
+        self.fallback = Some(fallback);
+        self.mirror_writes = mirror_writes;
+        self
+    }
+
+    /// Replace the circuit breaker. Clones made afterwards share the new breaker.
+    pub fn with_circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.breaker = Arc::new(breaker);
+        self
+    }
+
+    /// Current position of the circuit breaker guarding the primary
+    pub fn circuit_state(&self) -> CircuitState {
+        self.breaker.state()
+    }
+
+    /// Call `callback` whenever commands on the primary see the connection drop
+    /// (`Reconnecting`), give up (`Failed`, once the circuit breaker opens) or recover
+    /// (`Connected`). Applies to this manager and every clone sharing its circuit breaker,
+    /// so register after `with_circuit_breaker`.
+    ///
+    /// The connection reconnects lazily, so events are only noticed when commands run; an
+    /// idle manager reports nothing until its next command.
+    pub fn on_connection_event<F>(&self, callback: F)
+    where
+        F: Fn(ConnectionEvent) + Send + Sync + 'static,
+    {
+        self.breaker.on_event(callback);
+    }
+
+    /// Turn caching off or back on without dropping the connection, e.g. from an admin
+    /// endpoint during an incident. Applies to this manager and every clone of it.
+    ///
+    /// While disabled, reads return misses and writes are skipped. Deletes, invalidations and
+    /// maintenance commands still run, so entries can be purged while the cache is bypassed.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        info!("Caching {} at runtime", if enabled { "enabled" } else { "disabled" });
+    }
+
+    /// Whether caching is switched on with `set_enabled` (it is by default)
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.conn.is_some()
+    }
+
+    /// Whether keys are redacted in this manager's log lines, see `log_key`
+    pub(crate) fn redacts_logs(&self) -> bool {
+        self.redact_logs
+    }
+
+    /// Close this manager's connections and refuse further use, for graceful shutdown.
+    ///
+    /// Applies to this manager and every clone of it: afterwards their Redis calls fail with
+    /// `CacheError::Closed`. Commands already in flight on clones finish normally, and the
+    /// underlying connection is released once the last clone holding it is dropped.
+    pub fn shutdown(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+        self.conn = None;
+        self.replicas.clear();
+        self.fallback = None;
+        self.invalidations = None;
+        info!("Cache manager shut down");
+    }
+
+    /// Switch to new ACL credentials at runtime, e.g. after the platform rotated them.
+    ///
+    /// A new connection is opened from the settings this manager was built with (`REDIS_URL`
+    /// for `with_connection` and `from_client`) with the credentials replaced, and checked
+    /// with `PING` before it replaces the current one, so bad credentials leave the manager
+    /// as it was. Commands already running on the old connection finish on it. Clones made
+    /// before the call keep the old connection, and replicas and the fallback are unchanged.
+    pub async fn update_credentials(&mut self, username: &str, password: &str) -> AnyResult<()> {
+        self.ensure_open()?;
+        let config = self.config.with_credentials(username, password)?;
+        let mut conn = config.connect().await?;
+        tokio::time::timeout(PING_TIMEOUT, redis::cmd("PING").query_async::<_, String>(&mut conn))
+            .await
+            .map_err(|_| anyhow::anyhow!("Timed out waiting for PING reply after {}ms", PING_TIMEOUT.as_millis()))??;
+
+        self.conn = Some(conn);
+        self.config = Arc::new(config);
+        info!("Redis credentials updated, now connected as {}", if username.is_empty() { "default" } else { username });
+        Ok(())
+    }
+
+    fn ensure_open(&self) -> Result<(), CacheError> {
+        if self.closed.load(Ordering::Relaxed) {
+            return Err(CacheError::Closed);
+        }
+        Ok(())
+    }
+
+    /// Send a `PING` and check for `PONG`.
+    ///
+    /// Returns `Ok(false)` when Redis is not configured and an error if Redis does not
+    /// answer within a short timeout, so a dead connection is caught even when
+    /// `is_available` still reports true.
+    pub async fn ping(&mut self) -> AnyResult<bool> {
+        self.ensure_open()?;
+        if let Some(ref mut conn) = self.conn {
+            let reply: String = tokio::time::timeout(PING_TIMEOUT, redis::cmd("PING").query_async(conn))
+                .await
+                .map_err(|_| anyhow::anyhow!("Timed out waiting for PING reply after {}ms", PING_TIMEOUT.as_millis()))??;
+            Ok(reply == "PONG")
         } else {
-            let mut stats = HashMap::new();
-            stats.insert("status".to_string(), "Redis not available".to_string());
-            Ok(stats)
+            Ok(false)
+        }
+    }
+
+    /// Check Redis health, measuring the PING round trip
+    pub async fn health(&mut self) -> HealthStatus {
+        let start = Instant::now();
+        match self.ping().await {
+            Ok(true) => HealthStatus {
+                healthy: true,
+                latency: Some(start.elapsed()),
+                error: None,
+            },
+            Ok(false) => HealthStatus {
+                healthy: false,
+                latency: None,
+                error: Some("Redis not available".to_string()),
+            },
+            Err(e) => HealthStatus {
+                healthy: false,
+                latency: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+
+    /// Snapshot of hit, miss, error, set and delete counters since creation or the last reset.
+    ///
+    /// Redis errors and corrupted entries count as errors rather than misses. Counters are
+    /// shared with every clone of this manager.
+    pub fn metrics(&self) -> CacheMetrics {
+        self.counters.snapshot()
+    }
+
+    /// Reset all metrics counters to zero
+    pub fn reset_metrics(&self) {
+        self.counters.reset();
+    }
+
+    /// Generate a cache key from request data.
+    ///
+    /// Requests whose serialized form is no longer than `CACHE_KEY_HASH_THRESHOLD` bytes and
+    /// contains no whitespace, control or glob characters are embedded as-is so keys stay
+    /// readable in `redis-cli`. Everything else is SHA256 hashed (the default, as the
+    /// threshold defaults to 0).
+    ///
+    /// Keys are only as stable as the serialized form. A `HashMap` serializes in its
+    /// iteration order, which differs between instances and even between maps with the same
+    /// contents, so equal requests can produce different keys and miss. Use a `BTreeMap` or
+    /// `generate_cache_key_from_parts` for such requests.
+    pub fn generate_cache_key<T: Serialize>(prefix: &str, request_data: &T) -> AnyResult<String> {
+        build_cache_key(prefix, request_data, get_key_hash_threshold())
+    }
+
+    /// Generate a cache key by hashing an explicit, ordered list of components.
+    ///
+    /// The request type needs no `Serialize`, and the caller decides the order, e.g. by
+    /// sorting map entries first. Each part is length-prefixed before hashing, so
+    /// `["a:b"]` and `["a", "b"]` give different keys.
+    pub fn generate_cache_key_from_parts(prefix: &str, parts: &[&str]) -> String {
+        let mut hasher = Sha256::new();
+        for part in parts {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        format!("{}:{}", prefix, hex::encode(hasher.finalize()))
+    }
+
+    /// `generate_cache_key` using this manager's hash threshold
+    pub(crate) fn cache_key_for<T: Serialize>(&self, prefix: &str, request_data: &T) -> AnyResult<String> {
+        build_cache_key(prefix, request_data, self.key_hash_threshold)
+    }
+
+    /// `cache_key_for`, or `None` when key generation failed and the manager is set to skip
+    /// the cache in that case
+    fn cache_key_or_skip<T: Serialize>(&self, prefix: &str, request_data: &T) -> AnyResult<Option<String>> {
+        match self.cache_key_for(prefix, request_data) {
+            Ok(cache_key) => Ok(Some(cache_key)),
+            Err(e) if self.skip_cache_on_key_error => {
+                warn!("Failed to generate cache key for prefix {}, bypassing cache: {}", prefix, e);
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Run a read-only command on the next replica in round-robin order.
+    ///
+    /// Replicas that fail with a connection error are skipped, and the primary is used (with
+    /// retries) when no replicas are configured or all of them are down. If the primary is
+    /// unreachable too, the command is tried once against the fallback.
+    async fn query_read<V: redis::FromRedisValue>(&mut self, cmd: &redis::Cmd) -> redis::RedisResult<V> {
+        for _ in 0..self.replicas.len() {
+            let index = self.next_replica;
+            self.next_replica = (index + 1) % self.replicas.len();
+            match cmd.query_async(&mut self.replicas[index]).await {
+                Err(e) if RetryPolicy::is_retryable(&e) => {
+                    warn!("Replica {} unavailable, trying next: {}", index, e);
+                }
+                result => return result,
+            }
+        }
+
+        let result = match self.conn {
+            Some(ref mut conn) if self.breaker.allow() => {
+                let result = self.retry.query(cmd, conn).await;
+                self.breaker.record(&result);
+                result
+            }
+            Some(_) => Err(CircuitBreaker::open_error()),
+            None => return Err(redis::RedisError::from((redis::ErrorKind::IoError, "Redis not available"))),
+        };
+        match (result, self.fallback.as_mut()) {
+            (Err(e), Some(fallback)) if RetryPolicy::is_retryable(&e) => {
+                warn!("Primary Redis unavailable, reading from fallback: {}", e);
+                cmd.query_async(fallback).await
+            }
+            (result, _) => result,
+        }
+    }
+
+    /// Replay a successful write on the fallback in the background, when mirroring is on.
+    /// Failures are logged and never reach the caller.
+    fn mirror_write(&self, cmd: &redis::Cmd) {
+        if let (true, Some(fallback)) = (self.mirror_writes, self.fallback.as_ref()) {
+            let mut fallback = fallback.clone();
+            let cmd = cmd.clone();
+            tokio::spawn(async move {
+                if let Err(e) = cmd.query_async::<_, redis::Value>(&mut fallback).await {
+                    warn!("Failed to mirror write to fallback Redis: {}", e);
+                }
+            });
+        }
+    }
+
+    /// Pipelined counterpart of `mirror_write`
+    fn mirror_pipeline(&self, pipe: &redis::Pipeline) {
+        if let (true, Some(fallback)) = (self.mirror_writes, self.fallback.as_ref()) {
+            let mut fallback = fallback.clone();
+            let pipe = pipe.clone();
+            tokio::spawn(async move {
+                if let Err(e) = pipe.query_async::<_, redis::Value>(&mut fallback).await {
+                    warn!("Failed to mirror writes to fallback Redis: {}", e);
+                }
+            });
+        }
+    }
+
+    /// Remove an entry that could not be decoded. Always runs against the primary.
+    async fn remove_corrupted(&mut self, key: &str) {
+        if let Some(ref mut conn) = self.conn {
+            if self.breaker.allow() {
+                let removed: redis::RedisResult<()> = conn.del(key).await;
+                self.breaker.record(&removed);
+            }
+        }
+    }
+
+    /// Get cached response by key.
+    ///
+    /// Returns `Ok(None)` on a miss or when Redis is not configured. A corrupted entry is
+    /// removed and reported as `CacheError::Serialization`. Reads go to a replica when
+    /// `REDIS_REPLICA_URLS` is set, so a read right after a write may still miss until the
+    /// replica catches up.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        name = "cache.get",
+        skip_all,
+        fields(redis.key = %key, redis.operation = "GET", cache.result, redis.latency_ms)
+    ))]
+    pub async fn get<T>(&mut self, key: &str) -> Result<Option<CachedResponse<T>>, CacheError>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        self.ensure_open()?;
+        let key = namespaced_key(&self.namespace, key);
+        let key = key.as_str();
+        if !self.is_enabled() {
+            self.counters.record_miss("get");
+            record_result("miss");
+            debug!("Caching disabled, returning cache miss for key: {}", log_key(key, self.redact_logs));
+            return Ok(None);
+        }
+        if self.is_available() {
+            if let Some(cached_data) = self.local.as_ref().and_then(|local| local.get(key)) {
+                if let Ok(response) = self.encoding.decode::<CachedResponse<T>>(&cached_data) {
+                    self.counters.record_hit("get");
+                    record_result("hit");
+                    debug!("Cache L1 HIT for key: {}", log_key(key, self.redact_logs));
+                    return Ok(Some(response));
+                }
+            }
+
+            let start = Instant::now();
+            let result = self.query_read::<Option<Vec<u8>>>(redis::cmd("GET").arg(key)).await;
+            record_latency(start);
+            record_duration("get", start);
+            match result {
+                Ok(Some(cached_data)) => {
+                    debug!("Cache HIT for key: {}", log_key(key, self.redact_logs));
+                    match self.encoding.decode::<CachedResponse<T>>(&cached_data) {
+                        Ok(response) => {
+                            self.counters.record_hit("get");
+                            record_result("hit");
+                            if let Some(local) = &self.local {
+                                local.insert(key, cached_data, 0);
+                            }
+                            Ok(Some(response))
+                        }
+                        // Fail closed: an entry we cannot decrypt is a miss, and is left in
+                        // place in case another instance holds the right key
+                        Err(e) if e.is::<DecryptionError>() => {
+                            self.counters.record_miss("get");
+                            record_result("miss");
+                            error!("Failed to decrypt cached data for key {}, treating as miss", log_key(key, self.redact_logs));
+                            Ok(None)
+                        }
+                        Err(e) => {
+                            self.counters.record_error("get");
+                            record_result("error");
+                            error!("Failed to deserialize cached data for key {}: {}", log_key(key, self.redact_logs), log_error(&e, self.redact_logs));
+                            self.remove_corrupted(key).await;
+                            Err(CacheError::Serialization(e))
+                        }
+                    }
+                }
+                Ok(None) => {
+                    self.counters.record_miss("get");
+                    record_result("miss");
+                    debug!("Cache MISS for key: {}", log_key(key, self.redact_logs));
+                    Ok(None)
+                }
+                Err(e) if CircuitBreaker::is_open_error(&e) => {
+                    self.counters.record_miss("get");
+                    record_result("miss");
+                    debug!("Circuit breaker open, returning cache miss for key: {}", log_key(key, self.redact_logs));
+                    Ok(None)
+                }
+                Err(e) => {
+                    self.counters.record_error("get");
+                    record_result("error");
+                    error!("Redis error while getting key {}: {}", log_key(key, self.redact_logs), e);
+                    Err(e.into())
+                }
+            }
+        } else {
+            self.counters.record_miss("get");
+            record_result("miss");
+            debug!("Redis not available, returning cache miss for key: {}", log_key(key, self.redact_logs));
+            Ok(None)
+        }
+    }
+
+    /// Get cached response by key along with its freshness relative to `max_age`.
+    ///
+    /// Returns `CacheState::Miss` with no value on a miss, otherwise `Fresh` or `Stale`
+    /// with the entry. Callers can serve a stale value immediately and refresh it in the
+    /// background.
+    pub async fn get_with_state<T>(&mut self, key: &str, max_age: Duration) -> Result<(CacheState, Option<CachedResponse<T>>), CacheError>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        match self.get(key).await? {
+            Some(response) => {
+                let state = response.state_at(max_age, chrono::Utc::now());
+                if state == CacheState::Stale {
+                    debug!("Cache entry for key {} is older than {:?}", log_key(key, self.redact_logs), max_age);
+                }
+                Ok((state, Some(response)))
+            }
+            None => Ok((CacheState::Miss, None)),
+        }
+    }
+
+    /// Get multiple cached responses in a single MGET round trip.
+    ///
+    /// The output preserves the order of `keys`. Missing entries map to `None`, and
+    /// corrupted entries map to `None` and are removed without failing the whole batch.
+    /// A Redis failure fails the whole call, as with `get`. Like `get`, reads go to a
+    /// replica when one is configured.
+    pub async fn get_many<T>(&mut self, keys: &[&str]) -> Result<Vec<Option<CachedResponse<T>>>, CacheError>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        self.ensure_open()?;
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        if !self.is_enabled() {
+            keys.iter().for_each(|_| self.counters.record_miss("get_many"));
+            debug!("Caching disabled, returning cache miss for {} keys", keys.len());
+            return Ok(keys.iter().map(|_| None).collect());
+        }
+
+        let keys: Vec<String> = keys.iter().map(|key| namespaced_key(&self.namespace, key)).collect();
+        if self.is_available() {
+            let start = Instant::now();
+            let result = self.query_read(redis::cmd("MGET").arg(&keys)).await;
+            record_duration("get_many", start);
+            let values: Vec<Option<Vec<u8>>> = match result {
+                Ok(values) => values,
+                Err(e) if CircuitBreaker::is_open_error(&e) => {
+                    debug!("Circuit breaker open, returning cache miss for {} keys", keys.len());
+                    return Ok(keys.iter().map(|_| None).collect());
+                }
+                Err(e) => {
+                    self.counters.record_error("get_many");
+                    error!("Redis error while getting {} keys: {}", keys.len(), e);
+                    return Err(e.into());
+                }
+            };
+
+            let mut results = Vec::with_capacity(keys.len());
+            for (key, value) in keys.iter().zip(values) {
+                match value {
+                    Some(cached_data) => {
+                        debug!("Cache HIT for key: {}", log_key(key, self.redact_logs));
+                        match self.encoding.decode::<CachedResponse<T>>(&cached_data) {
+                            Ok(response) => {
+                                self.counters.record_hit("get_many");
+                                results.push(Some(response));
+                            }
+                            Err(e) if e.is::<DecryptionError>() => {
+                                self.counters.record_miss("get_many");
+                                error!("Failed to decrypt cached data for key {}, treating as miss", log_key(key, self.redact_logs));
+                                results.push(None);
+                            }
+                            Err(e) => {
+                                self.counters.record_error("get_many");
+                                error!("Failed to deserialize cached data for key {}: {}", log_key(key, self.redact_logs), log_error(&e, self.redact_logs));
+                                self.remove_corrupted(key).await;
+                                results.push(None);
+                            }
+                        }
+                    }
+                    None => {
+                        self.counters.record_miss("get_many");
+                        debug!("Cache MISS for key: {}", log_key(key, self.redact_logs));
+                        results.push(None);
+                    }
+                }
+            }
+
+            Ok(results)
+        } else {
+            for _ in &keys {
+                self.counters.record_miss("get_many");
+            }
+            debug!("Redis not available, returning cache miss for {} keys", keys.len());
+            Ok(keys.iter().map(|_| None).collect())
+        }
+    }
+
+    /// Set cached response with the default TTL
+    pub async fn set<T>(&mut self, key: &str, data: &CachedResponse<T>) -> Result<bool, CacheError>
+    where
+        T: Serialize,
+    {
+        self.set_with_ttl(key, data, self.ttl_seconds).await
+    }
+
+    /// Set cached response with an explicit TTL. A `ttl_seconds` of 0 stores the entry without expiry.
+    ///
+    /// Returns `Ok(false)` when Redis is not configured.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        name = "cache.set",
+        skip_all,
+        fields(redis.key = %key, redis.operation = "SET", cache.ttl_seconds = ttl_seconds, redis.latency_ms)
+    ))]
+    pub async fn set_with_ttl<T>(&mut self, key: &str, data: &CachedResponse<T>, ttl_seconds: u64) -> Result<bool, CacheError>
+    where
+        T: Serialize,
+    {
+        self.ensure_open()?;
+        let key = namespaced_key(&self.namespace, key);
+        let key = key.as_str();
+        if !self.is_enabled() {
+            debug!("Caching disabled, skipping cache set for key: {}", log_key(key, self.redact_logs));
+            return Ok(false);
+        }
+        if let Some(ref mut conn) = self.conn {
+            let serialized = self.encoding.encode(data).map_err(CacheError::Serialization)?;
+            if self.max_value_bytes > 0 && serialized.len() > self.max_value_bytes {
+                return self.reject_oversized(key, serialized.len());
+            }
+            if !self.breaker.allow() {
+                debug!("Circuit breaker open, skipping cache set for key: {}", log_key(key, self.redact_logs));
+                return Ok(false);
+            }
+            let local_copy = self.local.as_ref().map(|_| serialized.clone());
+            let size = serialized.len();
+            let start = Instant::now();
+            let mut cmd = redis::cmd("SET");
+            cmd.arg(key).arg(serialized);
+            if ttl_seconds > 0 {
+                cmd.arg("EX").arg(ttl_seconds);
+            }
+            let result: redis::RedisResult<()> = self.retry.query(&cmd, conn).await;
+            record_latency(start);
+            record_duration("set_with_ttl", start);
+            self.breaker.record(&result);
+            if let (Some(local), Some(value)) = (&self.local, local_copy) {
+                match result {
+                    Ok(_) => local.insert(key, value, ttl_seconds),
+                    Err(_) => local.remove(key),
+                }
+            }
+            
+            match result {
+                Ok(_) => {
+                    self.counters.record_sets(1);
+                    self.mirror_write(&cmd);
+                    self.audit("set", key, size).await;
+                    debug!("Cache SET for key: {} with TTL: {}s", log_key(key, self.redact_logs), ttl_seconds);
+                    Ok(true)
+                }
+                Err(e) => {
+                    self.counters.record_error("set_with_ttl");
+                    error!("Failed to set cache for key {}: {}", log_key(key, self.redact_logs), e);
+                    Err(e.into())
+                }
+            }
+        } else {
+            debug!("Redis not available, skipping cache set for key: {}", log_key(key, self.redact_logs));
+            Ok(false)
+        }
+    }
+
+    /// Write `data` only if the entry is still at `expected_version`, for optimistic
+    /// concurrency between handlers refreshing the same key.
+    ///
+    /// Read the entry with `get`, then pass its `version` (0 when it is missing or was never
+    /// versioned). On success the entry is stored with the default TTL at `expected_version + 1`
+    /// and returned. If another writer got there first, nothing is written and
+    /// `CacheError::VersionConflict` carries the version now stored, so the caller can re-read
+    /// and retry. The version is stored in a header on the value itself, so any other write
+    /// to the key, such as a plain `set`, resets it to 0.
+    ///
+    /// Like `set`, the write is skipped with `Ok(None)` when caching is disabled, Redis is
+    /// unavailable, the circuit breaker is open, or the value is oversized under
+    /// `OversizePolicy::Skip`.
+    pub async fn set_if_version<T>(
+        &mut self,
+        key: &str,
+        mut data: CachedResponse<T>,
+        expected_version: u64,
+    ) -> Result<Option<CachedResponse<T>>, CacheError>
+    where
+        T: Serialize,
+    {
+        self.ensure_open()?;
+        let key = namespaced_key(&self.namespace, key);
+        let key = key.as_str();
+        if !self.is_enabled() {
+            debug!("Caching disabled, skipping cache set for key: {}", log_key(key, self.redact_logs));
+            return Ok(None);
+        }
+        self.forget_local(&[key]);
+        if let Some(ref mut conn) = self.conn {
+            data.version = Some(expected_version + 1);
+            let serialized = self.encoding.encode_versioned(&data, expected_version + 1).map_err(CacheError::Serialization)?;
+            if self.max_value_bytes > 0 && serialized.len() > self.max_value_bytes {
+                return self.reject_oversized(key, serialized.len()).map(|_| None);
+            }
+            if !self.breaker.allow() {
+                debug!("Circuit breaker open, skipping cache set for key: {}", log_key(key, self.redact_logs));
+                return Ok(None);
+            }
+            let size = serialized.len();
+            let mut mirror = redis::cmd("SET");
+            mirror.arg(key).arg(&serialized);
+            if self.ttl_seconds > 0 {
+                mirror.arg("EX").arg(self.ttl_seconds);
+            }
+            // Values without the version header (missing, or written by a plain set) are at
+            // version 0.
+            let mut cmd = redis::cmd("EVAL");
+            cmd.arg(
+                r"
+                local stored = redis.call('GET', KEYS[1])
+                local current = 0
+                if stored and #stored > 8 and string.byte(stored, 1) == 202 then
+                    for i = 2, 9 do
+                        current = current * 256 + string.byte(stored, i)
+                    end
+                end
+                if current ~= tonumber(ARGV[1]) then
+                    return {0, current}
+                end
+                local ttl = tonumber(ARGV[3])
+                if ttl > 0 then
+                    redis.call('SET', KEYS[1], ARGV[2], 'EX', ttl)
+                else
+                    redis.call('SET', KEYS[1], ARGV[2])
+                end
+                return {1, current + 1}
+                ",
+            )
+            .arg(1)
+            .arg(key)
+            .arg(expected_version)
+            .arg(serialized)
+            .arg(self.ttl_seconds);
+            let start = Instant::now();
+            // Not retried: if the reply is lost after the script ran, a second attempt would
+            // report a conflict with our own write.
+            let result: redis::RedisResult<(u8, u64)> = cmd.query_async(conn).await;
+            record_latency(start);
+            record_duration("set_if_version", start);
+            self.breaker.record(&result);
+
+            match result {
+                Ok((0, version)) => {
+                    debug!("Version conflict for key {}: expected {}, found {}", log_key(key, self.redact_logs), expected_version, version);
+                    Err(CacheError::VersionConflict { expected: expected_version, actual: version })
+                }
+                Ok((_, version)) => {
+                    self.counters.record_sets(1);
+                    self.mirror_write(&mirror);
+                    self.audit("set", key, size).await;
+                    debug!("Cache SET for key: {} at version {}", log_key(key, self.redact_logs), version);
+                    Ok(Some(data))
+                }
+                Err(e) => {
+                    self.counters.record_error("set_if_version");
+                    error!("Failed to set cache for key {}: {}", log_key(key, self.redact_logs), e);
+                    Err(e.into())
+                }
+            }
+        } else {
+            debug!("Redis not available, skipping cache set for key: {}", log_key(key, self.redact_logs));
+            Ok(None)
+        }
+    }
+
+    /// Store raw bytes under `key` without `CachedResponse`, serde or compression, e.g. for
+    /// rendered images or protobuf blobs. Encrypted when encryption is configured. A
+    /// `ttl_seconds` of 0 stores the value without expiry. Returns `Ok(false)` when Redis is
+    /// not configured.
+    pub async fn set_bytes(&mut self, key: &str, value: &[u8], ttl_seconds: u64) -> Result<bool, CacheError> {
+        self.ensure_open()?;
+        let key = namespaced_key(&self.namespace, key);
+        let key = key.as_str();
+        if !self.is_enabled() {
+            debug!("Caching disabled, skipping cache set for key: {}", log_key(key, self.redact_logs));
+            return Ok(false);
+        }
+        self.forget_local(&[key]);
+        if let Some(ref mut conn) = self.conn {
+            let stored = match &self.encoding.encryption {
+                Some(encryption) => encryption.encrypt(value).map_err(CacheError::Serialization)?,
+                None => value.to_vec(),
+            };
+            let size = stored.len();
+            if self.max_value_bytes > 0 && size > self.max_value_bytes {
+                return self.reject_oversized(key, size);
+            }
+            if !self.breaker.allow() {
+                debug!("Circuit breaker open, skipping cache set for key: {}", log_key(key, self.redact_logs));
+                return Ok(false);
+            }
+            let mut cmd = redis::cmd("SET");
+            cmd.arg(key).arg(stored);
+            if ttl_seconds > 0 {
+                cmd.arg("EX").arg(ttl_seconds);
+            }
+            let result: redis::RedisResult<()> = self.retry.query(&cmd, conn).await;
+            self.breaker.record(&result);
+            match result {
+                Ok(_) => {
+                    self.counters.record_sets(1);
+                    self.mirror_write(&cmd);
+                    self.audit("set", key, size).await;
+                    debug!("Cache SET of {} raw bytes for key: {}", value.len(), log_key(key, self.redact_logs));
+                    Ok(true)
+                }
+                Err(e) => {
+                    self.counters.record_error("set_bytes");
+                    error!("Failed to set raw cache value for key {}: {}", log_key(key, self.redact_logs), e);
+                    Err(e.into())
+                }
+            }
+        } else {
+            debug!("Redis not available, skipping cache set for key: {}", log_key(key, self.redact_logs));
+            Ok(false)
+        }
+    }
+
+    /// Read bytes written with `set_bytes`, exactly as they were passed in. As with `get`, an
+    /// entry that cannot be decrypted is reported as a miss.
+    pub async fn get_bytes(&mut self, key: &str) -> AnyResult<Option<Vec<u8>>> {
+        self.ensure_open()?;
+        let key = namespaced_key(&self.namespace, key);
+        if !self.is_enabled() || !self.is_available() {
+            self.counters.record_miss("get_bytes");
+            debug!("Cache unavailable or disabled, returning cache miss for key: {}", log_key(&key, self.redact_logs));
+            return Ok(None);
+        }
+
+        let start = Instant::now();
+        let result = self.query_read::<Option<Vec<u8>>>(redis::cmd("GET").arg(&key)).await;
+        record_duration("get_bytes", start);
+        let stored = match result {
+            Ok(stored) => stored,
+            Err(e) if CircuitBreaker::is_open_error(&e) => None,
+            Err(e) => {
+                self.counters.record_error("get_bytes");
+                return Err(e.into());
+            }
+        };
+        let stored = match (stored, &self.encoding.encryption) {
+            (Some(stored), Some(encryption)) => match encryption.decrypt(&stored) {
+                Ok(plaintext) => Some(plaintext),
+                // Fail closed like `get`, leaving the entry for an instance holding the right key
+                Err(e) if e.is::<DecryptionError>() => {
+                    error!("Failed to decrypt raw cache value for key {}, treating as miss", log_key(&key, self.redact_logs));
+                    None
+                }
+                Err(e) => {
+                    self.counters.record_error("get_bytes");
+                    return Err(e);
+                }
+            },
+            (stored, _) => stored,
+        };
+        match stored {
+            Some(stored) => {
+                self.counters.record_hit("get_bytes");
+                Ok(Some(stored))
+            }
+            None => {
+                self.counters.record_miss("get_bytes");
+                Ok(None)
+            }
+        }
+    }
+
+    /// Set multiple cached responses in a single pipelined round trip.
+    ///
+    /// Entries that fail to serialize are skipped without aborting the rest. An oversized
+    /// entry is skipped under `OversizePolicy::Skip`, and under `OversizePolicy::Reject` fails
+    /// the whole batch with `CacheError::ValueTooLarge` before anything is written. A
+    /// `ttl_seconds` of 0 stores entries without expiry. Returns the number of entries stored,
+    /// or the error if Redis fails.
+    pub async fn set_many<T>(&mut self, entries: &[(&str, &CachedResponse<T>)], ttl_seconds: u64) -> Result<usize, CacheError>
+    where
+        T: Serialize,
+    {
+        self.ensure_open()?;
+        if !self.is_enabled() {
+            debug!("Caching disabled, skipping cache set for {} keys", entries.len());
+            return Ok(0);
+        }
+        let written: Vec<String> = entries.iter().map(|(key, _)| namespaced_key(&self.namespace, key)).collect();
+        self.forget_local(&written);
+        if !self.is_available() {
+            debug!("Redis not available, skipping cache set for {} keys", entries.len());
+            return Ok(0);
+        }
+
+        let mut pipe = redis::pipe();
+        let mut queued = Vec::new();
+        for (key, data) in entries {
+            match self.encoding.encode(data) {
+                Ok(serialized) if self.max_value_bytes > 0 && serialized.len() > self.max_value_bytes => {
+                    // Under `OversizePolicy::Reject` the whole batch fails before anything is sent
+                    self.reject_oversized(key, serialized.len())?;
+                }
+                Ok(serialized) => {
+                    let key = namespaced_key(&self.namespace, key);
+                    queued.push((key.clone(), serialized.len()));
+                    if ttl_seconds == 0 {
+                        pipe.set(key, serialized);
+                    } else {
+                        pipe.set_ex(key, serialized, ttl_seconds as usize);
+                    }
+                }
+                Err(e) => {
+                    self.counters.record_error("set_many");
+                    error!("Failed to serialize cache data for key {}: {}", log_key(key, self.redact_logs), e);
+                }
+            }
+        }
+
+        if queued.is_empty() {
+            return Ok(0);
+        }
+        if !self.breaker.allow() {
+            debug!("Circuit breaker open, skipping cache set for {} keys", queued.len());
+            return Ok(0);
+        }
+        let Some(ref mut conn) = self.conn else {
+            return Ok(0);
+        };
+
+        let result = self.retry.query_pipeline::<_, Vec<redis::Value>>(&pipe, conn).await;
+        self.breaker.record(&result);
+        match result {
+            Ok(results) => {
+                let stored = results.iter().filter(|v| matches!(v, redis::Value::Okay)).count();
+                self.counters.record_sets(stored as u64);
+                self.mirror_pipeline(&pipe);
+                self.audit_many("set", &queued).await;
+                debug!("Cache SET for {} of {} keys with TTL: {}s", stored, entries.len(), ttl_seconds);
+                Ok(stored)
+            }
+            Err(e) => {
+                self.counters.record_error("set_many");
+                error!("Failed to set cache for {} keys: {}", queued.len(), e);
+                Err(e.into())
+            }
         }
     }
-} 
\ No newline at end of file
+
+    /// Cache a response
+    pub async fn cache_response<T, R>(
+        &mut self,
+        cache_prefix: &str,
+        request_data: &R,
+        response_data: T,
+    ) -> AnyResult<CachedResponse<T>>
+    where
+        T: Serialize + Clone,
+        R: Serialize,
+    {
+        self.cache_response_with_ttl(cache_prefix, request_data, response_data, self.ttl_seconds).await
+    }
+
+    /// Cache a response with an explicit TTL. A `ttl_seconds` of 0 stores the entry without expiry.
+    ///
+    /// With `with_skip_on_key_error`, a request that cannot be keyed is returned uncached with
+    /// an empty `cache_key` instead of failing.
+    pub async fn cache_response_with_ttl<T, R>(
+        &mut self,
+        cache_prefix: &str,
+        request_data: &R,
+        response_data: T,
+        ttl_seconds: u64,
+    ) -> AnyResult<CachedResponse<T>>
+    where
+        T: Serialize + Clone,
+        R: Serialize,
+    {
+        let Some(cache_key) = self.cache_key_or_skip(cache_prefix, request_data)? else {
+            return Ok(CachedResponse::new(response_data, String::new()));
+        };
+        let cached_response = CachedResponse::new(response_data.clone(), cache_key.clone());
+        
+        // A failed write only loses the cache entry, so still hand the response back
+        match self.set_with_ttl(&cache_key, &cached_response, ttl_seconds).await {
+            Ok(true) => info!("Successfully cached response for key: {}", log_key(&cache_key, self.redact_logs)),
+            Ok(false) => {}
+            Err(e) => warn!("Failed to cache response for key {}: {}", log_key(&cache_key, self.redact_logs), e),
+        }
+        
+        Ok(cached_response)
+    }
+
+    /// Cache a response and record its key under each tag so `invalidate_tag` can drop it.
+    ///
+    /// Each tag set's TTL is refreshed to the entry's TTL on every tagged write, so a tag
+    /// that is never invalidated expires along with its newest entry.
+    pub async fn cache_response_tagged<T, R>(
+        &mut self,
+        cache_prefix: &str,
+        request_data: &R,
+        response_data: T,
+        tags: &[&str],
+    ) -> AnyResult<CachedResponse<T>>
+    where
+        T: Serialize + Clone,
+        R: Serialize,
+    {
+        let ttl_seconds = self.ttl_seconds;
+        let cached_response = self
+            .cache_response_with_ttl(cache_prefix, request_data, response_data, ttl_seconds)
+            .await?;
+
+        // An empty key means the cache was bypassed, so there is nothing to tag
+        if tags.is_empty() || cached_response.cache_key.is_empty() {
+            return Ok(cached_response);
+        }
+        let key = namespaced_key(&self.namespace, &cached_response.cache_key);
+        if let Some(ref mut conn) = self.conn {
+            let mut pipe = redis::pipe();
+            for tag in tags {
+                let set_key = namespaced_key(&self.namespace, &tag_set_key(tag));
+                pipe.sadd(&set_key, &key).ignore();
+                if ttl_seconds > 0 {
+                    pipe.expire(&set_key, ttl_seconds as usize).ignore();
+                }
+            }
+            if let Err(e) = self.retry.query_pipeline::<_, ()>(&pipe, conn).await {
+                self.counters.record_error("cache_response_tagged");
+                let tags: Vec<String> = tags.iter().map(|tag| log_key(tag, self.redact_logs)).collect();
+                warn!("Failed to tag cache key {} with {:?}: {}", log_key(&key, self.redact_logs), tags, e);
+            }
+        }
+
+        Ok(cached_response)
+    }
+
+    /// Delete every entry tagged with `tag`, then the tag set itself.
+    ///
+    /// The tag's members are read and the set removed in one `MULTI`/`EXEC`, so entries
+    /// tagged while invalidation runs start a fresh set instead of being lost. Returns the
+    /// number of entries deleted.
+    pub async fn invalidate_tag(&mut self, tag: &str) -> AnyResult<u32> {
+        self.ensure_open()?;
+        let set_key = namespaced_key(&self.namespace, &tag_set_key(tag));
+        if let Some(ref mut conn) = self.conn {
+            if !self.breaker.allow() {
+                debug!("Circuit breaker open, skipping invalidation of tag: {}", log_key(tag, self.redact_logs));
+                return Ok(0);
+            }
+            let read: redis::RedisResult<(Vec<String>, u32)> = redis::pipe()
+                .atomic()
+                .smembers(&set_key)
+                .del(&set_key)
+                .query_async(conn)
+                .await;
+            self.breaker.record(&read);
+            let (members, _) = read?;
+            if members.is_empty() {
+                debug!("No cache entries tagged {}", log_key(tag, self.redact_logs));
+                return Ok(0);
+            }
+            if let Some(local) = &self.local {
+                members.iter().for_each(|member| local.remove(member));
+            }
+
+            let deleted = self.retry.query::<_, u32>(redis::cmd("DEL").arg(&members), conn).await;
+            self.breaker.record(&deleted);
+            let deleted = deleted?;
+            self.counters.record_deletes(deleted as u64);
+            info!("Invalidated {} cache entries tagged {}", deleted, log_key(tag, self.redact_logs));
+            Ok(deleted)
+        } else {
+            debug!("Redis not available, skipping invalidation of tag: {}", log_key(tag, self.redact_logs));
+            Ok(0)
+        }
+    }
+
+    /// Get cached response. With `with_skip_on_key_error`, a request that cannot be keyed is
+    /// reported as a miss.
+    pub async fn get_cached_response<T, R>(
+        &mut self,
+        cache_prefix: &str,
+        request_data: &R,
+    ) -> AnyResult<Option<CachedResponse<T>>>
+    where
+        T: for<'de> Deserialize<'de>,
+        R: Serialize,
+    {
+        match self.cache_key_or_skip(cache_prefix, request_data)? {
+            Some(cache_key) => Ok(self.get(&cache_key).await?),
+            None => Ok(None),
+        }
+    }
+
+    /// Get a cached response, or compute and cache it on a miss.
+    ///
+    /// `compute` runs at most once per call. To avoid a stampede on a cold key, the first
+    /// caller to miss takes a short-lived Redis lock and computes, while concurrent callers
+    /// poll for its value for up to `CACHE_STAMPEDE_LOCK_MS`. If the value still has not
+    /// appeared by then (the holder failed or is slow), they compute it themselves.
+    pub async fn get_or_set<T, R, F, Fut>(
+        &mut self,
+        cache_prefix: &str,
+        request_data: &R,
+        compute: F,
+    ) -> AnyResult<CachedResponse<T>>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Clone,
+        R: Serialize,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = AnyResult<T>>,
+    {
+        match self.get_cached_response::<T, R>(cache_prefix, request_data).await {
+            Ok(Some(cached)) => return Ok(cached),
+            Ok(None) => {}
+            // Treat cache failures as a miss so the caller still gets a value
+            Err(e) => warn!("Cache lookup failed for prefix {}: {}", cache_prefix, e),
+        }
+
+        if self.stampede_lock_ttl.is_zero() || !self.is_available() || !self.is_enabled() {
+            let response_data = compute().await?;
+            return self.cache_response(cache_prefix, request_data, response_data).await;
+        }
+
+        let Some(cache_key) = self.cache_key_or_skip(cache_prefix, request_data)? else {
+            return Ok(CachedResponse::new(compute().await?, String::new()));
+        };
+        let lock_key = namespaced_key(&self.namespace, &format!("{}:lock", cache_key));
+        let token = uuid::Uuid::new_v4().to_string();
+        if self.acquire_lock(&lock_key, &token).await {
+            let computed = match compute().await {
+                Ok(response_data) => self.cache_response(cache_prefix, request_data, response_data).await,
+                Err(e) => Err(e),
+            };
+            self.release_lock(&lock_key, &token).await;
+            return computed;
+        }
+
+        debug!("Waiting for another caller to compute key: {}", log_key(&cache_key, self.redact_logs));
+        let deadline = Instant::now() + self.stampede_lock_ttl;
+        while Instant::now() < deadline {
+            tokio::time::sleep(STAMPEDE_POLL_INTERVAL).await;
+            if let Ok(Some(cached)) = self.get_cached_response::<T, R>(cache_prefix, request_data).await {
+                return Ok(cached);
+            }
+        }
+
+        warn!("Timed out waiting for key {} to be computed, computing it here", log_key(&cache_key, self.redact_logs));
+        let response_data = compute().await?;
+        self.cache_response(cache_prefix, request_data, response_data).await
+    }
+
+    /// Take the stampede lock for a key. Lock errors count as acquired, so a Redis problem
+    /// never stops the caller from computing.
+    async fn acquire_lock(&self, lock_key: &str, token: &str) -> bool {
+        let Some(conn) = self.conn.clone() else {
+            return true;
+        };
+        match set_nx_with_expiry(lock_key.to_string(), token.to_string(), self.stampede_lock_ttl, conn).await {
+            Ok(acquired) => acquired,
+            Err(e) => {
+                warn!("Failed to take lock {}: {}", log_key(lock_key, self.redact_logs), e);
+                true
+            }
+        }
+    }
+
+    /// Release the stampede lock if this caller still holds it
+    async fn release_lock(&mut self, lock_key: &str, token: &str) {
+        if let Some(ref mut conn) = self.conn {
+            let script = redis::Script::new(
+                r"
+                if redis.call('GET', KEYS[1]) == ARGV[1] then
+                    return redis.call('DEL', KEYS[1])
+                end
+                return 0
+                ",
+            );
+            let released: redis::RedisResult<u32> = script.key(lock_key).arg(token).invoke_async(conn).await;
+            if let Err(e) = released {
+                warn!("Failed to release lock {}: {}", log_key(lock_key, self.redact_logs), e);
+            }
+        }
+    }
+
+    /// Precompute and store entries for `requests`, e.g. to populate hot keys at startup.
+    ///
+    /// `loader` runs for at most `concurrency` requests at a time, and the results are written
+    /// in one `set_many` round trip with the default TTL, so an oversized result fails the
+    /// warm-up under `OversizePolicy::Reject`. Requests whose loader fails are logged and
+    /// skipped. Returns the number of entries stored.
+    pub async fn warm<T, R, F, Fut>(
+        &mut self,
+        cache_prefix: &str,
+        requests: Vec<R>,
+        concurrency: usize,
+        loader: F,
+    ) -> AnyResult<usize>
+    where
+        T: Serialize,
+        R: Serialize,
+        F: Fn(R) -> Fut,
+        Fut: Future<Output = AnyResult<T>>,
+    {
+        self.ensure_open()?;
+        if !self.is_available() {
+            debug!("Redis not available, skipping cache warm-up for prefix: {}", cache_prefix);
+            return Ok(0);
+        }
+
+        let mut keyed = Vec::with_capacity(requests.len());
+        for request in requests {
+            keyed.push((self.cache_key_for(cache_prefix, &request)?, request));
+        }
+
+        let redact_logs = self.redact_logs;
+        let loaded: Vec<(String, CachedResponse<T>)> = futures::stream::iter(keyed)
+            .map(|(cache_key, request)| {
+                let load = loader(request);
+                async move { (cache_key, load.await) }
+            })
+            .buffer_unordered(concurrency.max(1))
+            .filter_map(|(cache_key, result)| async move {
+                match result {
+                    Ok(data) => Some((cache_key.clone(), CachedResponse::new(data, cache_key))),
+                    Err(e) => {
+                        warn!("Failed to load cache entry for key {}: {}", log_key(&cache_key, redact_logs), e);
+                        None
+                    }
+                }
+            })
+            .collect()
+            .await;
+
+        let entries: Vec<(&str, &CachedResponse<T>)> = loaded.iter().map(|(key, data)| (key.as_str(), data)).collect();
+        let stored = self.set_many(&entries, self.ttl_seconds).await?;
+        info!("Warmed {} cache entries for prefix: {}", stored, cache_prefix);
+        Ok(stored)
+    }
+
+    /// Whether an entry exists, without fetching or decoding it
+    pub async fn exists(&mut self, key: &str) -> AnyResult<bool> {
+        self.ensure_open()?;
+        if !self.is_available() {
+            return Ok(false);
+        }
+        let key = namespaced_key(&self.namespace, key);
+        let count: u32 = self.query_read(redis::cmd("EXISTS").arg(&key)).await?;
+        Ok(count > 0)
+    }
+
+    /// Time left before an entry expires.
+    ///
+    /// Returns `None` when the key is absent and `Some(Duration::MAX)` when it exists
+    /// without an expiry.
+    pub async fn remaining_ttl(&mut self, key: &str) -> AnyResult<Option<Duration>> {
+        self.ensure_open()?;
+        if !self.is_available() {
+            return Ok(None);
+        }
+        let key = namespaced_key(&self.namespace, key);
+        let millis: i64 = self.query_read(redis::cmd("PTTL").arg(&key)).await?;
+        Ok(match millis {
+            -2 => None,
+            -1 => Some(Duration::MAX),
+            millis => Some(Duration::from_millis(millis.max(0) as u64)),
+        })
+    }
+
+    /// Make `key` expire `ttl` from now, to the millisecond, without rewriting its value. A
+    /// zero `ttl` deletes the key. Returns `false` when the key does not exist.
+    pub async fn expire(&mut self, key: &str, ttl: Duration) -> AnyResult<bool> {
+        let key = namespaced_key(&self.namespace, key);
+        // `PEXPIRE` so a sub-second TTL is not rounded down to an immediate delete
+        let millis = usize::try_from(ttl.as_millis()).unwrap_or(usize::MAX);
+        let cmd = redis::Cmd::pexpire(&key, millis);
+        self.update_expiry("expire", &key, &cmd).await
+    }
+
+    /// Make `key` expire at `unix_ts` seconds since the epoch. A time in the past deletes
+    /// the key. Returns `false` when the key does not exist.
+    pub async fn expire_at(&mut self, key: &str, unix_ts: u64) -> AnyResult<bool> {
+        let key = namespaced_key(&self.namespace, key);
+        let cmd = redis::Cmd::expire_at(&key, unix_ts as usize);
+        self.update_expiry("expire_at", &key, &cmd).await
+    }
+
+    /// Remove the expiry from `key` so it is kept until deleted. Returns `false` when the
+    /// key does not exist or already has no expiry.
+    pub async fn persist(&mut self, key: &str) -> AnyResult<bool> {
+        let key = namespaced_key(&self.namespace, key);
+        let cmd = redis::Cmd::persist(&key);
+        self.update_expiry("persist", &key, &cmd).await
+    }
+
+    /// Run an `EXPIRE`-family command on the primary and report whether it applied. The
+    /// local copy is dropped so the next read sees the new expiry.
+    async fn update_expiry(&mut self, operation: &'static str, key: &str, cmd: &redis::Cmd) -> AnyResult<bool> {
+        self.ensure_open()?;
+        // The local copy carries the old expiry
+        self.forget_local(&[key]);
+        let Some(ref mut conn) = self.conn else {
+            debug!("Redis not available, skipping cache {} for key: {}", operation, log_key(key, self.redact_logs));
+            return Ok(false);
+        };
+        if !self.breaker.allow() {
+            return Err(CircuitBreaker::open_error().into());
+        }
+        let result = self.retry.query::<_, bool>(cmd, conn).await;
+        self.breaker.record(&result);
+        match result {
+            Ok(updated) => {
+                self.mirror_write(cmd);
+                debug!("Cache {} for key {}: {}", operation, log_key(key, self.redact_logs), updated);
+                Ok(updated)
+            }
+            Err(e) => {
+                self.counters.record_error(operation);
+                error!("Failed to {} cache key {}: {}", operation, log_key(key, self.redact_logs), e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Delete cache entry by key. A Redis failure is returned as a `CacheError`.
+    pub async fn delete(&mut self, key: &str) -> AnyResult<bool> {
+        self.ensure_open()?;
+        let key = namespaced_key(&self.namespace, key);
+        let key = key.as_str();
+        self.forget_local(&[key]);
+        if let Some(ref mut conn) = self.conn {
+            if !self.breaker.allow() {
+                debug!("Circuit breaker open, skipping cache delete for key: {}", log_key(key, self.redact_logs));
+                return Ok(false);
+            }
+            let cmd = redis::Cmd::del(key);
+            let result = self.retry.query::<_, u32>(&cmd, conn).await;
+            self.breaker.record(&result);
+            match result {
+                Ok(deleted_count) => {
+                    self.counters.record_deletes(deleted_count as u64);
+                    self.mirror_write(&cmd);
+                    self.audit("delete", key, 0).await;
+                    debug!("Deleted {} cache entries for key: {}", deleted_count, log_key(key, self.redact_logs));
+                    Ok(deleted_count > 0)
+                }
+                Err(e) => {
+                    self.counters.record_error("delete");
+                    error!("Failed to delete cache for key {}: {}", log_key(key, self.redact_logs), e);
+                    Err(CacheError::from(e).into())
+                }
+            }
+        } else {
+            debug!("Redis not available, skipping cache delete for key: {}", log_key(key, self.redact_logs));
+            Ok(false)
+        }
+    }
+
+    /// Delete several entries in one round trip, returning how many existed.
+    ///
+    /// Uses `UNLINK` so large values are freed in the background, falling back to `DEL` on
+    /// servers older than 4.0. In cluster mode all keys must hash to the same slot.
+    pub async fn delete_many(&mut self, keys: &[&str]) -> AnyResult<u32> {
+        self.ensure_open()?;
+        if keys.is_empty() {
+            return Ok(0);
+        }
+        let keys: Vec<String> = keys.iter().map(|key| namespaced_key(&self.namespace, key)).collect();
+        self.forget_local(&keys);
+        if let Some(ref mut conn) = self.conn {
+            if !self.breaker.allow() {
+                debug!("Circuit breaker open, skipping cache delete for {} keys", keys.len());
+                return Ok(0);
+            }
+            let start = Instant::now();
+            let deleted = match self.retry.query::<_, u32>(redis::cmd("UNLINK").arg(&keys), conn).await {
+                Err(e) if is_unknown_command(&e) => self.retry.query::<_, u32>(&redis::Cmd::del(&keys), conn).await,
+                result => result,
+            };
+            record_duration("delete_many", start);
+            self.breaker.record(&deleted);
+            match deleted {
+                Ok(deleted_count) => {
+                    self.counters.record_deletes(deleted_count as u64);
+                    self.mirror_write(&redis::Cmd::del(&keys));
+                    let audited: Vec<(&String, usize)> = keys.iter().map(|key| (key, 0)).collect();
+                    self.audit_many("delete", &audited).await;
+                    debug!("Deleted {} of {} cache entries", deleted_count, keys.len());
+                    Ok(deleted_count)
+                }
+                Err(e) => {
+                    self.counters.record_error("delete_many");
+                    error!("Failed to delete {} cache keys: {}", keys.len(), e);
+                    Err(CacheError::from(e).into())
+                }
+            }
+        } else {
+            debug!("Redis not available, skipping cache delete for {} keys", keys.len());
+            Ok(0)
+        }
+    }
+
+    /// Delete a related set of entries all at once, returning how many existed.
+    ///
+    /// The deletes run in one Lua script, so no reader sees some of the keys gone and others
+    /// still present. In cluster mode all keys must hash to the same slot.
+    pub async fn invalidate_atomic(&mut self, keys: &[&str]) -> AnyResult<u32> {
+        self.ensure_open()?;
+        if keys.is_empty() {
+            return Ok(0);
+        }
+        let keys: Vec<String> = keys.iter().map(|key| namespaced_key(&self.namespace, key)).collect();
+        self.forget_local(&keys);
+        let conn = self.conn.as_mut().ok_or_else(|| anyhow::anyhow!("Redis not available"))?;
+        if !self.breaker.allow() {
+            return Err(CircuitBreaker::open_error().into());
+        }
+
+        // Deleting one key per call keeps clear of Lua's limit on unpacked arguments
+        let script = redis::Script::new(
+            r"
+            local deleted = 0
+            for _, key in ipairs(KEYS) do
+                deleted = deleted + redis.call('DEL', key)
+            end
+            return deleted
+            ",
+        );
+        let mut invocation = script.prepare_invoke();
+        for key in &keys {
+            invocation.key(key);
+        }
+        let result: redis::RedisResult<u32> = invocation.invoke_async(conn).await;
+        self.breaker.record(&result);
+        match result {
+            Ok(deleted_count) => {
+                self.counters.record_deletes(deleted_count as u64);
+                self.mirror_write(&redis::Cmd::del(&keys));
+                let audited: Vec<(&String, usize)> = keys.iter().map(|key| (key, 0)).collect();
+                self.audit_many("delete", &audited).await;
+                debug!("Atomically deleted {} of {} cache entries", deleted_count, keys.len());
+                Ok(deleted_count)
+            }
+            Err(e) => {
+                self.counters.record_error("invalidate_atomic");
+                error!("Failed to atomically delete {} cache keys: {}", keys.len(), e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Atomically move the entry at `from` to `to`, keeping its value and TTL.
+    ///
+    /// With `overwrite` an existing entry at `to` is replaced (`RENAME`); without it nothing
+    /// changes and `false` is returned when `to` already exists (`RENAMENX`). Fails with an
+    /// error if `from` does not exist. In cluster mode both keys must hash to the same slot.
+    pub async fn rename(&mut self, from: &str, to: &str, overwrite: bool) -> AnyResult<bool> {
+        self.ensure_open()?;
+        let from = namespaced_key(&self.namespace, from);
+        let to = namespaced_key(&self.namespace, to);
+        self.forget_local(&[&from, &to]);
+        let Some(ref mut conn) = self.conn else {
+            debug!("Redis not available, skipping rename of key: {}", log_key(&from, self.redact_logs));
+            return Ok(false);
+        };
+        if !self.breaker.allow() {
+            return Err(CircuitBreaker::open_error().into());
+        }
+
+        let mut cmd = redis::cmd(if overwrite { "RENAME" } else { "RENAMENX" });
+        cmd.arg(&from).arg(&to);
+        let result = self.retry.query::<_, bool>(&cmd, conn).await;
+        self.breaker.record(&result);
+        match result {
+            Ok(renamed) => {
+                if renamed {
+                    self.mirror_write(&cmd);
+                    self.audit_many("rename", &[(&from, 0), (&to, 0)]).await;
+                }
+                debug!("Renamed cache key {} to {}: {}", log_key(&from, self.redact_logs), log_key(&to, self.redact_logs), renamed);
+                Ok(renamed)
+            }
+            Err(e) => {
+                // A missing source is a plain `ERR` reply, so confirm it rather than parse the message
+                if e.code() == Some("ERR") {
+                    let exists = self.retry.query::<_, bool>(redis::cmd("EXISTS").arg(&from), conn).await;
+                    if let Ok(false) = exists {
+                        return Err(anyhow::anyhow!("Cannot rename {}: key does not exist", log_key(&from, self.redact_logs)));
+                    }
+                }
+                self.counters.record_error("rename");
+                error!("Failed to rename cache key {} to {}: {}", log_key(&from, self.redact_logs), log_key(&to, self.redact_logs), e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Duplicate the entry at `from` into `to` on the server, keeping its TTL (`COPY`,
+    /// Redis 6.2+).
+    ///
+    /// With `replace` an existing entry at `to` is overwritten; without it nothing changes.
+    /// Returns `false` when `from` does not exist or, without `replace`, when `to` already
+    /// does. In cluster mode both keys must hash to the same slot.
+    pub async fn copy(&mut self, from: &str, to: &str, replace: bool) -> AnyResult<bool> {
+        self.ensure_open()?;
+        let from = namespaced_key(&self.namespace, from);
+        let to = namespaced_key(&self.namespace, to);
+        self.forget_local(&[&to]);
+        if let Some(ref mut conn) = self.conn {
+            if !self.breaker.allow() {
+                return Err(CircuitBreaker::open_error().into());
+            }
+            let mut cmd = redis::cmd("COPY");
+            cmd.arg(&from).arg(&to);
+            if replace {
+                cmd.arg("REPLACE");
+            }
+            let result = self.retry.query::<_, bool>(&cmd, conn).await;
+            self.breaker.record(&result);
+            match result {
+                Ok(copied) => {
+                    if copied {
+                        self.mirror_write(&cmd);
+                        self.audit("copy", &to, 0).await;
+                    }
+                    debug!("Copied cache key {} to {}: {}", log_key(&from, self.redact_logs), log_key(&to, self.redact_logs), copied);
+                    Ok(copied)
+                }
+                Err(e) => {
+                    self.counters.record_error("copy");
+                    error!("Failed to copy cache key {} to {}: {}", log_key(&from, self.redact_logs), log_key(&to, self.redact_logs), e);
+                    Err(e.into())
+                }
+            }
+        } else {
+            debug!("Redis not available, skipping copy of key: {}", log_key(&from, self.redact_logs));
+            Ok(false)
+        }
+    }
+
+    /// Serialize the entry at `key` in Redis's native `DUMP` format, or `None` if it is absent.
+    ///
+    /// Pair with `remaining_ttl` and `restore` to move entries between instances with their
+    /// type and expiry intact.
+    pub async fn dump(&mut self, key: &str) -> AnyResult<Option<Vec<u8>>> {
+        self.ensure_open()?;
+        let key = namespaced_key(&self.namespace, key);
+        let Some(ref mut conn) = self.conn else {
+            return Err(anyhow::anyhow!("Redis not available"));
+        };
+        if !self.breaker.allow() {
+            return Err(CircuitBreaker::open_error().into());
+        }
+        let result = self.retry.query(redis::cmd("DUMP").arg(&key), conn).await;
+        self.breaker.record(&result);
+        Ok(result?)
+    }
+
+    /// Recreate an entry from `dump` output, expiring after `ttl` (`Duration::ZERO` for none).
+    ///
+    /// Returns `false` without changing anything when `key` exists and `replace` is false.
+    /// The payload must come from a compatible Redis version.
+    pub async fn restore(&mut self, key: &str, ttl: Duration, data: &[u8], replace: bool) -> AnyResult<bool> {
+        self.ensure_open()?;
+        let key = namespaced_key(&self.namespace, key);
+        self.forget_local(&[&key]);
+        let Some(ref mut conn) = self.conn else {
+            return Err(anyhow::anyhow!("Redis not available"));
+        };
+        if !self.breaker.allow() {
+            return Err(CircuitBreaker::open_error().into());
+        }
+
+        let mut cmd = redis::cmd("RESTORE");
+        cmd.arg(&key).arg(ttl.as_millis() as u64).arg(data);
+        if replace {
+            cmd.arg("REPLACE");
+        }
+        let result = self.retry.query::<_, ()>(&cmd, conn).await;
+        self.breaker.record(&result);
+        match result {
+            Ok(()) => {
+                self.mirror_write(&cmd);
+                self.audit("restore", &key, data.len()).await;
+                debug!("Restored cache key: {}", log_key(&key, self.redact_logs));
+                Ok(true)
+            }
+            Err(e) if e.code() == Some("BUSYKEY") => {
+                debug!("Not restoring key {}: it already exists", log_key(&key, self.redact_logs));
+                Ok(false)
+            }
+            Err(e) => {
+                self.counters.record_error("restore");
+                error!("Failed to restore cache key {}: {}", log_key(&key, self.redact_logs), e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Lazily iterate the keys matching `pattern` within the configured namespace.
+    ///
+    /// Drives a `SCAN` cursor with the `CACHE_SCAN_COUNT` hint, so keys are fetched a batch
+    /// at a time instead of all at once as with `KEYS`. Yielded keys have the namespace
+    /// stripped and can be passed straight back to `get` or `delete`. `SCAN` may return a
+    /// key more than once if the keyspace is resized mid-iteration. The stream ends after
+    /// the first error, which is `CacheError::Closed` once the manager is shut down and the
+    /// circuit breaker's open error while it fails fast.
+    pub fn scan_keys(&self, pattern: &str) -> impl Stream<Item = AnyResult<String>> {
+        let pattern = namespaced_key(&self.namespace, pattern);
+        let prefix = self.namespace.as_ref().map(|namespace| format!("{}:", namespace));
+        let count = self.scan_count;
+        let state = ScanState {
+            conn: self.conn.clone(),
+            closed: self.closed.clone(),
+            breaker: self.breaker.clone(),
+            retry: self.retry,
+            cursor: 0,
+            buffered: VecDeque::new(),
+            finished: false,
+        };
+
+        futures::stream::unfold(state, move |mut state| {
+            let pattern = pattern.clone();
+            let prefix = prefix.clone();
+            async move {
+                loop {
+                    if let Some(key) = state.buffered.pop_front() {
+                        let key = match &prefix {
+                            Some(prefix) => key.strip_prefix(prefix.as_str()).map(str::to_string).unwrap_or(key),
+                            None => key,
+                        };
+                        return Some((Ok(key), state));
+                    }
+                    if state.finished {
+                        return None;
+                    }
+                    if state.closed.load(Ordering::Relaxed) {
+                        state.finished = true;
+                        return Some((Err(CacheError::Closed.into()), state));
+                    }
+                    let conn = state.conn.as_mut()?;
+                    if !state.breaker.allow() {
+                        state.finished = true;
+                        return Some((Err(CircuitBreaker::open_error().into()), state));
+                    }
+                    let mut cmd = redis::cmd("SCAN");
+                    cmd.arg(state.cursor).arg("MATCH").arg(&pattern).arg("COUNT").arg(count);
+                    let scanned: redis::RedisResult<(u64, Vec<String>)> = state.retry.query(&cmd, conn).await;
+                    state.breaker.record(&scanned);
+                    match scanned {
+                        Ok((next_cursor, keys)) => {
+                            state.buffered.extend(keys);
+                            state.cursor = next_cursor;
+                            state.finished = next_cursor == 0;
+                        }
+                        Err(e) => {
+                            state.finished = true;
+                            return Some((Err(e.into()), state));
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    /// Count the keys matching `pattern` within the configured namespace.
+    ///
+    /// Counts while scanning rather than collecting keys, so memory stays flat however many
+    /// keys match. Since `SCAN` can return a key twice, treat the result as approximate.
+    pub async fn count_keys(&mut self, pattern: &str) -> AnyResult<u64> {
+        self.ensure_open()?;
+        let mut keys = Box::pin(self.scan_keys(pattern));
+        let mut count = 0;
+        while let Some(key) = keys.next().await {
+            key?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Give every key matching `pattern` that has no expiry a TTL of `ttl_seconds`.
+    ///
+    /// A maintenance helper for caches that accumulated keys written without a TTL. The
+    /// TTL check and `EXPIRE` run together in a script, so keys that gain an expiry while
+    /// the scan runs are left alone, and resending it after a lost reply is harmless. Like
+    /// `expire`, each key goes through the circuit breaker and retry policy, and the scan
+    /// stops at the first failure. Returns the number of keys given an expiry.
+    pub async fn expire_missing_ttl(&mut self, pattern: &str, ttl_seconds: u64) -> AnyResult<u32> {
+        self.ensure_open()?;
+        let script = r"
+            if redis.call('TTL', KEYS[1]) == -1 then
+                return redis.call('EXPIRE', KEYS[1], ARGV[1])
+            end
+            return 0
+            ";
+        let mut keys = Box::pin(self.scan_keys(pattern));
+        let mut fixed = 0;
+        while let Some(key) = keys.next().await {
+            let key = match key {
+                Ok(key) => namespaced_key(&self.namespace, &key),
+                Err(e) => {
+                    self.counters.record_error("expire_missing_ttl");
+                    error!("Failed to scan keys matching pattern {}: {}", log_key(pattern, self.redact_logs), e);
+                    return Err(e);
+                }
+            };
+            // The local copy carries the old expiry
+            self.forget_local(&[&key]);
+            let Some(ref mut conn) = self.conn else {
+                break;
+            };
+            if !self.breaker.allow() {
+                return Err(CircuitBreaker::open_error().into());
+            }
+            let mut cmd = redis::cmd("EVAL");
+            cmd.arg(script).arg(1).arg(&key).arg(ttl_seconds);
+            let result = self.retry.query::<_, u32>(&cmd, conn).await;
+            self.breaker.record(&result);
+            match result {
+                Ok(expired) => {
+                    if expired > 0 {
+                        self.mirror_write(&cmd);
+                    }
+                    fixed += expired;
+                }
+                Err(e) => {
+                    self.counters.record_error("expire_missing_ttl");
+                    error!("Failed to apply a TTL to cache key {}: {}", log_key(&key, self.redact_logs), e);
+                    return Err(e.into());
+                }
+            }
+        }
+        if fixed > 0 {
+            info!(
+                "Applied a {}s TTL to {} keys matching {} that had none",
+                ttl_seconds,
+                fixed,
+                log_key(pattern, self.redact_logs)
+            );
+        }
+        Ok(fixed)
+    }
+
+    /// Total number of keys in the current database, across all namespaces
+    pub async fn dbsize(&mut self) -> AnyResult<u64> {
+        self.ensure_open()?;
+        if !self.is_available() {
+            return Ok(0);
+        }
+        Ok(self.query_read(&redis::cmd("DBSIZE")).await?)
+    }
+
+    /// Block until `num_replicas` replicas have acknowledged every write made so far on this
+    /// manager's connection, or `timeout` passes. Returns how many acknowledged.
+    ///
+    /// Call it after a critical `set` before responding. An acknowledgment means the replica
+    /// received the write, not that it is persisted, so a write can still be lost if the
+    /// primary and the acknowledging replicas fail together. Redis reads a zero timeout as
+    /// "wait forever", which would stall every command sharing the connection, so a
+    /// `timeout` under 1ms is an error. Like other commands, `WAIT` is refused while the
+    /// circuit breaker is open.
+    pub async fn wait_for_replicas(&mut self, num_replicas: u32, timeout: Duration) -> AnyResult<u32> {
+        self.ensure_open()?;
+        if timeout < Duration::from_millis(1) {
+            return Err(anyhow::anyhow!("Replica wait timeout must be at least 1ms, got {:?}", timeout));
+        }
+        let conn = self.conn.as_mut().ok_or_else(|| anyhow::anyhow!("Redis not available"))?;
+        if !self.breaker.allow() {
+            return Err(CircuitBreaker::open_error().into());
+        }
+        let millis = u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX);
+        let acknowledged = redis::cmd("WAIT").arg(num_replicas).arg(millis).query_async(conn).await;
+        self.breaker.record(&acknowledged);
+        Ok(acknowledged?)
+    }
+
+    /// Clear cache entries matching a pattern, scoped to the configured namespace.
+    ///
+    /// Walks the keyspace with `SCAN` instead of `KEYS` so Redis is never blocked, and
+    /// removes each batch with `UNLINK`, falling back to `DEL` on servers without it.
+    pub async fn clear_pattern(&mut self, pattern: &str) -> AnyResult<u32> {
+        self.ensure_open()?;
+        let pattern = namespaced_key(&self.namespace, pattern);
+        let pattern = pattern.as_str();
+        if let Some(ref mut conn) = self.conn {
+            let count = self.scan_count;
+            let mut cursor: u64 = 0;
+            let mut deleted_count = 0;
+            
+            loop {
+                if !self.breaker.allow() {
+                    debug!("Circuit breaker open, stopping pattern clear for: {}", log_key(pattern, self.redact_logs));
+                    break;
+                }
+                let scanned = self
+                    .retry
+                    .query::<_, (u64, Vec<String>)>(redis::cmd("SCAN").arg(cursor).arg("MATCH").arg(pattern).arg("COUNT").arg(count), conn)
+                    .await;
+                self.breaker.record(&scanned);
+                let (next_cursor, keys) = match scanned {
+                    Ok(result) => result,
+                    Err(e) => {
+                        self.counters.record_error("clear_pattern");
+                        error!("Failed to scan keys matching pattern {}: {}", log_key(pattern, self.redact_logs), e);
+                        break;
+                    }
+                };
+                
+                if !keys.is_empty() {
+                    if let Some(local) = &self.local {
+                        keys.iter().for_each(|key| local.remove(key));
+                    }
+                    let unlinked = match self.retry.query::<_, u32>(redis::cmd("UNLINK").arg(&keys), conn).await {
+                        Err(e) if is_unknown_command(&e) => self.retry.query::<_, u32>(&redis::Cmd::del(&keys), conn).await,
+                        result => result,
+                    };
+                    self.breaker.record(&unlinked);
+                    match unlinked {
+                        Ok(count) => deleted_count += count,
+                        Err(e) => {
+                            self.counters.record_error("clear_pattern");
+                            error!("Failed to delete keys matching pattern {}: {}", log_key(pattern, self.redact_logs), e);
+                            break;
+                        }
+                    }
+                }
+                
+                if next_cursor == 0 {
+                    break;
+                }
+                cursor = next_cursor;
+            }
+            
+            self.counters.record_deletes(deleted_count as u64);
+            self.audit("clear_pattern", pattern, deleted_count as usize).await;
+            info!("Cleared {} cache entries matching pattern: {}", deleted_count, log_key(pattern, self.redact_logs));
+            Ok(deleted_count)
+        } else {
+            debug!("Redis not available, skipping pattern clear for: {}", log_key(pattern, self.redact_logs));
+            Ok(0)
+        }
+    }
+
+    /// Typed memory, client, keyspace and uptime statistics from a single default `INFO`.
+    ///
+    /// In cluster mode the reply comes from whichever node serves the command.
+    pub async fn server_stats(&mut self) -> AnyResult<RedisStats> {
+        self.ensure_open()?;
+        match self.conn {
+            Some(ref mut conn) => {
+                let info: String = self.retry.query(&redis::cmd("INFO"), conn).await?;
+                Ok(RedisStats::from_info(&info))
+            }
+            None => Err(anyhow::anyhow!("Redis not available")),
+        }
+    }
+
+    /// Get cache statistics
+    pub async fn get_cache_info(&mut self) -> AnyResult<HashMap<String, String>> {
+        self.ensure_open()?;
+        if let Some(ref mut conn) = self.conn {
+            let info: String = redis::cmd("INFO")
+                .arg("memory")
+                .query_async(conn)
+                .await
+                .unwrap_or_default();
+            
+            let mut stats = HashMap::new();
+            for line in info.lines() {
+                if let Some((key, value)) = line.split_once(':') {
+                    stats.insert(key.to_string(), value.to_string());
+                }
+            }
+            
+            Ok(stats)
+        } else {
+            let mut stats = HashMap::new();
+            stats.insert("status".to_string(), "Redis not available".to_string());
+            Ok(stats)
+        }
+    }
+}
+
+/// The core cache operations, so code can be written against any backend.
+///
+/// `CacheManager` implements it over Redis. With the `test-util` feature,
+/// `memory::InMemoryCache` implements it over a `HashMap`, letting business logic that is
+/// generic over `Cache` be unit tested without a Redis server.
+#[async_trait]
+pub trait Cache: Send {
+    async fn get<T>(&mut self, key: &str) -> Result<Option<CachedResponse<T>>, CacheError>
+    where
+        T: for<'de> Deserialize<'de> + Send;
+
+    /// Store `data` with the backend's default TTL. Returns `false` if the write was skipped.
+    async fn set<T>(&mut self, key: &str, data: &CachedResponse<T>) -> Result<bool, CacheError>
+    where
+        T: Serialize + Sync;
+
+    async fn delete(&mut self, key: &str) -> AnyResult<bool>;
+
+    async fn exists(&mut self, key: &str) -> AnyResult<bool>;
+}
+
+#[async_trait]
+impl Cache for CacheManager {
+    async fn get<T>(&mut self, key: &str) -> Result<Option<CachedResponse<T>>, CacheError>
+    where
+        T: for<'de> Deserialize<'de> + Send,
+    {
+        CacheManager::get(self, key).await
+    }
+
+    async fn set<T>(&mut self, key: &str, data: &CachedResponse<T>) -> Result<bool, CacheError>
+    where
+        T: Serialize + Sync,
+    {
+        CacheManager::set(self, key, data).await
+    }
+
+    async fn delete(&mut self, key: &str) -> AnyResult<bool> {
+        CacheManager::delete(self, key).await
+    }
+
+    async fn exists(&mut self, key: &str) -> AnyResult<bool> {
+        CacheManager::exists(self, key).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{capture_logs, dropping_server, FakeRedis};
+    use std::collections::HashSet;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Mutex;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct UserProfile {
+        id: u32,
+        name: String,
+        nickname: Option<String>,
+        preferences: Vec<String>,
+    }
+
+    fn profile() -> CachedResponse<UserProfile> {
+        let data = UserProfile {
+            id: 42,
+            name: "Ayu".to_string(),
+            nickname: None,
+            preferences: vec!["dark_mode".to_string()],
+        };
+        CachedResponse::new(data, "user:42".to_string())
+    }
+
+    /// Manager on the server at `REDIS_URL`, in a namespace of its own. Tests using it are
+    /// ignored by default; run them with `cargo test -- --ignored` against a disposable server.
+    async fn live_manager() -> CacheManager {
+        let manager = CacheManager::from_config(RedisConfig::from_env()).await;
+        assert!(manager.is_available(), "these tests need a Redis server at REDIS_URL");
+        manager.with_namespace(&format!("shared-redis-test:{}", uuid::Uuid::new_v4()))
+    }
+
+    /// Manager on the test server at `url`, without the namespace, L1, retries or circuit
+    /// breaker the environment might configure
+    async fn manager_on(url: &str) -> CacheManager {
+        let conn = AsyncConnManager::new(Client::open(url).unwrap()).await.unwrap();
+        CacheManager::with_connection(conn)
+            .with_namespace("")
+            .with_local_cache(0, Duration::ZERO)
+            .with_retry_policy(RetryPolicy::none())
+            .with_circuit_breaker(CircuitBreaker::disabled())
+    }
+
+    fn encoding(json_style: SerializationOptions, encryption: Option<CacheEncryption>) -> ValueEncoding {
+        ValueEncoding {
+            format: CacheFormat::Json,
+            json_style,
+            compression: CacheCompression::None,
+            compression_threshold: 1024,
+            encryption,
+        }
+    }
+
+    #[test]
+    fn compression_round_trips_each_codec() {
+        let payload = "hotel search results ".repeat(200).into_bytes();
+        for compression in [CacheCompression::Gzip, CacheCompression::Zstd] {
+            let stored = compression.encode(&payload, 1024).unwrap();
+            assert_eq!(&stored[..2], &[COMPRESSION_MAGIC, COMPRESSION_VERSION]);
+            assert!(stored.len() < payload.len());
+            assert_eq!(CacheCompression::decode(&stored).unwrap(), payload);
+        }
+    }
+
+    #[test]
+    fn compression_skips_small_payloads_and_reads_uncompressed_values() {
+        let payload = br#"{"id":1}"#.to_vec();
+        let stored = CacheCompression::Zstd.encode(&payload, 1024).unwrap();
+        assert_eq!(stored, payload);
+        assert_eq!(CacheCompression::decode(&stored).unwrap(), payload);
+    }
+
+    #[test]
+    fn every_json_style_reads_back_the_same_struct() {
+        let written = profile();
+        for style in [SerializationOptions::Compact, SerializationOptions::Pretty, SerializationOptions::SkipNulls] {
+            let stored = encoding(style, None).encode(&written).unwrap();
+            // A reader configured with any style decodes what any other style wrote
+            let read: CachedResponse<UserProfile> = encoding(SerializationOptions::Compact, None).decode(&stored).unwrap();
+            assert_eq!(read.data, written.data);
+            assert_eq!(read.cache_key, written.cache_key);
+        }
+
+        let pretty = encoding(SerializationOptions::Pretty, None).encode(&written).unwrap();
+        assert!(pretty.contains(&b'\n'));
+        let skip_nulls = encoding(SerializationOptions::SkipNulls, None).encode(&written).unwrap();
+        assert!(!String::from_utf8(skip_nulls).unwrap().contains("nickname"));
+    }
+
+    #[test]
+    fn encryption_round_trips_after_compression() {
+        let key = CacheEncryption::new(&[7u8; 32]).unwrap();
+        let mut encoding = encoding(SerializationOptions::Compact, Some(key));
+        encoding.compression = CacheCompression::Gzip;
+        encoding.compression_threshold = 0;
+
+        let stored = encoding.encode(&profile()).unwrap();
+        assert_eq!(stored.first(), Some(&ENCRYPTION_MAGIC));
+        let read: CachedResponse<UserProfile> = encoding.decode(&stored).unwrap();
+        assert_eq!(read.data, profile().data);
+    }
+
+    #[test]
+    fn wrong_or_missing_key_is_a_decryption_error() {
+        let writer = encoding(SerializationOptions::Compact, Some(CacheEncryption::new(&[7u8; 32]).unwrap()));
+        let stored = writer.encode(&profile()).unwrap();
+
+        // `get` reports a `DecryptionError` as a cache miss rather than returning the error
+        let wrong_key = encoding(SerializationOptions::Compact, Some(CacheEncryption::new(&[8u8; 32]).unwrap()));
+        let error = wrong_key.decode::<CachedResponse<UserProfile>>(&stored).unwrap_err();
+        assert!(error.is::<DecryptionError>());
+
+        let no_key = encoding(SerializationOptions::Compact, None);
+        let error = no_key.decode::<CachedResponse<UserProfile>>(&stored).unwrap_err();
+        assert!(error.is::<DecryptionError>());
+    }
+
+    #[test]
+    fn encryption_key_must_be_32_bytes() {
+        assert!(CacheEncryption::new(&[0u8; 16]).is_err());
+    }
+
+    #[test]
+    fn state_at_reports_stale_after_max_age() {
+        let entry = profile();
+        let max_age = Duration::from_secs(60);
+        let at = |seconds: i64| entry.cached_at + chrono::Duration::seconds(seconds);
+
+        assert_eq!(entry.state_at(max_age, at(30)), CacheState::Fresh);
+        assert_eq!(entry.state_at(max_age, at(60)), CacheState::Fresh);
+        assert_eq!(entry.state_at(max_age, at(61)), CacheState::Stale);
+        // Written by an instance whose clock runs ahead
+        assert_eq!(entry.state_at(max_age, at(-30)), CacheState::Fresh);
+    }
+
+    #[test]
+    fn short_requests_stay_readable_under_the_threshold() {
+        let key = build_cache_key("hotel_search", &42, 64).unwrap();
+        assert_eq!(key, "hotel_search:42");
+
+        let key = build_cache_key("hotel_search", &"jakarta", 64).unwrap();
+        assert_eq!(key, r#"hotel_search:"jakarta""#);
+    }
+
+    #[test]
+    fn long_or_unsafe_requests_are_hashed() {
+        let long = build_cache_key("hotel_search", &"x".repeat(100), 64).unwrap();
+        let (prefix, hash) = long.split_once(':').unwrap();
+        assert_eq!(prefix, "hotel_search");
+        assert_eq!(hash.len(), 64);
+        assert!(hash.chars().all(|c| c.is_ascii_hexdigit()));
+
+        // Whitespace and glob characters would make keys ambiguous, so they force a hash
+        assert_ne!(build_cache_key("p", &"new york", 64).unwrap(), r#"p:"new york""#);
+        assert_ne!(build_cache_key("p", &"user*", 64).unwrap(), r#"p:"user*""#);
+        // The default threshold of 0 always hashes
+        assert_eq!(build_cache_key("p", &1, 0).unwrap().len(), "p:".len() + 64);
+    }
+
+    #[test]
+    fn parts_api_gives_equal_requests_the_same_key() {
+        let mut first = HashMap::new();
+        let mut second = HashMap::new();
+        for (name, value) in [("city", "bali"), ("guests", "2"), ("nights", "3"), ("rooms", "1")] {
+            first.insert(name, value);
+        }
+        for (name, value) in [("rooms", "1"), ("nights", "3"), ("guests", "2"), ("city", "bali")] {
+            second.insert(name, value);
+        }
+
+        let key_for = |request: &HashMap<&str, &str>| {
+            let mut entries: Vec<String> = request.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+            entries.sort();
+            let parts: Vec<&str> = entries.iter().map(String::as_str).collect();
+            CacheManager::generate_cache_key_from_parts("hotel_search", &parts)
+        };
+        assert_eq!(key_for(&first), key_for(&second));
+    }
+
+    #[test]
+    fn parts_are_length_prefixed() {
+        assert_ne!(
+            CacheManager::generate_cache_key_from_parts("p", &["a:b"]),
+            CacheManager::generate_cache_key_from_parts("p", &["a", "b"])
+        );
+    }
+
+    #[test]
+    fn redis_stats_are_read_from_info_sections() {
+        let info = "# Server\r\nredis_version:7.2.4\r\nuptime_in_seconds:3600\r\n\r\n\
+                    # Clients\r\nconnected_clients:12\r\n\r\n\
+                    # Memory\r\nused_memory:1048576\r\nused_memory_human:1.00M\r\n\r\n\
+                    # Stats\r\nkeyspace_hits:30\r\nkeyspace_misses:10\r\nevicted_keys:2\r\n";
+        let stats = RedisStats::from_info(info);
+        assert_eq!(stats.used_memory_bytes, 1_048_576);
+        assert_eq!(stats.connected_clients, 12);
+        assert_eq!((stats.keyspace_hits, stats.keyspace_misses, stats.evicted_keys), (30, 10, 2));
+        assert_eq!(stats.uptime, Duration::from_secs(3600));
+        assert_eq!(stats.hit_ratio(), 0.75);
+
+        let empty = RedisStats::from_info("# Server\r\n");
+        assert_eq!(empty.used_memory_bytes, 0);
+        assert_eq!(empty.hit_ratio(), 0.0);
+    }
+
+    #[tokio::test]
+    async fn a_dropped_connection_is_an_error_not_a_miss() {
+        let mut manager = manager_on(&dropping_server().await).await;
+
+        assert!(matches!(manager.get::<UserProfile>("profile").await, Err(CacheError::Connection(_))));
+        assert!(matches!(manager.set("profile", &profile()).await, Err(CacheError::Connection(_))));
+        assert!(matches!(manager.get_many::<UserProfile>(&["a", "b"]).await, Err(CacheError::Connection(_))));
+        assert!(matches!(manager.set_many(&[("a", &profile())], 60).await, Err(CacheError::Connection(_))));
+        let error = manager.delete("a").await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<CacheError>(), Some(CacheError::Connection(_))), "{}", error);
+        let error = manager.delete_many(&["a", "b"]).await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<CacheError>(), Some(CacheError::Connection(_))), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn a_corrupted_entry_is_a_serialization_error_and_is_removed() {
+        let server = FakeRedis::start().await;
+        let mut manager = manager_on(server.url()).await;
+        server.insert("profile", b"not a cache entry");
+
+        assert!(matches!(manager.get::<UserProfile>("profile").await, Err(CacheError::Serialization(_))));
+        assert_eq!(server.calls("DEL"), 1);
+
+        // In a batch only the corrupted slot is lost
+        server.insert("profile", b"not a cache entry");
+        manager.set("other", &profile()).await.unwrap();
+        let read = manager.get_many::<UserProfile>(&["profile", "other"]).await.unwrap();
+        assert!(read[0].is_none());
+        assert_eq!(read[1].as_ref().unwrap().data, profile().data);
+    }
+
+    #[tokio::test]
+    async fn delete_many_unlinks_and_falls_back_to_del_only_on_old_servers() {
+        let server = FakeRedis::start().await;
+        let mut manager = manager_on(server.url()).await;
+        server.insert("a", b"1");
+        server.insert("b", b"2");
+
+        assert_eq!(manager.delete_many(&["a", "b", "c"]).await.unwrap(), 2);
+        assert_eq!((server.calls("UNLINK"), server.calls("DEL")), (1, 0));
+
+        server.insert("a", b"1");
+        server.disable("UNLINK");
+        assert_eq!(manager.delete_many(&["a", "b"]).await.unwrap(), 1);
+        assert_eq!((server.calls("UNLINK"), server.calls("DEL")), (2, 1));
+    }
+
+    #[tokio::test]
+    async fn an_open_breaker_keeps_every_write_and_read_off_the_wire() {
+        let server = FakeRedis::start().await;
+        let mut manager = manager_on(server.url()).await.with_circuit_breaker(CircuitBreaker::new(1, Duration::from_secs(60)));
+        manager.breaker.record_failure();
+
+        for error in [
+            manager.copy("a", "b", false).await.unwrap_err(),
+            manager.dump("a").await.unwrap_err(),
+            manager.restore("a", Duration::ZERO, b"payload", true).await.unwrap_err(),
+            manager.rename("a", "b", false).await.unwrap_err(),
+            manager.wait_for_replicas(1, Duration::from_secs(1)).await.unwrap_err(),
+        ] {
+            let redis_error = error.downcast_ref::<redis::RedisError>();
+            assert!(redis_error.is_some_and(CircuitBreaker::is_open_error), "{}", error);
+        }
+        assert_eq!(manager.invalidate_tag("users").await.unwrap(), 0);
+        assert_eq!(manager.clear_pattern("*").await.unwrap(), 0);
+        let error = Box::pin(manager.scan_keys("*")).next().await.unwrap().unwrap_err();
+        let redis_error = error.downcast_ref::<redis::RedisError>();
+        assert!(redis_error.is_some_and(CircuitBreaker::is_open_error), "{}", error);
+        for command in ["COPY", "DUMP", "RESTORE", "RENAMENX", "MULTI", "SCAN", "WAIT"] {
+            assert_eq!(server.calls(command), 0, "{} was sent", command);
+        }
+    }
+
+    #[tokio::test]
+    async fn a_replica_wait_that_would_block_forever_is_rejected_before_sending() {
+        let server = FakeRedis::start().await;
+        let mut manager = manager_on(server.url()).await;
+        assert!(manager.wait_for_replicas(1, Duration::ZERO).await.is_err());
+        assert!(manager.wait_for_replicas(1, Duration::from_micros(500)).await.is_err());
+        assert_eq!(server.calls("WAIT"), 0);
+    }
+
+    #[tokio::test]
+    async fn repeated_reads_are_served_locally_until_the_expiry_changes() {
+        let server = FakeRedis::start().await;
+        let mut writer = manager_on(server.url()).await;
+        let mut reader = manager_on(server.url()).await.with_local_cache(16, Duration::from_secs(60));
+        writer.set("profile", &profile()).await.unwrap();
+
+        assert!(reader.get::<UserProfile>("profile").await.unwrap().is_some());
+        assert!(reader.get::<UserProfile>("profile").await.unwrap().is_some());
+        assert_eq!(server.calls("GET"), 1);
+
+        assert!(reader.expire("profile", Duration::from_secs(5)).await.unwrap());
+        assert!(reader.get::<UserProfile>("profile").await.unwrap().is_some());
+        assert!(reader.expire_at("profile", 4_102_444_800).await.unwrap());
+        assert!(reader.get::<UserProfile>("profile").await.unwrap().is_some());
+        assert!(reader.persist("profile").await.unwrap());
+        assert!(reader.get::<UserProfile>("profile").await.unwrap().is_some());
+        assert_eq!(server.calls("GET"), 4);
+    }
+
+    #[tokio::test]
+    async fn a_sub_second_expiry_keeps_the_entry_until_it_runs_out() {
+        let server = FakeRedis::start().await;
+        let mut manager = manager_on(server.url()).await;
+        manager.set("session", &profile()).await.unwrap();
+
+        assert!(manager.expire("session", Duration::from_millis(500)).await.unwrap());
+        assert!(manager.exists("session").await.unwrap());
+        assert_eq!((server.calls("PEXPIRE"), server.calls("EXPIRE")), (1, 0));
+        assert!(manager.expire("session", Duration::ZERO).await.unwrap());
+        assert!(!manager.exists("session").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn without_a_connection_expiry_updates_report_false_like_deletes() {
+        let server = FakeRedis::start().await;
+        let mut manager = manager_on(server.url()).await;
+        manager.conn = None;
+
+        assert!(!manager.expire("session", Duration::from_secs(60)).await.unwrap());
+        assert!(!manager.expire_at("session", 4_102_444_800).await.unwrap());
+        assert!(!manager.persist("session").await.unwrap());
+        assert!(!manager.delete("session").await.unwrap());
+        assert!(!manager.copy("session", "other", false).await.unwrap());
+        assert!(!manager.rename("session", "other", false).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn copying_keeps_an_existing_destination_unless_replacing() {
+        let server = FakeRedis::start().await;
+        let mut manager = manager_on(server.url()).await;
+        manager.set("profile", &profile()).await.unwrap();
+        let mut other = profile();
+        other.data.name = "Bima".to_string();
+        manager.set("taken", &other).await.unwrap();
+
+        assert!(manager.copy("profile", "fresh", false).await.unwrap());
+        assert_eq!(manager.get::<UserProfile>("fresh").await.unwrap().unwrap().data, profile().data);
+        assert!(!manager.copy("profile", "taken", false).await.unwrap());
+        assert_eq!(manager.get::<UserProfile>("taken").await.unwrap().unwrap().data.name, "Bima");
+        assert!(manager.copy("profile", "taken", true).await.unwrap());
+        assert_eq!(manager.get::<UserProfile>("taken").await.unwrap().unwrap().data.name, "Ayu");
+        assert!(!manager.copy("missing", "fresh", true).await.unwrap());
+        assert_eq!(manager.get::<UserProfile>("profile").await.unwrap().unwrap().data, profile().data);
+    }
+
+    #[tokio::test]
+    async fn values_over_the_size_limit_are_rejected_or_skipped() {
+        let server = FakeRedis::start().await;
+        // One value throughout, as the encoded timestamp varies in length
+        let value = profile();
+        let size = manager_on(server.url()).await.encoding.encode(&value).unwrap().len();
+        let mut at_limit = manager_on(server.url()).await.with_max_value_size(size);
+        let mut below_limit = manager_on(server.url()).await.with_max_value_size(size - 1);
+
+        assert!(at_limit.set("fits", &value).await.unwrap());
+        assert!(at_limit.exists("fits").await.unwrap());
+        let error = below_limit.set("too-big", &value).await.unwrap_err();
+        assert!(matches!(error, CacheError::ValueTooLarge { size: s, max } if s == size && max == size - 1), "{:?}", error);
+        let mut skipping = below_limit.with_oversize_policy(OversizePolicy::Skip);
+        assert!(!skipping.set("too-big", &value).await.unwrap());
+        assert!(!skipping.exists("too-big").await.unwrap());
+        assert_eq!(server.calls("SET"), 1);
+    }
+
+    #[tokio::test]
+    async fn an_oversized_entry_fails_or_thins_a_batch_by_policy() {
+        let server = FakeRedis::start().await;
+        let value = profile();
+        let size = manager_on(server.url()).await.encoding.encode(&value).unwrap().len();
+        let mut small = profile();
+        small.data.preferences.clear();
+        let entries = [("fits", &small), ("too-big", &value)];
+
+        let mut rejecting = manager_on(server.url()).await.with_max_value_size(size - 1).with_oversize_policy(OversizePolicy::Reject);
+        let error = rejecting.set_many(&entries, 60).await.unwrap_err();
+        assert!(matches!(error, CacheError::ValueTooLarge { size: s, max } if s == size && max == size - 1), "{:?}", error);
+        assert_eq!(server.calls("SETEX"), 0);
+
+        let mut skipping = rejecting.with_oversize_policy(OversizePolicy::Skip);
+        assert_eq!(skipping.set_many(&entries, 60).await.unwrap(), 1);
+        assert!(skipping.exists("fits").await.unwrap());
+        assert!(!skipping.exists("too-big").await.unwrap());
+        assert_eq!(server.calls("SETEX"), 1);
+    }
+
+    #[tokio::test]
+    async fn a_batch_of_fifty_reads_back_and_skips_only_what_cannot_be_encoded() {
+        let server = FakeRedis::start().await;
+        let mut manager = manager_on(server.url()).await;
+        // JSON maps need string keys, so only the non-empty map fails to encode
+        type Grid = HashMap<(u32, u32), u32>;
+        let keys: Vec<String> = (0..50).map(|i| format!("cell:{}", i)).collect();
+        let empty = CachedResponse::new(Grid::new(), String::new());
+        let broken = CachedResponse::new(Grid::from([((1, 1), 1)]), String::new());
+        let mut entries: Vec<(&str, &CachedResponse<Grid>)> = keys.iter().map(|key| (key.as_str(), &empty)).collect();
+        entries.insert(25, ("broken", &broken));
+
+        assert_eq!(manager.set_many(&entries, 60).await.unwrap(), 50);
+        assert_eq!(server.calls("SETEX"), 50);
+        let mut keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+        keys.push("broken");
+        let read = manager.get_many::<Grid>(&keys).await.unwrap();
+        assert!(read[..50].iter().all(|entry| entry.as_ref().is_some_and(|entry| entry.data.is_empty())));
+        assert!(read[50].is_none());
+        assert_eq!(server.calls("MGET"), 1);
+    }
+
+    #[tokio::test]
+    async fn the_namespace_prefixes_every_key() {
+        let server = FakeRedis::start().await;
+        let mut service = manager_on(server.url()).await.with_namespace("svcA");
+        let mut raw = manager_on(server.url()).await;
+        let cached = service.cache_response("user_profile", &42, profile().data).await.unwrap();
+
+        service.set("profile", &profile()).await.unwrap();
+        assert!(raw.exists("svcA:profile").await.unwrap());
+        assert!(!raw.exists("profile").await.unwrap());
+        assert!(raw.exists(&format!("svcA:{}", cached.cache_key)).await.unwrap());
+        assert!(cached.cache_key.starts_with("user_profile:"), "{}", cached.cache_key);
+        assert!(service.delete("profile").await.unwrap());
+        assert!(!raw.exists("svcA:profile").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn health_reports_latency_or_why_the_ping_failed() {
+        let server = FakeRedis::start().await;
+        let mut manager = manager_on(server.url()).await;
+        assert!(manager.ping().await.unwrap());
+        let health = manager.health().await;
+        assert!(health.healthy && health.latency.is_some() && health.error.is_none(), "{:?}", health);
+
+        let mut dead = manager_on(&dropping_server().await).await;
+        assert!(dead.ping().await.is_err());
+        let health = dead.health().await;
+        assert!(!health.healthy && health.latency.is_none() && health.error.is_some(), "{:?}", health);
+    }
+
+    #[tokio::test]
+    async fn reads_alternate_between_replicas_and_writes_go_to_the_primary() {
+        let primary = FakeRedis::start().await;
+        let replicas = [FakeRedis::start().await, FakeRedis::start().await];
+        for replica in &replicas {
+            manager_on(replica.url()).await.set("profile", &profile()).await.unwrap();
+        }
+        let config = RedisConfig {
+            redis_url: Some(primary.url().to_string()),
+            replica_urls: replicas.iter().map(|replica| replica.url().to_string()).collect(),
+            key_namespace: None,
+            local_cache_capacity: 0,
+            ..RedisConfig::from_env()
+        };
+        let mut manager = CacheManager::from_config(config).await;
+
+        for _ in 0..4 {
+            assert!(manager.get::<UserProfile>("profile").await.unwrap().is_some());
+        }
+        assert_eq!(replicas.each_ref().map(|replica| replica.calls("GET")), [2, 2]);
+        assert_eq!(primary.calls("GET"), 0);
+        manager.set("profile", &profile()).await.unwrap();
+        assert_eq!(primary.calls("SET"), 1);
+        assert_eq!(replicas.each_ref().map(|replica| replica.calls("SET")), [1, 1]);
+    }
+
+    #[tokio::test]
+    async fn an_injected_connection_or_client_is_used_as_is() {
+        let server = FakeRedis::start().await;
+        let conn = AsyncConnManager::new(Client::open(server.url()).unwrap()).await.unwrap();
+        let mut injected = CacheManager::with_connection(conn).with_namespace("");
+        let mut from_client = CacheManager::from_client(Client::open(server.url()).unwrap()).await.unwrap().with_namespace("");
+
+        assert!(injected.set("profile", &profile()).await.unwrap());
+        let read = from_client.get::<UserProfile>("profile").await.unwrap().unwrap();
+        assert_eq!(read.data, profile().data);
+        assert_eq!(server.calls("SET"), 1);
+    }
+
+    #[tokio::test]
+    async fn clones_share_the_connection_across_concurrent_tasks() {
+        let server = FakeRedis::start().await;
+        let manager = manager_on(server.url()).await;
+
+        let tasks: Vec<_> = (0..20)
+            .map(|i| {
+                let mut manager = manager.clone();
+                tokio::spawn(async move {
+                    let key = format!("profile:{}", i);
+                    let mut value = profile();
+                    value.data.id = i;
+                    manager.set(&key, &value).await.unwrap();
+                    manager.get::<UserProfile>(&key).await.unwrap().unwrap().data.id
+                })
+            })
+            .collect();
+        for (i, task) in tasks.into_iter().enumerate() {
+            assert_eq!(task.await.unwrap(), i as u32);
+        }
+        assert_eq!((server.calls("SET"), server.calls("GET")), (20, 20));
+    }
+
+    #[tokio::test]
+    async fn reads_fail_over_to_the_fallback_and_writes_are_mirrored_to_it() {
+        let fallback = FakeRedis::start().await;
+        let fallback_conn = || async { RedisConn::Single(AsyncConnManager::new(Client::open(fallback.url()).unwrap()).await.unwrap()) };
+        manager_on(fallback.url()).await.set("profile", &profile()).await.unwrap();
+
+        let mut unreachable = manager_on(&dropping_server().await).await.with_fallback(fallback_conn().await, false);
+        let read = unreachable.get::<UserProfile>("profile").await.unwrap().unwrap();
+        assert_eq!(read.data, profile().data);
+        assert_eq!(fallback.calls("GET"), 1);
+
+        let primary = FakeRedis::start().await;
+        let mut mirrored = manager_on(primary.url()).await.with_fallback(fallback_conn().await, true);
+        mirrored.set("settings", &profile()).await.unwrap();
+        assert_eq!(primary.calls("SET"), 1);
+        // Mirroring runs in the background
+        for _ in 0..100 {
+            if fallback.calls("SET") == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(fallback.calls("SET"), 2);
+    }
+
+    #[tokio::test]
+    async fn warmed_entries_are_all_hits_and_loaders_respect_the_concurrency_cap() {
+        let server = FakeRedis::start().await;
+        let mut manager = manager_on(server.url()).await;
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        let loader = |page: u32| {
+            let (running, peak) = (&running, &peak);
+            async move {
+                peak.fetch_max(running.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok(page * 2)
+            }
+        };
+        assert_eq!(manager.warm("report", (0..20).collect(), 4, loader).await.unwrap(), 20);
+        assert_eq!(peak.load(Ordering::SeqCst), 4);
+
+        for page in 0..20u32 {
+            let cached = manager.get_cached_response::<u32, _>("report", &page).await.unwrap().unwrap();
+            assert_eq!(cached.data, page * 2);
+        }
+        let metrics = manager.metrics();
+        assert_eq!((metrics.hits, metrics.misses, metrics.sets), (20, 0, 20));
+    }
+
+    #[tokio::test]
+    async fn hits_misses_sets_and_deletes_are_counted_as_they_happen() {
+        let server = FakeRedis::start().await;
+        let mut manager = manager_on(server.url()).await;
+
+        manager.set("profile", &profile()).await.unwrap();
+        assert!(manager.get::<UserProfile>("profile").await.unwrap().is_some());
+        assert!(manager.get::<UserProfile>("profile").await.unwrap().is_some());
+        assert!(manager.get::<UserProfile>("missing").await.unwrap().is_none());
+        assert!(manager.delete("profile").await.unwrap());
+        assert!(!manager.delete("profile").await.unwrap());
+        manager.cache_response("user", &42, profile().data).await.unwrap();
+        assert!(manager.get_cached_response::<UserProfile, _>("user", &42).await.unwrap().is_some());
+        assert!(manager.get_cached_response::<UserProfile, _>("user", &43).await.unwrap().is_none());
+
+        let metrics = manager.metrics();
+        assert_eq!((metrics.hits, metrics.misses, metrics.errors, metrics.sets, metrics.deletes), (3, 2, 0, 2, 1));
+        assert_eq!(metrics.hit_ratio(), 0.6);
+        // Clones share the counters
+        manager.clone().reset_metrics();
+        let metrics = manager.metrics();
+        assert_eq!((metrics.hits, metrics.misses, metrics.errors, metrics.sets, metrics.deletes), (0, 0, 0, 0, 0));
+    }
+
+    #[tokio::test]
+    async fn binary_values_are_stored_byte_for_byte() {
+        let server = FakeRedis::start().await;
+        let mut manager = manager_on(server.url()).await;
+        let blob: Vec<u8> = [0xff, 0xfe, 0x00, b'\r', b'\n', 0x80, b'"'].into_iter().chain(0..=255).collect();
+        assert!(std::str::from_utf8(&blob).is_err());
+
+        assert!(manager.set_bytes("thumbnail", &blob, 60).await.unwrap());
+        assert_eq!(manager.get_bytes("thumbnail").await.unwrap(), Some(blob.clone()));
+        let mut conn = AsyncConnManager::new(Client::open(server.url()).unwrap()).await.unwrap();
+        let raw: Vec<u8> = redis::cmd("GET").arg("thumbnail").query_async(&mut conn).await.unwrap();
+        assert_eq!(raw, blob, "stored without any wrapping");
+        assert_eq!(manager.get_bytes("missing").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn raw_bytes_that_cannot_be_decrypted_are_a_miss() {
+        let server = FakeRedis::start().await;
+        let mut manager = manager_on(server.url()).await.with_encryption(CacheEncryption::new(&[7u8; 32]).unwrap());
+        // Written before the encryption key was configured
+        server.insert("thumbnail", b"plaintext blob");
+
+        assert_eq!(manager.get_bytes("thumbnail").await.unwrap(), None);
+        let metrics = manager.metrics();
+        assert_eq!((metrics.hits, metrics.misses, metrics.errors), (0, 1, 0));
+        assert!(manager.exists("thumbnail").await.unwrap());
+
+        assert!(manager.set_bytes("thumbnail", b"sealed blob", 60).await.unwrap());
+        assert_eq!(manager.get_bytes("thumbnail").await.unwrap(), Some(b"sealed blob".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn invalidating_a_tag_deletes_its_entries_but_not_a_key_named_like_the_tag() {
+        let server = FakeRedis::start().await;
+        let mut manager = manager_on(server.url()).await;
+        manager.set("tag:user:123", &profile()).await.unwrap();
+
+        for page in ["profile", "orders", "settings"] {
+            manager.cache_response_tagged("user", &page, profile().data, &["user:123"]).await.unwrap();
+        }
+        assert!(manager.exists("__tag__:user:123").await.unwrap());
+        assert_eq!(manager.invalidate_tag("user:123").await.unwrap(), 3);
+        assert!(!manager.exists("__tag__:user:123").await.unwrap());
+        assert!(manager.get::<serde_json::Value>("tag:user:123").await.unwrap().is_some());
+        assert_eq!(manager.invalidate_tag("user:123").await.unwrap(), 0);
+
+        // Generated keys can never land on a tag set
+        assert!(manager.cache_response("__tag__", &"user:123", profile().data).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn connection_events_follow_the_server_going_away_and_coming_back() {
+        let server = FakeRedis::start().await;
+        let cooldown = Duration::from_millis(50);
+        let mut manager = manager_on(server.url()).await.with_circuit_breaker(CircuitBreaker::new(1, cooldown));
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+        manager.on_connection_event(move |event| seen.lock().unwrap().push(event));
+        manager.set("profile", &profile()).await.unwrap();
+
+        server.set_down(true);
+        assert!(manager.get::<UserProfile>("profile").await.is_err());
+        server.set_down(false);
+        tokio::time::sleep(cooldown).await;
+        assert!(manager.get::<UserProfile>("profile").await.unwrap().is_some());
+
+        use ConnectionEvent::*;
+        assert_eq!(*events.lock().unwrap(), [Reconnecting, Failed, Connected]);
+    }
+
+    #[tokio::test]
+    async fn a_shut_down_manager_and_its_clones_refuse_every_call() {
+        let server = FakeRedis::start().await;
+        let mut manager = manager_on(server.url()).await;
+        let mut clone = manager.clone();
+        manager.shutdown();
+
+        assert!(!manager.is_available());
+        assert!(matches!(manager.get::<UserProfile>("profile").await, Err(CacheError::Closed)));
+        assert!(matches!(clone.get::<UserProfile>("profile").await, Err(CacheError::Closed)));
+        assert!(matches!(clone.set("profile", &profile()).await, Err(CacheError::Closed)));
+        let error = clone.delete("profile").await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<CacheError>(), Some(CacheError::Closed)), "{}", error);
+        assert_eq!(server.calls("GET") + server.calls("SET") + server.calls("DEL"), 0);
+    }
+
+    #[tokio::test]
+    async fn scanning_from_a_clone_of_a_shut_down_manager_yields_closed() {
+        let server = FakeRedis::start().await;
+        let mut manager = manager_on(server.url()).await;
+        let clone = manager.clone();
+        manager.shutdown();
+
+        let mut keys = Box::pin(clone.scan_keys("*"));
+        let error = keys.next().await.unwrap().unwrap_err();
+        assert!(matches!(error.downcast_ref::<CacheError>(), Some(CacheError::Closed)), "{}", error);
+        assert!(keys.next().await.is_none());
+        assert_eq!(server.calls("SCAN"), 0);
+    }
+
+    #[tokio::test]
+    async fn a_request_that_cannot_be_keyed_skips_the_cache_when_asked_to() {
+        let server = FakeRedis::start().await;
+        // JSON map keys must be strings, so this request cannot be serialized into a key
+        let request = HashMap::from([((1, 2), "page")]);
+
+        let mut strict = manager_on(server.url()).await;
+        assert!(strict.get_or_set("user", &request, || async { Ok(profile().data) }).await.is_err());
+
+        let mut lenient = manager_on(server.url()).await.with_skip_on_key_error(true);
+        let fresh = lenient.get_or_set("user", &request, || async { Ok(profile().data) }).await.unwrap();
+        assert_eq!(fresh.data, profile().data);
+        assert!(fresh.cache_key.is_empty());
+        assert!(lenient.get_cached_response::<UserProfile, _>("user", &request).await.unwrap().is_none());
+        assert_eq!(server.calls("GET") + server.calls("SET"), 0);
+    }
+
+    #[tokio::test]
+    async fn disabling_the_cache_bypasses_reads_and_writes_but_not_deletes() {
+        let server = FakeRedis::start().await;
+        let mut manager = manager_on(server.url()).await;
+        manager.set("profile", &profile()).await.unwrap();
+
+        let mut clone = manager.clone();
+        manager.set_enabled(false);
+        assert!(!clone.is_enabled());
+        assert!(clone.get::<UserProfile>("profile").await.unwrap().is_none());
+        assert!(!clone.set("other", &profile()).await.unwrap());
+        assert_eq!((server.calls("GET"), server.calls("SET")), (0, 1));
+        assert!(clone.delete("profile").await.unwrap());
+
+        manager.set_enabled(true);
+        assert!(clone.set("profile", &profile()).await.unwrap());
+        assert!(clone.get::<UserProfile>("profile").await.unwrap().is_some());
+    }
+
+    #[test]
+    fn redacted_keys_are_short_stable_digests() {
+        assert_eq!(log_key("user:42:email", false), "user:42:email");
+
+        let redacted = log_key("user:42:email", true);
+        assert_eq!(redacted.len(), 13);
+        assert!(redacted.starts_with('#') && redacted[1..].chars().all(|c| c.is_ascii_hexdigit()));
+        assert!(!redacted.contains("42"));
+        assert_eq!(redacted, log_key("user:42:email", true));
+        assert_ne!(redacted, log_key("user:43:email", true));
+    }
+
+    #[tokio::test]
+    async fn redaction_keeps_keys_tags_and_patterns_out_of_the_logs() {
+        let server = FakeRedis::start().await;
+        let config = RedisConfig {
+            redis_url: Some(server.url().to_string()),
+            key_namespace: None,
+            local_cache_capacity: 0,
+            log_redact: true,
+            ..RedisConfig::from_env()
+        };
+        let mut manager = CacheManager::from_config(config).await;
+        let secret = "user:42:email";
+
+        let ((), lines) = capture_logs(async {
+            manager.set(secret, &profile()).await.unwrap();
+            assert!(manager.get::<UserProfile>(secret).await.unwrap().is_some());
+            manager.cache_response_tagged("user", &secret, profile().data, &[secret]).await.unwrap();
+            assert_eq!(manager.invalidate_tag(secret).await.unwrap(), 1);
+            assert!(manager.delete(secret).await.unwrap());
+            // The fake server has no `SCAN`, so this logs the failure with the pattern
+            manager.clear_pattern(&format!("{}*", secret)).await.unwrap();
+        })
+        .await;
+        assert!(lines.len() >= 6, "{:?}", lines);
+        for line in &lines {
+            assert!(!line.contains(secret) && !line.contains("email"), "{}", line);
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_misses_run_the_loader_once() {
+        let server = FakeRedis::start().await;
+        let mut manager = manager_on(server.url()).await;
+        manager.stampede_lock_ttl = Duration::from_secs(5);
+        let loads = Arc::new(AtomicUsize::new(0));
+
+        let callers = (0..50).map(|_| {
+            let mut manager = manager.clone();
+            let loads = loads.clone();
+            tokio::spawn(async move {
+                manager
+                    .get_or_set("report", &"daily", || async move {
+                        loads.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        Ok(profile().data)
+                    })
+                    .await
+            })
+        });
+        for caller in futures::future::join_all(callers).await {
+            assert_eq!(caller.unwrap().unwrap().data, profile().data);
+        }
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn versioned_values_decode_like_plain_ones() {
+        let encoding = encoding(SerializationOptions::Compact, None);
+        let stored = encoding.encode_versioned(&profile(), 3).unwrap();
+        assert_eq!(stored[0], VERSION_MAGIC);
+        assert_eq!(&stored[1..1 + VERSION_LEN], &3u64.to_be_bytes());
+        let read: CachedResponse<UserProfile> = encoding.decode(&stored).unwrap();
+        assert_eq!(read.data, profile().data);
+    }
+
+    #[test]
+    fn split_version_reads_the_header_and_passes_plain_values_through() {
+        let encoding = encoding(SerializationOptions::Compact, None);
+        let profile = profile();
+        let plain = encoding.encode(&profile).unwrap();
+        let versioned = encoding.encode_versioned(&profile, 7).unwrap();
+        assert_eq!(split_version(&versioned), (7, plain.as_slice()));
+        assert_eq!(split_version(&plain), (0, plain.as_slice()));
+        assert_eq!(split_version(&versioned[..VERSION_LEN]), (0, &versioned[..VERSION_LEN]));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn only_the_writer_with_the_current_version_wins() {
+        let mut first = live_manager().await;
+        let mut second = first.clone();
+
+        let (a, b) = tokio::join!(
+            first.set_if_version("profile", profile(), 0),
+            second.set_if_version("profile", profile(), 0),
+        );
+        let (winner, loser) = if a.is_ok() { (a, b) } else { (b, a) };
+        assert_eq!(winner.unwrap().unwrap().version, Some(1));
+        assert!(matches!(loser, Err(CacheError::VersionConflict { expected: 0, actual: 1 })));
+
+        let stored: CachedResponse<UserProfile> = first.get("profile").await.unwrap().unwrap();
+        assert_eq!(stored.version, Some(1));
+        assert!(first.set_if_version("profile", profile(), 1).await.unwrap().is_some());
+        first.delete("profile").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn client_tracking_drops_local_copies_changed_by_another_client() {
+        let namespace = format!("shared-redis-test:{}", uuid::Uuid::new_v4());
+        let mut config = RedisConfig::from_env();
+        config.client_tracking = true;
+        let mut reader = CacheManager::from_config(config)
+            .await
+            .with_namespace(&namespace)
+            .with_local_cache(100, Duration::from_secs(60));
+        assert!(reader.invalidations.is_some(), "these tests need a Redis server at REDIS_URL");
+        let mut writer = live_manager().await.with_namespace(&namespace).with_local_cache(0, Duration::ZERO);
+        // Give the listener time to subscribe and enable tracking
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        writer.set("profile", &profile()).await.unwrap();
+        let read: CachedResponse<UserProfile> = reader.get("profile").await.unwrap().unwrap();
+        assert_eq!(read.data.name, "Ayu");
+        let local = reader.local.clone().unwrap();
+        let key = format!("{}:profile", namespace);
+        assert!(local.get(&key).is_some(), "the value should be served from the L1 now");
+
+        let mut renamed = profile();
+        renamed.data.name = "Bima".to_string();
+        writer.set("profile", &renamed).await.unwrap();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while local.get(&key).is_some() {
+            assert!(Instant::now() < deadline, "the L1 entry was not invalidated");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let read: CachedResponse<UserProfile> = reader.get("profile").await.unwrap().unwrap();
+        assert_eq!(read.data.name, "Bima");
+        writer.delete("profile").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn rename_moves_entries_and_reports_a_missing_source_without_the_key() {
+        let mut manager = live_manager().await.with_log_redaction(true);
+        manager.set("draft", &profile()).await.unwrap();
+        manager.set("taken", &profile()).await.unwrap();
+
+        assert!(manager.rename("draft", "published", true).await.unwrap());
+        assert!(manager.get::<UserProfile>("draft").await.unwrap().is_none());
+        assert!(manager.get::<UserProfile>("published").await.unwrap().is_some());
+        assert!(!manager.rename("published", "taken", false).await.unwrap());
+        assert!(manager.exists("published").await.unwrap());
+
+        let error = manager.rename("user:secret@example.com", "elsewhere", true).await.unwrap_err();
+        assert!(error.to_string().contains("does not exist"), "{}", error);
+        assert!(!error.to_string().contains("secret@example.com"), "{}", error);
+        manager.delete_many(&["published", "taken"]).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn managers_with_different_configs_coexist() {
+        let run = uuid::Uuid::new_v4();
+        let config = |ttl_seconds: u64, namespace: &str| RedisConfig {
+            cache_ttl_seconds: ttl_seconds,
+            key_namespace: Some(format!("shared-redis-test:{}:{}", run, namespace)),
+            ..RedisConfig::from_env()
+        };
+        let mut short = CacheManager::from_config(config(100, "a")).await;
+        let mut long = CacheManager::from_config(config(1000, "b")).await;
+        assert!(short.is_available() && long.is_available(), "these tests need a Redis server at REDIS_URL");
+
+        short.set("profile", &profile()).await.unwrap();
+        long.set("profile", &profile()).await.unwrap();
+        let short_ttl = short.remaining_ttl("profile").await.unwrap().unwrap();
+        let long_ttl = long.remaining_ttl("profile").await.unwrap().unwrap();
+        assert!(short_ttl <= Duration::from_secs(100), "{:?}", short_ttl);
+        assert!(long_ttl > Duration::from_secs(100), "{:?}", long_ttl);
+
+        // Each namespace keeps its own copy
+        short.delete("profile").await.unwrap();
+        assert!(long.exists("profile").await.unwrap());
+        long.delete("profile").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn a_plain_set_resets_the_version() {
+        let mut manager = live_manager().await;
+        manager.set_if_version("profile", profile(), 0).await.unwrap();
+        manager.set("profile", &profile()).await.unwrap();
+
+        let stored: CachedResponse<UserProfile> = manager.get("profile").await.unwrap().unwrap();
+        assert_eq!(stored.version, None);
+        assert!(manager.set_if_version("profile", profile(), 0).await.unwrap().is_some());
+        manager.delete("profile").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn a_dumped_entry_restores_under_a_new_name_with_its_expiry() {
+        let mut manager = live_manager().await;
+        manager.set_with_ttl("profile", &profile(), 600).await.unwrap();
+        let dumped = manager.dump("profile").await.unwrap().unwrap();
+        let ttl = manager.remaining_ttl("profile").await.unwrap().unwrap();
+        assert_eq!(manager.dump("missing").await.unwrap(), None);
+
+        assert!(manager.restore("migrated", ttl, &dumped, false).await.unwrap());
+        assert_eq!(manager.get::<UserProfile>("migrated").await.unwrap().unwrap().data, profile().data);
+        let restored_ttl = manager.remaining_ttl("migrated").await.unwrap().unwrap();
+        assert!(restored_ttl > Duration::from_secs(590) && restored_ttl <= ttl, "{:?}", restored_ttl);
+
+        // An existing key is a BUSYKEY error unless replacing
+        assert!(!manager.restore("migrated", Duration::ZERO, &dumped, false).await.unwrap());
+        assert!(manager.restore("migrated", Duration::ZERO, &dumped, true).await.unwrap());
+        assert_eq!(manager.remaining_ttl("migrated").await.unwrap(), Some(Duration::MAX));
+        manager.delete_many(&["profile", "migrated"]).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn audited_mutations_are_read_back_newest_first() {
+        let stream = format!("shared-redis-test:{}:audit", uuid::Uuid::new_v4());
+        let mut manager = live_manager().await.with_audit_log(&stream, 3);
+        let profile_key = namespaced_key(&manager.namespace, "profile");
+
+        manager.set("profile", &profile()).await.unwrap();
+        assert!(manager.copy("profile", "backup", false).await.unwrap());
+        assert!(manager.delete("profile").await.unwrap());
+        assert_eq!(manager.clear_pattern("back*").await.unwrap(), 1);
+
+        let entries = manager.audit_log(10).await.unwrap();
+        let ops: Vec<&str> = entries.iter().map(|entry| entry.op.as_str()).collect();
+        assert_eq!(ops, ["clear_pattern", "delete", "copy", "set"]);
+        let set = entries.last().unwrap();
+        assert_eq!(set.key, profile_key);
+        assert!(set.size > 0);
+        assert!(chrono::Utc::now().signed_duration_since(set.timestamp) < chrono::Duration::minutes(1));
+        assert_eq!(entries[0].size, 1);
+
+        // Approximate trimming only drops whole nodes, so writing a few thousand keeps it bounded
+        for _ in 0..2_000 {
+            manager.delete("profile").await.unwrap();
+        }
+        let len: usize = manager.conn.as_mut().unwrap().xlen(&stream).await.unwrap();
+        assert!(len < 2_000, "{}", len);
+        let _: () = manager.conn.as_mut().unwrap().del(&stream).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a disposable Redis server at REDIS_URL whose config may be changed"]
+    async fn a_full_server_is_an_out_of_memory_error() {
+        let config = |args: &[&str]| {
+            let mut cmd = redis::cmd("CONFIG");
+            cmd.arg(args);
+            cmd
+        };
+        let mut manager = live_manager().await;
+        let conn = manager.conn.as_mut().unwrap();
+        let (_, maxmemory): (String, String) = config(&["GET", "maxmemory"]).query_async(conn).await.unwrap();
+        let (_, policy): (String, String) = config(&["GET", "maxmemory-policy"]).query_async(conn).await.unwrap();
+
+        config(&["SET", "maxmemory-policy", "noeviction"]).query_async::<_, ()>(conn).await.unwrap();
+        config(&["SET", "maxmemory", "1"]).query_async::<_, ()>(conn).await.unwrap();
+        let result = manager.set("profile", &profile()).await;
+        let conn = manager.conn.as_mut().unwrap();
+        config(&["SET", "maxmemory", &maxmemory]).query_async::<_, ()>(conn).await.unwrap();
+        config(&["SET", "maxmemory-policy", &policy]).query_async::<_, ()>(conn).await.unwrap();
+        assert!(matches!(result, Err(CacheError::OutOfMemory(_))), "{:?}", result);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn a_zero_ttl_stores_without_expiry() {
+        let mut manager = live_manager().await;
+        manager.set_with_ttl("forever", &profile(), 0).await.unwrap();
+        manager.set_with_ttl("brief", &profile(), 30).await.unwrap();
+        let cached = manager.cache_response_with_ttl("report", &"daily", profile().data, 0).await.unwrap();
+
+        assert_eq!(manager.remaining_ttl("forever").await.unwrap(), Some(Duration::MAX));
+        let brief = manager.remaining_ttl("brief").await.unwrap().unwrap();
+        assert!(brief > Duration::ZERO && brief <= Duration::from_secs(30), "{:?}", brief);
+        assert_eq!(manager.remaining_ttl(&cached.cache_key).await.unwrap(), Some(Duration::MAX));
+        manager.delete_many(&["forever", "brief", &cached.cache_key]).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn clearing_a_pattern_removes_every_match_and_nothing_else() {
+        // A small SCAN page makes the cursor loop run many times
+        let config = RedisConfig {
+            scan_count: 10,
+            ..RedisConfig::from_env()
+        };
+        let manager = CacheManager::from_config(config).await;
+        assert!(manager.is_available(), "these tests need a Redis server at REDIS_URL");
+        let mut manager = manager.with_namespace(&format!("shared-redis-test:{}", uuid::Uuid::new_v4()));
+        let value = profile();
+        let keys: Vec<String> = (0..300).map(|i| format!("session:{}", i)).collect();
+        let entries: Vec<(&str, &CachedResponse<UserProfile>)> = keys.iter().map(|key| (key.as_str(), &value)).collect();
+        assert_eq!(manager.set_many(&entries, 60).await.unwrap(), 300);
+        manager.set("sessions", &value).await.unwrap();
+        manager.set("profile", &value).await.unwrap();
+
+        assert_eq!(manager.clear_pattern("session:*").await.unwrap(), 300);
+        assert!(manager.scan_keys("session:*").collect::<Vec<_>>().await.is_empty());
+        assert!(manager.exists("sessions").await.unwrap());
+        assert!(manager.exists("profile").await.unwrap());
+        manager.delete_many(&["sessions", "profile"]).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn clearing_a_pattern_stays_inside_the_namespace() {
+        let run = format!("shared-redis-test:{}", uuid::Uuid::new_v4());
+        let mut service_a = live_manager().await.with_namespace(&format!("{}:svcA", run));
+        let mut service_b = live_manager().await.with_namespace(&format!("{}:svcB", run));
+        service_a.set("user:1", &profile()).await.unwrap();
+        service_b.set("user:1", &profile()).await.unwrap();
+
+        assert_eq!(service_a.clear_pattern("user:*").await.unwrap(), 1);
+        assert!(!service_a.exists("user:1").await.unwrap());
+        assert!(service_b.exists("user:1").await.unwrap());
+        service_b.delete("user:1").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn a_live_server_answers_the_health_check() {
+        let mut manager = live_manager().await;
+        assert!(manager.ping().await.unwrap());
+        let health = manager.health().await;
+        assert!(health.healthy, "{:?}", health.error);
+        assert!(health.latency.unwrap() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn exists_and_remaining_ttl_describe_present_and_absent_keys() {
+        let mut manager = live_manager().await;
+        manager.set_with_ttl("expiring", &profile(), 120).await.unwrap();
+        manager.set_with_ttl("lasting", &profile(), 0).await.unwrap();
+
+        assert!(manager.exists("expiring").await.unwrap());
+        let ttl = manager.remaining_ttl("expiring").await.unwrap().unwrap();
+        assert!(ttl > Duration::from_secs(110) && ttl <= Duration::from_secs(120), "{:?}", ttl);
+        assert!(manager.exists("lasting").await.unwrap());
+        assert_eq!(manager.remaining_ttl("lasting").await.unwrap(), Some(Duration::MAX));
+        assert!(!manager.exists("absent").await.unwrap());
+        assert_eq!(manager.remaining_ttl("absent").await.unwrap(), None);
+        manager.delete_many(&["expiring", "lasting"]).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn scanning_yields_exactly_the_matching_keys() {
+        let mut manager = live_manager().await;
+        let value = profile();
+        let keys: Vec<String> = (0..1000).map(|i| format!("{}:{}", if i % 5 < 3 { "match" } else { "other" }, i)).collect();
+        let entries: Vec<(&str, &CachedResponse<UserProfile>)> = keys.iter().map(|key| (key.as_str(), &value)).collect();
+        assert_eq!(manager.set_many(&entries, 60).await.unwrap(), 1000);
+
+        let scanned: Vec<String> = manager.scan_keys("match:*").map(Result::unwrap).collect().await;
+        // SCAN may repeat a key, but must not miss or invent one
+        let scanned: HashSet<String> = scanned.into_iter().collect();
+        let expected: HashSet<String> = keys.iter().filter(|key| key.starts_with("match:")).cloned().collect();
+        assert_eq!(expected.len(), 600);
+        assert_eq!(scanned, expected);
+        assert_eq!(manager.clear_pattern("*").await.unwrap(), 1000);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn counting_keys_matches_what_was_seeded() {
+        let mut manager = live_manager().await;
+        let value = profile();
+        let keys: Vec<String> = (0..37).map(|i| format!("{}:{}", if i < 12 { "user" } else { "order" }, i)).collect();
+        let entries: Vec<(&str, &CachedResponse<UserProfile>)> = keys.iter().map(|key| (key.as_str(), &value)).collect();
+        manager.set_many(&entries, 60).await.unwrap();
+
+        assert_eq!(manager.count_keys("*").await.unwrap(), 37);
+        assert_eq!(manager.count_keys("user:*").await.unwrap(), 12);
+        assert_eq!(manager.count_keys("missing:*").await.unwrap(), 0);
+        assert!(manager.dbsize().await.unwrap() >= 37);
+        manager.clear_pattern("*").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn only_keys_without_a_ttl_are_given_one() {
+        let mut manager = live_manager().await;
+        manager.set_with_ttl("session:leaked", &profile(), 0).await.unwrap();
+        manager.set_with_ttl("session:also-leaked", &profile(), 0).await.unwrap();
+        manager.set_with_ttl("session:fine", &profile(), 1000).await.unwrap();
+        manager.set_with_ttl("settings", &profile(), 0).await.unwrap();
+
+        assert_eq!(manager.expire_missing_ttl("session:*", 60).await.unwrap(), 2);
+        for key in ["session:leaked", "session:also-leaked"] {
+            let ttl = manager.remaining_ttl(key).await.unwrap().unwrap();
+            assert!(ttl > Duration::ZERO && ttl <= Duration::from_secs(60), "{}: {:?}", key, ttl);
+        }
+        assert!(manager.remaining_ttl("session:fine").await.unwrap().unwrap() > Duration::from_secs(60));
+        assert_eq!(manager.remaining_ttl("settings").await.unwrap(), Some(Duration::MAX));
+        assert_eq!(manager.expire_missing_ttl("session:*", 60).await.unwrap(), 0);
+        manager.clear_pattern("*").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a disposable Redis 6+ server at REDIS_URL whose ACL users may be changed"]
+    async fn rotated_credentials_are_picked_up_without_a_restart() {
+        let mut admin = live_manager().await;
+        let namespace = admin.namespace.clone().unwrap();
+        let user = namespace.replace(':', "-");
+        let acl = |args: &[&str]| {
+            let mut cmd = redis::cmd("ACL");
+            cmd.arg(args);
+            cmd
+        };
+        let admin_conn = admin.conn.as_mut().unwrap();
+        acl(&["SETUSER", &user, "on", ">before", "~*", "+@all"]).query_async::<_, ()>(admin_conn).await.unwrap();
+
+        let config = RedisConfig::from_env().with_credentials(&user, "before").unwrap();
+        let mut manager = CacheManager::from_config(config)
+            .await
+            .with_namespace(&namespace)
+            .with_local_cache(0, Duration::ZERO)
+            .with_retry_policy(RetryPolicy::none())
+            .with_circuit_breaker(CircuitBreaker::disabled());
+        assert!(manager.set("profile", &profile()).await.unwrap());
+
+        // Rotate the password and drop the sessions opened with the old one
+        acl(&["SETUSER", &user, "resetpass", ">after"]).query_async::<_, ()>(admin_conn).await.unwrap();
+        redis::cmd("CLIENT").arg("KILL").arg("USER").arg(&user).query_async::<_, ()>(admin_conn).await.unwrap();
+        assert!(manager.get::<UserProfile>("profile").await.is_err());
+
+        assert!(manager.update_credentials(&user, "wrong").await.is_err());
+        manager.update_credentials(&user, "after").await.unwrap();
+        assert!(manager.get::<UserProfile>("profile").await.unwrap().is_some());
+
+        manager.delete("profile").await.unwrap();
+        acl(&["DELUSER", &user]).query_async::<_, ()>(admin.conn.as_mut().unwrap()).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis primary with at least one replica at REDIS_URL"]
+    async fn replicas_acknowledge_a_critical_write() {
+        let mut manager = live_manager().await;
+        let info: String = redis::cmd("INFO")
+            .arg("replication")
+            .query_async(manager.conn.as_mut().unwrap())
+            .await
+            .unwrap();
+        let replicas: u32 = info
+            .lines()
+            .find_map(|line| line.strip_prefix("connected_slaves:"))
+            .and_then(|count| count.trim().parse().ok())
+            .unwrap();
+        assert!(replicas > 0, "REDIS_URL has no replicas");
+
+        manager.set("order", &profile()).await.unwrap();
+        assert_eq!(manager.wait_for_replicas(replicas, Duration::from_secs(5)).await.unwrap(), replicas);
+        // Asking for more replicas than exist returns at the timeout with those that answered
+        let acknowledged = manager.wait_for_replicas(replicas + 1, Duration::from_millis(200)).await.unwrap();
+        assert_eq!(acknowledged, replicas);
+        manager.delete("order").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn expiries_are_extended_set_and_removed_in_place() {
+        let mut manager = live_manager().await;
+        manager.set_with_ttl("session", &profile(), 30).await.unwrap();
+
+        assert!(manager.expire("session", Duration::from_secs(600)).await.unwrap());
+        let extended = manager.remaining_ttl("session").await.unwrap().unwrap();
+        assert!(extended > Duration::from_secs(30) && extended <= Duration::from_secs(600), "{:?}", extended);
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        assert!(manager.expire_at("session", now + 3600).await.unwrap());
+        let absolute = manager.remaining_ttl("session").await.unwrap().unwrap();
+        assert!(absolute > Duration::from_secs(3500) && absolute <= Duration::from_secs(3600), "{:?}", absolute);
+
+        assert!(manager.persist("session").await.unwrap());
+        assert_eq!(manager.remaining_ttl("session").await.unwrap(), Some(Duration::MAX));
+        assert!(!manager.persist("session").await.unwrap());
+        assert!(manager.get::<UserProfile>("session").await.unwrap().is_some());
+
+        assert!(!manager.expire("missing", Duration::from_secs(60)).await.unwrap());
+        assert!(!manager.expire_at("missing", now + 60).await.unwrap());
+        assert!(!manager.persist("missing").await.unwrap());
+        manager.delete("session").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn atomic_invalidation_is_never_seen_half_done() {
+        let mut manager = live_manager().await.with_local_cache(0, Duration::ZERO);
+        let keys: Vec<String> = (0..20).map(|i| format!("view:{}", i)).collect();
+        let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+        let value = profile();
+        let entries: Vec<(&str, &CachedResponse<UserProfile>)> = keys.iter().map(|key| (*key, &value)).collect();
+        assert_eq!(manager.set_many(&entries, 60).await.unwrap(), keys.len());
+        // `set_many` pipelines its writes, so rounds repopulate with one MSET of the stored
+        // bytes to keep the only partial state the one `invalidate_atomic` might leave
+        let stored: Vec<String> = keys.iter().map(|key| namespaced_key(&manager.namespace, key)).collect();
+        let values: Vec<Vec<u8>> = redis::cmd("MGET")
+            .arg(&stored)
+            .query_async(manager.conn.as_mut().unwrap())
+            .await
+            .unwrap();
+        let mut populate = redis::cmd("MSET");
+        for (key, value) in stored.iter().zip(&values) {
+            populate.arg(key).arg(value);
+        }
+
+        let done = Arc::new(AtomicBool::new(false));
+        let reader = {
+            let mut reader = manager.clone();
+            let keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+            let done = done.clone();
+            tokio::spawn(async move {
+                let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+                let mut reads = 0;
+                while !done.load(Ordering::SeqCst) {
+                    let values = reader.get_many::<UserProfile>(&keys).await.unwrap();
+                    let present = values.iter().filter(|value| value.is_some()).count();
+                    assert!(present == 0 || present == keys.len(), "saw {} of {} keys", present, keys.len());
+                    reads += 1;
+                }
+                reads
+            })
+        };
+
+        for _ in 0..200 {
+            populate.query_async::<_, ()>(manager.conn.as_mut().unwrap()).await.unwrap();
+            assert_eq!(manager.invalidate_atomic(&keys).await.unwrap(), keys.len() as u32);
+            assert!(manager.get_many::<UserProfile>(&keys).await.unwrap().iter().all(Option::is_none));
+        }
+        done.store(true, Ordering::SeqCst);
+        assert!(reader.await.unwrap() > 0);
+        assert_eq!(manager.invalidate_atomic(&keys).await.unwrap(), 0);
+    }
+}
+
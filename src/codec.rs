@@ -0,0 +1,238 @@
+use crate::config::{ENV_CACHE_FORMAT, ENV_CACHE_JSON_STYLE};
+use anyhow::Result as AnyResult;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Marks a stored value as carrying a format header. Values without it are JSON.
+pub const FORMAT_MAGIC: u8 = 0xC8;
+
+/// Serialization used for cache values.
+pub trait CacheCodec {
+    /// Identifier stored alongside encoded values so mixed formats can coexist
+    fn id(&self) -> u8;
+
+    fn serialize<T: Serialize>(&self, value: &T) -> AnyResult<Vec<u8>>;
+
+    fn deserialize<T: DeserializeOwned>(&self, bytes: &[u8]) -> AnyResult<T>;
+}
+
+/// JSON serialization via `serde_json` (default)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl CacheCodec for JsonCodec {
+    fn id(&self) -> u8 {
+        0
+    }
+
+    fn serialize<T: Serialize>(&self, value: &T) -> AnyResult<Vec<u8>> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn deserialize<T: DeserializeOwned>(&self, bytes: &[u8]) -> AnyResult<T> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// MessagePack serialization via `rmp-serde`
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePackCodec;
+
+#[cfg(feature = "msgpack")]
+impl CacheCodec for MessagePackCodec {
+    fn id(&self) -> u8 {
+        1
+    }
+
+    fn serialize<T: Serialize>(&self, value: &T) -> AnyResult<Vec<u8>> {
+        Ok(rmp_serde::to_vec_named(value)?)
+    }
+
+    fn deserialize<T: DeserializeOwned>(&self, bytes: &[u8]) -> AnyResult<T> {
+        Ok(rmp_serde::from_slice(bytes)?)
+    }
+}
+
+/// How JSON cache values are laid out. Every style reads back with the same decoder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SerializationOptions {
+    /// `serde_json` defaults, no whitespace
+    #[default]
+    Compact,
+    /// Indented, for reading values in `redis-cli` while debugging
+    Pretty,
+    /// Compact with `null` object fields dropped. Missing `Option` fields deserialize as
+    /// `None`, but fields of other types that serialize to `null` (such as `()`) do not.
+    SkipNulls,
+}
+
+impl SerializationOptions {
+    /// Read the style from `REDIS_CACHE_JSON_STYLE` (`compact`, `pretty` or `skip_nulls`)
+    pub fn from_env() -> Self {
+        match std::env::var(ENV_CACHE_JSON_STYLE)
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "pretty" => SerializationOptions::Pretty,
+            "skip_nulls" => SerializationOptions::SkipNulls,
+            _ => SerializationOptions::Compact,
+        }
+    }
+
+    /// Serialize `value` as JSON in this style
+    pub fn to_json<T: Serialize>(&self, value: &T) -> AnyResult<Vec<u8>> {
+        match self {
+            SerializationOptions::Compact => Ok(serde_json::to_vec(value)?),
+            SerializationOptions::Pretty => Ok(serde_json::to_vec_pretty(value)?),
+            SerializationOptions::SkipNulls => {
+                let mut tree = serde_json::to_value(value)?;
+                strip_nulls(&mut tree);
+                Ok(serde_json::to_vec(&tree)?)
+            }
+        }
+    }
+}
+
+fn strip_nulls(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(fields) => {
+            fields.retain(|_, field| !field.is_null());
+            fields.values_mut().for_each(strip_nulls);
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+/// Runtime selection of a `CacheCodec`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheFormat {
+    #[default]
+    Json,
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+}
+
+impl CacheFormat {
+    /// Read the format from `REDIS_CACHE_FORMAT` (`json` or `msgpack`)
+    pub fn from_env() -> Self {
+        match std::env::var(ENV_CACHE_FORMAT)
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            #[cfg(feature = "msgpack")]
+            "msgpack" => CacheFormat::MessagePack,
+            _ => CacheFormat::Json,
+        }
+    }
+
+    pub fn from_id(id: u8) -> AnyResult<Self> {
+        match id {
+            0 => Ok(CacheFormat::Json),
+            #[cfg(feature = "msgpack")]
+            1 => Ok(CacheFormat::MessagePack),
+            other => Err(anyhow::anyhow!("Unsupported cache format: {}", other)),
+        }
+    }
+
+    /// Prefix an encoded payload with this format's header. JSON is stored without a
+    /// header so values remain readable by older versions of the crate.
+    pub fn wrap(&self, payload: Vec<u8>) -> Vec<u8> {
+        if *self == CacheFormat::Json {
+            return payload;
+        }
+
+        let mut stored = Vec::with_capacity(payload.len() + 2);
+        stored.extend_from_slice(&[FORMAT_MAGIC, self.id()]);
+        stored.extend_from_slice(&payload);
+        stored
+    }
+
+    /// Split a stored value into its format and the remaining payload
+    pub fn unwrap(stored: &[u8]) -> AnyResult<(Self, &[u8])> {
+        match stored {
+            [FORMAT_MAGIC, id, body @ ..] => Ok((Self::from_id(*id)?, body)),
+            _ => Ok((CacheFormat::Json, stored)),
+        }
+    }
+}
+
+impl CacheCodec for CacheFormat {
+    fn id(&self) -> u8 {
+        match self {
+            CacheFormat::Json => JsonCodec.id(),
+            #[cfg(feature = "msgpack")]
+            CacheFormat::MessagePack => MessagePackCodec.id(),
+        }
+    }
+
+    fn serialize<T: Serialize>(&self, value: &T) -> AnyResult<Vec<u8>> {
+        match self {
+            CacheFormat::Json => JsonCodec.serialize(value),
+            #[cfg(feature = "msgpack")]
+            CacheFormat::MessagePack => MessagePackCodec.serialize(value),
+        }
+    }
+
+    fn deserialize<T: DeserializeOwned>(&self, bytes: &[u8]) -> AnyResult<T> {
+        match self {
+            CacheFormat::Json => JsonCodec.deserialize(bytes),
+            #[cfg(feature = "msgpack")]
+            CacheFormat::MessagePack => MessagePackCodec.deserialize(bytes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct UserProfile {
+        id: u32,
+        name: String,
+        email: String,
+        preferences: Vec<String>,
+    }
+
+    fn profile() -> UserProfile {
+        UserProfile {
+            id: 42,
+            name: "Ayu".to_string(),
+            email: "ayu@example.com".to_string(),
+            preferences: vec!["dark_mode".to_string(), "weekly_digest".to_string()],
+        }
+    }
+
+    fn round_trip(format: CacheFormat) {
+        let stored = format.wrap(format.serialize(&profile()).unwrap());
+        let (read_format, body) = CacheFormat::unwrap(&stored).unwrap();
+        assert_eq!(read_format, format);
+        assert_eq!(read_format.deserialize::<UserProfile>(body).unwrap(), profile());
+    }
+
+    #[test]
+    fn json_round_trips_without_header() {
+        round_trip(CacheFormat::Json);
+        let stored = CacheFormat::Json.wrap(JsonCodec.serialize(&profile()).unwrap());
+        assert_eq!(stored.first(), Some(&b'{'));
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn msgpack_round_trips_with_header() {
+        round_trip(CacheFormat::MessagePack);
+        let stored = CacheFormat::MessagePack.wrap(MessagePackCodec.serialize(&profile()).unwrap());
+        assert_eq!(&stored[..2], &[FORMAT_MAGIC, MessagePackCodec.id()]);
+    }
+
+    #[test]
+    fn unknown_format_id_is_an_error() {
+        assert!(CacheFormat::unwrap(&[FORMAT_MAGIC, 0xFF, b'x']).is_err());
+    }
+}
+
@@ -1,12 +1,177 @@
 use crate::cli::Env;
 use anyhow::Result as AnyResult;
 pub use redis::{aio::Connection as AsyncConnection, Client, aio::ConnectionManager as AsyncConnManager};
+pub use redis::cluster_async::ClusterConnection as AsyncClusterConnection;
+use redis::aio::ConnectionLike;
+use redis::cluster::ClusterClient;
+use redis::sentinel::Sentinel;
+use redis::{Cmd, ErrorKind, Pipeline, RedisError, RedisFuture, RedisResult, Value};
 use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+use url::Url;
 use log::{info, warn};
+use tokio::sync::{Mutex, OnceCell};
 
 pub const ENV_REDIS_URL: &str = "REDIS_URL"; // full connection string including timeout, credentials, and schema/namespace
 pub const ENV_CACHE_ENABLED: &str = "CACHE_ENABLED"; // enable/disable caching
 pub const ENV_CACHE_TTL_SECONDS: &str = "CACHE_TTL_SECONDS"; // cache expiration time
+pub const ENV_REDIS_KEY_NAMESPACE: &str = "REDIS_KEY_NAMESPACE"; // prefix applied to every cache key
+pub const ENV_CACHE_KEY_HASH_THRESHOLD: &str = "CACHE_KEY_HASH_THRESHOLD"; // max serialized request length kept readable in keys
+pub const ENV_CACHE_SKIP_ON_KEY_ERROR: &str = "CACHE_SKIP_ON_KEY_ERROR"; // bypass the cache instead of failing when a key cannot be generated
+pub const ENV_REDIS_LOG_REDACT: &str = "REDIS_LOG_REDACT"; // log hashed keys and no values
+pub const ENV_CACHE_FORMAT: &str = "REDIS_CACHE_FORMAT"; // json or msgpack
+pub const ENV_CACHE_JSON_STYLE: &str = "REDIS_CACHE_JSON_STYLE"; // compact, pretty or skip_nulls
+pub const ENV_CACHE_COMPRESSION: &str = "REDIS_CACHE_COMPRESSION"; // none, gzip or zstd
+pub const ENV_CACHE_ENCRYPTION_KEY: &str = "REDIS_CACHE_ENCRYPTION_KEY"; // hex-encoded 32-byte AES-256-GCM key
+pub const ENV_CACHE_COMPRESSION_THRESHOLD: &str = "REDIS_CACHE_COMPRESSION_THRESHOLD"; // minimum payload size in bytes to compress
+pub const ENV_CACHE_MAX_VALUE_BYTES: &str = "REDIS_CACHE_MAX_VALUE_BYTES"; // largest encoded value to cache, 0 for no limit
+pub const ENV_REDIS_MAX_VALUE_BYTES: &str = "REDIS_MAX_VALUE_BYTES"; // alias for REDIS_CACHE_MAX_VALUE_BYTES
+pub const ENV_CACHE_OVERSIZE_POLICY: &str = "REDIS_CACHE_OVERSIZE_POLICY"; // reject or skip values over the size limit
+pub const ENV_CACHE_LOCAL_CAPACITY: &str = "REDIS_CACHE_LOCAL_CAPACITY"; // entries kept in the in-process L1, 0 disables it
+pub const ENV_CACHE_LOCAL_TTL_MS: &str = "REDIS_CACHE_LOCAL_TTL_MS"; // how long an L1 entry may be served
+pub const ENV_REDIS_CLIENT_TRACKING: &str = "REDIS_CLIENT_TRACKING"; // invalidate the L1 through CLIENT TRACKING
+pub const ENV_REDIS_RESP3: &str = "REDIS_RESP3"; // alias for REDIS_CLIENT_TRACKING; connections stay on RESP2
+pub const ENV_REDIS_AUDIT_KEY: &str = "REDIS_AUDIT_KEY"; // stream recording cache mutations, unset disables auditing
+pub const ENV_REDIS_AUDIT_MAX_LEN: &str = "REDIS_AUDIT_MAX_LEN"; // approximate cap on audit stream entries
+pub const ENV_CACHE_STAMPEDE_LOCK_MS: &str = "CACHE_STAMPEDE_LOCK_MS"; // get_or_set lock lifetime and wait, 0 disables the lock
+pub const ENV_CACHE_SCAN_COUNT: &str = "CACHE_SCAN_COUNT"; // SCAN batch size hint for pattern operations
+pub const ENV_REDIS_REPLICA_URLS: &str = "REDIS_REPLICA_URLS"; // comma-separated read replica URLs
+pub const ENV_REDIS_SHARD_URLS: &str = "REDIS_SHARD_URLS"; // comma-separated independent Redis URLs for client-side sharding
+pub const ENV_REDIS_FALLBACK_URL: &str = "REDIS_FALLBACK_URL"; // secondary Redis used when the primary is unreachable
+pub const ENV_REDIS_FALLBACK_MIRROR_WRITES: &str = "REDIS_FALLBACK_MIRROR_WRITES"; // also write cache entries to the fallback
+pub const ENV_IDEMPOTENT_EXPIRY_IN_SEC: &str = "IDEMPOTENT_EXPIRY_IN_SEC"; // default window for set_with_options
+pub const ENV_REDIS_MAX_RETRIES: &str = "REDIS_MAX_RETRIES"; // retries for transient command failures
+pub const ENV_REDIS_RETRY_BASE_DELAY_MS: &str = "REDIS_RETRY_BASE_DELAY_MS"; // initial retry backoff
+pub const ENV_REDIS_BREAKER_THRESHOLD: &str = "REDIS_BREAKER_THRESHOLD"; // consecutive failures before failing fast, 0 disables
+pub const ENV_REDIS_BREAKER_COOLDOWN_MS: &str = "REDIS_BREAKER_COOLDOWN_MS"; // how long to fail fast before probing again
+pub const ENV_REDIS_CONNECT_TIMEOUT_MS: &str = "REDIS_CONNECT_TIMEOUT_MS"; // connection establishment timeout
+pub const ENV_REDIS_CLUSTER: &str = "REDIS_CLUSTER"; // enable/disable cluster mode
+pub const ENV_REDIS_NODES: &str = "REDIS_NODES"; // comma-separated cluster node URLs
+pub const ENV_REDIS_SENTINEL_NODES: &str = "REDIS_SENTINEL_NODES"; // comma-separated Sentinel URLs
+pub const ENV_REDIS_SENTINEL_MASTER: &str = "REDIS_SENTINEL_MASTER"; // master name monitored by the Sentinels
+
+/// Shared manager behind `get_shared_conn_manager`, emptied by `shutdown_shared_conn_manager`.
+/// Publishing only takes the uncontended read lock once it is initialized.
+static SHARED_CONN_MANAGER: OnceCell<RwLock<Option<AsyncConnManager>>> = OnceCell::const_new();
+static SHARED_CONN_CLOSED: AtomicBool = AtomicBool::new(false);
+static SHARED_CONFIG: OnceLock<RedisConfig> = OnceLock::new();
+
+/// A Redis connection to a single node, a cluster, or a Sentinel-managed master.
+///
+/// Implements `ConnectionLike`, so all `AsyncCommands` work against any variant.
+/// Cloning is cheap and clones share the underlying connection.
+#[derive(Clone)]
+pub enum RedisConn {
+    Single(AsyncConnManager),
+    Cluster(AsyncClusterConnection),
+    Sentinel(SentinelConn),
+}
+
+/// Connection to the current master of a Sentinel-monitored group.
+///
+/// Commands that fail with a connection error, or with `READONLY` because the old master
+/// was demoted, trigger a fresh master lookup through the Sentinels and are retried once
+/// against the new master.
+#[derive(Clone)]
+pub struct SentinelConn {
+    sentinel: Arc<Mutex<Sentinel>>,
+    master_name: String,
+    conn: AsyncConnManager,
+}
+
+impl SentinelConn {
+    /// Name of the master group this connection follows
+    pub fn master_name(&self) -> &str {
+        &self.master_name
+    }
+
+    async fn reconnect(&mut self) -> RedisResult<()> {
+        let mut sentinel = self.sentinel.lock().await;
+        self.conn = resolve_sentinel_master(&mut sentinel, &self.master_name).await?;
+        Ok(())
+    }
+}
+
+/// Whether an error means the connection no longer points at a writable master
+fn needs_failover(e: &RedisError) -> bool {
+    e.is_io_error() || e.is_connection_dropped() || e.is_connection_refusal() || e.kind() == ErrorKind::ReadOnly
+}
+
+async fn resolve_sentinel_master(sentinel: &mut Sentinel, master_name: &str) -> RedisResult<AsyncConnManager> {
+    let client = sentinel.async_master_for(master_name, None).await?;
+    info!("Sentinel master {} resolved to {}", master_name, client.get_connection_info().addr);
+    AsyncConnManager::new(client).await
+}
+
+impl ConnectionLike for SentinelConn {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        Box::pin(async move {
+            match self.conn.req_packed_command(cmd).await {
+                Err(e) if needs_failover(&e) => {
+                    warn!("Sentinel master {} unavailable, re-resolving: {}", self.master_name, e);
+                    self.reconnect().await?;
+                    self.conn.req_packed_command(cmd).await
+                }
+                result => result,
+            }
+        })
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        Box::pin(async move {
+            match self.conn.req_packed_commands(cmd, offset, count).await {
+                Err(e) if needs_failover(&e) => {
+                    warn!("Sentinel master {} unavailable, re-resolving: {}", self.master_name, e);
+                    self.reconnect().await?;
+                    self.conn.req_packed_commands(cmd, offset, count).await
+                }
+                result => result,
+            }
+        })
+    }
+
+    fn get_db(&self) -> i64 {
+        self.conn.get_db()
+    }
+}
+
+impl ConnectionLike for RedisConn {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        match self {
+            RedisConn::Single(conn) => conn.req_packed_command(cmd),
+            RedisConn::Cluster(conn) => conn.req_packed_command(cmd),
+            RedisConn::Sentinel(conn) => conn.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        match self {
+            RedisConn::Single(conn) => conn.req_packed_commands(cmd, offset, count),
+            RedisConn::Cluster(conn) => conn.req_packed_commands(cmd, offset, count),
+            RedisConn::Sentinel(conn) => conn.req_packed_commands(cmd, offset, count),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            RedisConn::Single(conn) => conn.get_db(),
+            RedisConn::Cluster(conn) => conn.get_db(),
+            RedisConn::Sentinel(conn) => conn.get_db(),
+        }
+    }
+}
 
 pub fn init_redis_vars() {
     let _env = Env::from_env();
@@ -26,24 +191,456 @@ pub fn get_cache_ttl() -> u64 {
         .unwrap_or(3600)
 }
 
+pub fn get_key_namespace() -> Option<String> {
+    env::var(ENV_REDIS_KEY_NAMESPACE)
+        .ok()
+        .filter(|namespace| !namespace.is_empty())
+}
+
+pub fn get_key_hash_threshold() -> usize {
+    env::var(ENV_CACHE_KEY_HASH_THRESHOLD)
+        .unwrap_or_else(|_| "0".to_string())
+        .parse()
+        .unwrap_or(0)
+}
+
+pub fn is_skip_on_key_error_enabled() -> bool {
+    env::var(ENV_CACHE_SKIP_ON_KEY_ERROR)
+        .unwrap_or_else(|_| "false".to_string())
+        .to_lowercase() == "true"
+}
+
+pub fn is_log_redact_enabled() -> bool {
+    env::var(ENV_REDIS_LOG_REDACT)
+        .unwrap_or_else(|_| "false".to_string())
+        .to_lowercase() == "true"
+}
+
+pub fn get_compression_threshold() -> usize {
+    env::var(ENV_CACHE_COMPRESSION_THRESHOLD)
+        .unwrap_or_else(|_| "1024".to_string())
+        .parse()
+        .unwrap_or(1024)
+}
+
+pub fn get_max_value_bytes() -> usize {
+    env::var(ENV_CACHE_MAX_VALUE_BYTES)
+        .or_else(|_| env::var(ENV_REDIS_MAX_VALUE_BYTES))
+        .unwrap_or_else(|_| "0".to_string())
+        .parse()
+        .unwrap_or(0)
+}
+
+pub fn get_local_cache_capacity() -> usize {
+    env::var(ENV_CACHE_LOCAL_CAPACITY)
+        .unwrap_or_else(|_| "0".to_string())
+        .parse()
+        .unwrap_or(0)
+}
+
+pub fn get_local_cache_ttl() -> Duration {
+    let millis = env::var(ENV_CACHE_LOCAL_TTL_MS)
+        .unwrap_or_else(|_| "1000".to_string())
+        .parse()
+        .unwrap_or(1000);
+    Duration::from_millis(millis)
+}
+
+/// `REDIS_RESP3` turns on the same RESP2 tracking: the redis 0.23 client cannot speak RESP3,
+/// so no RESP3 connection is ever opened
+pub fn is_client_tracking_enabled() -> bool {
+    env::var(ENV_REDIS_CLIENT_TRACKING)
+        .or_else(|_| env::var(ENV_REDIS_RESP3))
+        .unwrap_or_else(|_| "false".to_string())
+        .to_lowercase() == "true"
+}
+
+pub fn get_stampede_lock_ttl() -> Duration {
+    let millis = env::var(ENV_CACHE_STAMPEDE_LOCK_MS)
+        .unwrap_or_else(|_| "5000".to_string())
+        .parse()
+        .unwrap_or(5000);
+    Duration::from_millis(millis)
+}
+
+pub fn get_audit_key() -> Option<String> {
+    env::var(ENV_REDIS_AUDIT_KEY)
+        .ok()
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+}
+
+pub fn get_audit_max_len() -> usize {
+    env::var(ENV_REDIS_AUDIT_MAX_LEN)
+        .unwrap_or_else(|_| "10000".to_string())
+        .parse()
+        .unwrap_or(10000)
+}
+
+/// Idempotency window used by `set_with_options`, from the shared config
+pub fn get_idempotent_expiry() -> Duration {
+    shared_config().idempotent_expiry
+}
+
+fn idempotent_expiry_from_env() -> Duration {
+    let secs = env::var(ENV_IDEMPOTENT_EXPIRY_IN_SEC)
+        .unwrap_or_else(|_| "120".to_string())
+        .parse()
+        .unwrap_or(120);
+    Duration::from_secs(secs)
+}
+
+pub fn get_max_retries() -> u32 {
+    env::var(ENV_REDIS_MAX_RETRIES)
+        .unwrap_or_else(|_| "3".to_string())
+        .parse()
+        .unwrap_or(3)
+}
+
+pub fn get_retry_base_delay() -> Duration {
+    let millis = env::var(ENV_REDIS_RETRY_BASE_DELAY_MS)
+        .unwrap_or_else(|_| "50".to_string())
+        .parse()
+        .unwrap_or(50);
+    Duration::from_millis(millis)
+}
+
+pub fn get_scan_count() -> usize {
+    env::var(ENV_CACHE_SCAN_COUNT)
+        .unwrap_or_else(|_| "100".to_string())
+        .parse()
+        .unwrap_or(100)
+}
+
+pub fn is_cluster_enabled() -> bool {
+    env::var(ENV_REDIS_CLUSTER)
+        .unwrap_or_else(|_| "false".to_string())
+        .to_lowercase() == "true"
+}
+
+pub fn get_cluster_nodes() -> Vec<String> {
+    env::var(ENV_REDIS_NODES)
+        .unwrap_or_default()
+        .split(',')
+        .map(|node| node.trim().to_string())
+        .filter(|node| !node.is_empty())
+        .collect()
+}
+
+pub fn get_sentinel_nodes() -> Vec<String> {
+    env::var(ENV_REDIS_SENTINEL_NODES)
+        .unwrap_or_default()
+        .split(',')
+        .map(|node| node.trim().to_string())
+        .filter(|node| !node.is_empty())
+        .collect()
+}
+
+pub fn get_sentinel_master() -> Option<String> {
+    env::var(ENV_REDIS_SENTINEL_MASTER)
+        .ok()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+pub fn get_replica_urls() -> Vec<String> {
+    env::var(ENV_REDIS_REPLICA_URLS)
+        .unwrap_or_default()
+        .split(',')
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect()
+}
+
+pub fn get_shard_urls() -> Vec<String> {
+    env::var(ENV_REDIS_SHARD_URLS)
+        .unwrap_or_default()
+        .split(',')
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect()
+}
+
+pub fn get_breaker_threshold() -> u32 {
+    env::var(ENV_REDIS_BREAKER_THRESHOLD)
+        .unwrap_or_else(|_| "5".to_string())
+        .parse()
+        .unwrap_or(5)
+}
+
+pub fn get_breaker_cooldown() -> Duration {
+    let millis = env::var(ENV_REDIS_BREAKER_COOLDOWN_MS)
+        .unwrap_or_else(|_| "30000".to_string())
+        .parse()
+        .unwrap_or(30000);
+    Duration::from_millis(millis)
+}
+
+pub fn get_fallback_url() -> Option<String> {
+    env::var(ENV_REDIS_FALLBACK_URL)
+        .ok()
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+}
+
+pub fn is_fallback_mirror_enabled() -> bool {
+    env::var(ENV_REDIS_FALLBACK_MIRROR_WRITES)
+        .unwrap_or_else(|_| "false".to_string())
+        .to_lowercase() == "true"
+}
+
+pub fn get_connect_timeout() -> Duration {
+    let millis = env::var(ENV_REDIS_CONNECT_TIMEOUT_MS)
+        .unwrap_or_else(|_| "5000".to_string())
+        .parse()
+        .unwrap_or(5000);
+    Duration::from_millis(millis)
+}
+
+/// Connection and cache settings, read from the environment by default.
+///
+/// Building managers from separate `RedisConfig` values lets several differently
+/// configured caches share one process without touching process-wide env vars.
+#[derive(Debug, Clone)]
+pub struct RedisConfig {
+    pub redis_url: Option<String>,
+    pub cache_enabled: bool,
+    pub cache_ttl_seconds: u64,
+    pub key_namespace: Option<String>,
+    pub key_hash_threshold: usize,
+    pub skip_cache_on_key_error: bool,
+    pub log_redact: bool,
+    pub compression_threshold: usize,
+    pub max_value_bytes: usize,
+    pub local_cache_capacity: usize,
+    pub local_cache_ttl: Duration,
+    pub client_tracking: bool,
+    pub stampede_lock_ttl: Duration,
+    pub audit_key: Option<String>,
+    pub audit_max_len: usize,
+    pub scan_count: usize,
+    pub idempotent_expiry: Duration,
+    pub max_retries: u32,
+    pub retry_base_delay: Duration,
+    pub breaker_threshold: u32,
+    pub breaker_cooldown: Duration,
+    pub connect_timeout: Duration,
+    pub cluster_enabled: bool,
+    pub cluster_nodes: Vec<String>,
+    pub sentinel_nodes: Vec<String>,
+    pub sentinel_master: Option<String>,
+    pub replica_urls: Vec<String>,
+    pub shard_urls: Vec<String>,
+    pub fallback_url: Option<String>,
+    pub mirror_writes_to_fallback: bool,
+}
+
+impl RedisConfig {
+    /// Read every setting from its environment variable, using the usual defaults
+    pub fn from_env() -> Self {
+        Self {
+            redis_url: env::var(ENV_REDIS_URL).ok(),
+            cache_enabled: is_cache_enabled(),
+            cache_ttl_seconds: get_cache_ttl(),
+            key_namespace: get_key_namespace(),
+            key_hash_threshold: get_key_hash_threshold(),
+            skip_cache_on_key_error: is_skip_on_key_error_enabled(),
+            log_redact: is_log_redact_enabled(),
+            compression_threshold: get_compression_threshold(),
+            max_value_bytes: get_max_value_bytes(),
+            local_cache_capacity: get_local_cache_capacity(),
+            local_cache_ttl: get_local_cache_ttl(),
+            client_tracking: is_client_tracking_enabled(),
+            stampede_lock_ttl: get_stampede_lock_ttl(),
+            audit_key: get_audit_key(),
+            audit_max_len: get_audit_max_len(),
+            scan_count: get_scan_count(),
+            idempotent_expiry: idempotent_expiry_from_env(),
+            max_retries: get_max_retries(),
+            retry_base_delay: get_retry_base_delay(),
+            breaker_threshold: get_breaker_threshold(),
+            breaker_cooldown: get_breaker_cooldown(),
+            connect_timeout: get_connect_timeout(),
+            cluster_enabled: is_cluster_enabled(),
+            cluster_nodes: get_cluster_nodes(),
+            sentinel_nodes: get_sentinel_nodes(),
+            sentinel_master: get_sentinel_master(),
+            replica_urls: get_replica_urls(),
+            shard_urls: get_shard_urls(),
+            fallback_url: get_fallback_url(),
+            mirror_writes_to_fallback: is_fallback_mirror_enabled(),
+        }
+    }
+
+    /// Environment defaults, but pointing at `redis_url`
+    pub fn with_url(redis_url: &str) -> Self {
+        Self {
+            redis_url: Some(redis_url.to_string()),
+            ..Self::from_env()
+        }
+    }
+
+    /// The same settings with `username` and `password` (empty `username` for the default
+    /// user) in place of the credentials in `redis_url` and the cluster node URLs.
+    ///
+    /// Sentinel deployments are rejected, as the master's credentials come from Sentinel.
+    pub fn with_credentials(&self, username: &str, password: &str) -> AnyResult<Self> {
+        if self.sentinel_master.is_some() {
+            return Err(anyhow::anyhow!("Updating credentials is not supported with Sentinel"));
+        }
+        let redis_url = match &self.redis_url {
+            Some(url) => Some(url_with_credentials(url, username, password)?),
+            None => None,
+        };
+        let cluster_nodes = self
+            .cluster_nodes
+            .iter()
+            .map(|node| url_with_credentials(node, username, password))
+            .collect::<AnyResult<Vec<_>>>()?;
+        Ok(Self {
+            redis_url,
+            cluster_nodes,
+            ..self.clone()
+        })
+    }
+
+    /// Connect using cluster mode, Sentinel discovery or a single node, in that order
+    pub async fn connect(&self) -> AnyResult<RedisConn> {
+        if self.cluster_enabled {
+            if self.cluster_nodes.is_empty() {
+                return Err(anyhow::anyhow!("Environment variable \"REDIS_NODES\" is not set!"));
+            }
+            return Ok(RedisConn::Cluster(cluster_conn_with_timeout(&self.cluster_nodes, self.connect_timeout).await?));
+        }
+
+        if let Some(master_name) = &self.sentinel_master {
+            if self.sentinel_nodes.is_empty() {
+                return Err(anyhow::anyhow!("Environment variable \"REDIS_SENTINEL_NODES\" is not set!"));
+            }
+            let conn = sentinel_conn_with_timeout(&self.sentinel_nodes, master_name, self.connect_timeout).await?;
+            return Ok(RedisConn::Sentinel(conn));
+        }
+
+        match &self.redis_url {
+            Some(redis_uri) => Ok(RedisConn::Single(conn_manager_with_timeout(redis_uri, self.connect_timeout).await?)),
+            None => Err(anyhow::anyhow!("Environment variable \"REDIS_URL\" is not set!")),
+        }
+    }
+
+    /// Open a dedicated, non-multiplexed connection to `redis_url`, as needed by pub/sub,
+    /// blocking commands and `WATCH`
+    pub async fn connect_dedicated(&self) -> AnyResult<AsyncConnection> {
+        let redis_uri = self.redis_url.as_deref().ok_or_else(|| anyhow::anyhow!("Environment variable \"REDIS_URL\" is not set!"))?;
+        let client = Client::open(redis_uri)?;
+        let conn = tokio::time::timeout(self.connect_timeout, client.get_async_connection())
+            .await
+            .map_err(|_| anyhow::anyhow!("Timed out connecting to Redis after {}ms", self.connect_timeout.as_millis()))??;
+        Ok(conn)
+    }
+
+    /// Like `connect`, but returns `None` when caching is disabled or the connection fails
+    pub async fn connect_optional(&self) -> Option<RedisConn> {
+        if !self.cache_enabled {
+            info!("Redis caching is disabled");
+            return None;
+        }
+
+        match self.connect().await {
+            Ok(conn) => {
+                info!("Redis connection created successfully");
+                Some(conn)
+            }
+            Err(e) => {
+                warn!("Failed to create Redis connection: {}. Continuing without cache.", e);
+                None
+            }
+        }
+    }
+
+    /// Connect to each read replica, skipping any that are unreachable
+    pub async fn connect_replicas(&self) -> Vec<RedisConn> {
+        let mut replicas = Vec::with_capacity(self.replica_urls.len());
+        for url in &self.replica_urls {
+            match conn_manager_with_timeout(url, self.connect_timeout).await {
+                Ok(conn) => replicas.push(RedisConn::Single(conn)),
+                Err(e) => warn!("Failed to connect to read replica {}: {}", url, e),
+            }
+        }
+        if !replicas.is_empty() {
+            info!("Reading cache entries from {} replicas", replicas.len());
+        }
+        replicas
+    }
+
+    /// Connect to the fallback Redis, or `None` if it is not configured or unreachable
+    pub async fn connect_fallback(&self) -> Option<RedisConn> {
+        let url = self.fallback_url.as_ref()?;
+        match conn_manager_with_timeout(url, self.connect_timeout).await {
+            Ok(conn) => {
+                info!("Fallback Redis connection created successfully");
+                Some(RedisConn::Single(conn))
+            }
+            Err(e) => {
+                warn!("Failed to connect to fallback Redis {}: {}", url, e);
+                None
+            }
+        }
+    }
+}
+
+/// Replace the credentials in a Redis URL, keeping host, port, database and TLS settings.
+/// Unix socket URLs carry them in the `user` and `pass` query parameters.
+fn url_with_credentials(redis_uri: &str, username: &str, password: &str) -> AnyResult<String> {
+    let mut url = Url::parse(redis_uri)?;
+    if url.scheme() == "redis+unix" || url.scheme() == "unix" {
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(name, _)| name != "user" && name != "pass")
+            .map(|(name, value)| (name.into_owned(), value.into_owned()))
+            .collect();
+        let mut query = url.query_pairs_mut();
+        query.clear().extend_pairs(pairs);
+        if !username.is_empty() {
+            query.append_pair("user", username);
+        }
+        if !password.is_empty() {
+            query.append_pair("pass", password);
+        }
+        drop(query);
+        return Ok(url.to_string());
+    }
+
+    url.set_username(username)
+        .map_err(|_| anyhow::anyhow!("Cannot set credentials on Redis URL"))?;
+    url.set_password(Some(password).filter(|password| !password.is_empty()))
+        .map_err(|_| anyhow::anyhow!("Cannot set credentials on Redis URL"))?;
+    Ok(url.to_string())
+}
+
 pub async fn create_redis_pool(redis_uri: &str) -> AnyResult<AsyncConnection> {
     let client = Client::open(redis_uri)?;
-    let async_conn = client.get_async_connection().await?;
+    let timeout = get_connect_timeout();
+    let async_conn = tokio::time::timeout(timeout, client.get_async_connection())
+        .await
+        .map_err(|_| anyhow::anyhow!("Timed out connecting to Redis after {}ms", timeout.as_millis()))??;
     Ok(async_conn)
 }
 
+/// Open a dedicated connection to the shared config's `redis_url`, for subscriptions and
+/// other commands that hold a connection to themselves
 pub async fn get_redis_pool() -> AnyResult<AsyncConnection> {
-    if let Ok(env_redis_uri) = env::var(ENV_REDIS_URL) {
-        let redis_uri = env_redis_uri;
-        return create_redis_pool(&redis_uri).await;
-    }
-
-    Err(anyhow::anyhow!("Environment variable \"REDIS_URL\" is not set!"))
+    shared_config().connect_dedicated().await
 }
 
 pub async fn create_redis_conn_manager(redis_uri: &str) -> AnyResult<AsyncConnManager> {
+    conn_manager_with_timeout(redis_uri, get_connect_timeout()).await
+}
+
+async fn conn_manager_with_timeout(redis_uri: &str, timeout: Duration) -> AnyResult<AsyncConnManager> {
     let client = Client::open(redis_uri)?;
-    let conn = AsyncConnManager::new(client).await?;
+    let conn = tokio::time::timeout(timeout, AsyncConnManager::new(client))
+        .await
+        .map_err(|_| anyhow::anyhow!("Timed out connecting to Redis after {}ms", timeout.as_millis()))??;
     
     Ok(conn)
 }
@@ -57,6 +654,117 @@ pub async fn get_redis_conn_manager() -> AnyResult<AsyncConnManager> {
     Err(anyhow::anyhow!("Environment variable \"REDIS_URL\" is not set!"))
 }
 
+/// Settings behind the shared connection manager and the free functions that use it or
+/// `get_redis_pool` (publishing and subscribing, the generic key/value operations, streams,
+/// rate limiting and keyspace events).
+///
+/// This is the config passed to `init_shared_config`, or `RedisConfig::from_env` read once
+/// on first use. `CacheManager`s take their own config instead.
+pub fn shared_config() -> &'static RedisConfig {
+    SHARED_CONFIG.get_or_init(RedisConfig::from_env)
+}
+
+/// Use `config` instead of the environment for the shared connection manager and the free
+/// functions listed under `shared_config`.
+///
+/// Call this at startup, before anything reads the shared config; it fails once the
+/// settings are fixed.
+pub fn init_shared_config(config: RedisConfig) -> AnyResult<()> {
+    SHARED_CONFIG
+        .set(config)
+        .map_err(|_| anyhow::anyhow!("Shared Redis config is already initialized"))
+}
+
+/// Initialize the process-wide shared connection manager from `shared_config`.
+///
+/// Call this at startup to warm the connection before the first publish. The
+/// manager is created at most once; later calls return a clone of it. Clones are
+/// cheap and safe to use concurrently from any task, since they multiplex over the
+/// same underlying connection and reconnect automatically.
+pub async fn init_shared_conn_manager() -> AnyResult<AsyncConnManager> {
+    let closed = || anyhow::anyhow!("Shared Redis connection is closed");
+    if SHARED_CONN_CLOSED.load(Ordering::Acquire) {
+        return Err(closed());
+    }
+    let shared = SHARED_CONN_MANAGER
+        .get_or_try_init(|| async {
+            let config = shared_config();
+            let redis_uri = config.redis_url.as_deref().ok_or_else(|| anyhow::anyhow!("Environment variable \"REDIS_URL\" is not set!"))?;
+            let conn = conn_manager_with_timeout(redis_uri, config.connect_timeout).await?;
+            Ok::<_, anyhow::Error>(RwLock::new(Some(conn)))
+        })
+        .await?;
+    // A shutdown that raced the first connection must not leave it open
+    if SHARED_CONN_CLOSED.load(Ordering::Acquire) {
+        shared.write().unwrap_or_else(|e| e.into_inner()).take();
+        return Err(closed());
+    }
+    let conn = shared.read().unwrap_or_else(|e| e.into_inner()).clone();
+    conn.ok_or_else(closed)
+}
+
+/// Close the shared connection manager used for publishing, streams and rate limiting.
+///
+/// Later calls that need it fail with a "closed" error instead of reconnecting. Operations
+/// already in flight hold their own handle and finish normally; the connection is released
+/// once the last of them completes.
+pub fn shutdown_shared_conn_manager() {
+    SHARED_CONN_CLOSED.store(true, Ordering::Release);
+    let previous = SHARED_CONN_MANAGER
+        .get()
+        .and_then(|shared| shared.write().unwrap_or_else(|e| e.into_inner()).take());
+    if previous.is_some() {
+        info!("Shared Redis connection closed");
+    }
+}
+
+/// Get a handle to the shared connection manager, initializing it on first use.
+pub async fn get_shared_conn_manager() -> AnyResult<AsyncConnManager> {
+    init_shared_conn_manager().await
+}
+
+pub async fn create_redis_cluster_conn(nodes: &[String]) -> AnyResult<AsyncClusterConnection> {
+    cluster_conn_with_timeout(nodes, get_connect_timeout()).await
+}
+
+async fn cluster_conn_with_timeout(nodes: &[String], timeout: Duration) -> AnyResult<AsyncClusterConnection> {
+    let client = ClusterClient::new(nodes.to_vec())?;
+    let conn = tokio::time::timeout(timeout, client.get_async_connection())
+        .await
+        .map_err(|_| anyhow::anyhow!("Timed out connecting to Redis cluster after {}ms", timeout.as_millis()))??;
+
+    Ok(conn)
+}
+
+/// Ask the Sentinels for the current master of `master_name` and connect to it
+pub async fn create_redis_sentinel_conn(nodes: &[String], master_name: &str) -> AnyResult<SentinelConn> {
+    sentinel_conn_with_timeout(nodes, master_name, get_connect_timeout()).await
+}
+
+async fn sentinel_conn_with_timeout(nodes: &[String], master_name: &str, timeout: Duration) -> AnyResult<SentinelConn> {
+    let mut sentinel = Sentinel::build(nodes.to_vec())?;
+    let conn = tokio::time::timeout(timeout, resolve_sentinel_master(&mut sentinel, master_name))
+        .await
+        .map_err(|_| anyhow::anyhow!("Timed out resolving Sentinel master after {}ms", timeout.as_millis()))??;
+
+    Ok(SentinelConn {
+        sentinel: Arc::new(Mutex::new(sentinel)),
+        master_name: master_name.to_string(),
+        conn,
+    })
+}
+
+/// Get a connection to Redis, using cluster mode when `REDIS_CLUSTER` is enabled,
+/// Sentinel discovery when `REDIS_SENTINEL_MASTER` is set, and falling back to the
+/// single node at `REDIS_URL` otherwise.
+pub async fn get_redis_conn() -> AnyResult<RedisConn> {
+    RedisConfig::from_env().connect().await
+}
+
+pub async fn get_redis_conn_optional() -> Option<RedisConn> {
+    RedisConfig::from_env().connect_optional().await
+}
+
 pub async fn get_redis_conn_manager_optional() -> Option<AsyncConnManager> {
     if !is_cache_enabled() {
         info!("Redis caching is disabled");
@@ -73,4 +781,78 @@ pub async fn get_redis_conn_manager_optional() -> Option<AsyncConnManager> {
             None
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::silent_server;
+
+    #[tokio::test]
+    async fn connect_times_out_when_server_never_answers() {
+        let redis_uri = silent_server().await;
+        let timeout = Duration::from_millis(200);
+        let started = std::time::Instant::now();
+
+        let result = conn_manager_with_timeout(&redis_uri, timeout).await;
+        let error = result.err().expect("connecting to a silent server must fail");
+        assert!(error.to_string().contains("Timed out connecting to Redis after 200ms"), "{}", error);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis Cluster at REDIS_NODES"]
+    async fn cluster_keys_spread_over_several_masters() {
+        let config = RedisConfig {
+            cluster_enabled: true,
+            ..RedisConfig::from_env()
+        };
+        let mut conn = config.connect().await.expect("these tests need a Redis Cluster at REDIS_NODES");
+        let keys: Vec<String> = (0..50).map(|i| format!("{}:{}", crate::testing::live_prefix(), i)).collect();
+        for key in &keys {
+            redis::cmd("SET").arg(key).arg(key).query_async::<_, ()>(&mut conn).await.unwrap();
+        }
+        for key in &keys {
+            let value: String = redis::cmd("GET").arg(key).query_async(&mut conn).await.unwrap();
+            assert_eq!(&value, key);
+        }
+
+        // Each slot range names the master serving it first
+        let ranges: Vec<Vec<redis::Value>> = redis::cmd("CLUSTER").arg("SLOTS").query_async(&mut conn).await.unwrap();
+        let ranges: Vec<(u16, u16, String)> = ranges
+            .iter()
+            .map(|range| {
+                let master: Vec<redis::Value> = redis::from_redis_value(&range[2]).unwrap();
+                let address = format!("{}:{}", redis::from_redis_value::<String>(&master[0]).unwrap(), redis::from_redis_value::<u16>(&master[1]).unwrap());
+                (redis::from_redis_value(&range[0]).unwrap(), redis::from_redis_value(&range[1]).unwrap(), address)
+            })
+            .collect();
+        let mut masters = std::collections::HashSet::new();
+        for key in &keys {
+            let slot: u16 = redis::cmd("CLUSTER").arg("KEYSLOT").arg(key).query_async(&mut conn).await.unwrap();
+            let (_, _, master) = ranges.iter().find(|(start, end, _)| (*start..=*end).contains(&slot)).unwrap();
+            masters.insert(master.clone());
+        }
+        assert!(masters.len() > 1, "every key landed on {:?}", masters);
+        for key in &keys {
+            redis::cmd("DEL").arg(key).query_async::<_, ()>(&mut conn).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs Sentinels at REDIS_SENTINEL_NODES monitoring REDIS_SENTINEL_MASTER"]
+    async fn sentinels_resolve_the_master_by_name() {
+        let nodes = get_sentinel_nodes();
+        let master = get_sentinel_master().expect("these tests need REDIS_SENTINEL_MASTER");
+        let mut conn = create_redis_sentinel_conn(&nodes, &master).await.unwrap();
+
+        let role: Vec<redis::Value> = redis::cmd("ROLE").query_async(&mut conn).await.unwrap();
+        assert_eq!(redis::from_redis_value::<String>(&role[0]).unwrap(), "master");
+        let key = format!("{}:sentinel", crate::testing::live_prefix());
+        redis::cmd("SET").arg(&key).arg("ok").query_async::<_, ()>(&mut conn).await.unwrap();
+        redis::cmd("DEL").arg(&key).query_async::<_, ()>(&mut conn).await.unwrap();
+
+        assert!(create_redis_sentinel_conn(&nodes, "no-such-master").await.is_err());
+    }
+}
+
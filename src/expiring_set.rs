@@ -0,0 +1,112 @@
+use crate::trace::record_latency;
+use anyhow::Result as AnyResult;
+use redis::aio::ConnectionLike;
+use redis::AsyncCommands;
+use std::time::{Duration, Instant};
+
+// A set whose members expire independently, stored as a sorted set scored by each member's
+// expiry time in Unix milliseconds. Expiry times come from the local clock, so writers
+// should keep their clocks in sync.
+
+fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// Add `member`, or extend it, so that it expires `ttl` from now. Returns `true` if the
+/// member was not in the set before (expired members that were not cleaned up count as present).
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "ZADD", redis.latency_ms)
+))]
+pub async fn add<M, C>(key: String, member: M, ttl: Duration, mut conn: C) -> AnyResult<bool>
+where
+    M: redis::ToRedisArgs + Send + Sync,
+    C: ConnectionLike + Send,
+{
+    let expires_at = now_millis() + ttl.as_millis() as i64;
+    let start = Instant::now();
+    let added: u64 = conn.zadd(key, member, expires_at).await?;
+    record_latency(start);
+
+    Ok(added > 0)
+}
+
+/// Whether `member` is in the set and has not expired yet
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "ZSCORE", redis.latency_ms)
+))]
+pub async fn is_member<M, C>(key: String, member: M, mut conn: C) -> AnyResult<bool>
+where
+    M: redis::ToRedisArgs + Send + Sync,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let expires_at: Option<i64> = conn.zscore(key, member).await?;
+    record_latency(start);
+
+    Ok(matches!(expires_at, Some(expires_at) if expires_at > now_millis()))
+}
+
+/// Members that have not expired yet
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "ZRANGEBYSCORE", redis.latency_ms)
+))]
+pub async fn members<M, C>(key: String, mut conn: C) -> AnyResult<Vec<M>>
+where
+    M: redis::FromRedisValue,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let members = conn.zrangebyscore(key, format!("({}", now_millis()), "+inf").await?;
+    record_latency(start);
+
+    Ok(members)
+}
+
+/// Remove expired members, returning how many were removed. Expired members are already
+/// hidden from `is_member` and `members`; this only reclaims their memory.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "ZREMRANGEBYSCORE", redis.latency_ms)
+))]
+pub async fn cleanup<C>(key: String, mut conn: C) -> AnyResult<u64>
+where
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let removed = conn.zrembyscore(key, "-inf", now_millis()).await?;
+    record_latency(start);
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::live_conn;
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn members_drop_out_once_their_ttl_passes() {
+        let (mut conn, prefix) = live_conn().await;
+        let key = format!("{}:sessions", prefix);
+
+        assert!(add(key.clone(), "short", Duration::from_millis(200), conn.clone()).await.unwrap());
+        assert!(add(key.clone(), "long", Duration::from_secs(60), conn.clone()).await.unwrap());
+        assert!(!add(key.clone(), "long", Duration::from_secs(120), conn.clone()).await.unwrap());
+        assert!(is_member(key.clone(), "short", conn.clone()).await.unwrap());
+        let mut live: Vec<String> = members(key.clone(), conn.clone()).await.unwrap();
+        live.sort();
+        assert_eq!(live, ["long", "short"]);
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(!is_member(key.clone(), "short", conn.clone()).await.unwrap());
+        assert!(!is_member(key.clone(), "unknown", conn.clone()).await.unwrap());
+        assert_eq!(members::<String, _>(key.clone(), conn.clone()).await.unwrap(), ["long"]);
+        assert_eq!(cleanup(key.clone(), conn.clone()).await.unwrap(), 1);
+        assert_eq!(cleanup(key.clone(), conn.clone()).await.unwrap(), 0);
+        let _: () = conn.del(&key).await.unwrap();
+    }
+}
@@ -0,0 +1,145 @@
+use crate::trace::record_latency;
+use anyhow::Result as AnyResult;
+use redis::aio::ConnectionLike;
+use std::time::Instant;
+
+/// Distance unit for radius searches and returned distances
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeoUnit {
+    Meters,
+    Kilometers,
+    Miles,
+    Feet,
+}
+
+impl GeoUnit {
+    fn as_arg(&self) -> &'static str {
+        match self {
+            GeoUnit::Meters => "m",
+            GeoUnit::Kilometers => "km",
+            GeoUnit::Miles => "mi",
+            GeoUnit::Feet => "ft",
+        }
+    }
+}
+
+/// Add a member at the given coordinates, or move it. Returns 1 if the member is new.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "GEOADD", redis.latency_ms)
+))]
+pub async fn geo_add<M, C>(key: String, longitude: f64, latitude: f64, member: M, mut conn: C) -> AnyResult<u64>
+where
+    M: redis::ToRedisArgs + Send + Sync,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let added = redis::cmd("GEOADD")
+        .arg(key)
+        .arg(longitude)
+        .arg(latitude)
+        .arg(member)
+        .query_async(&mut conn)
+        .await?;
+    record_latency(start);
+
+    Ok(added)
+}
+
+/// Members within `radius` of a point, nearest first, with their distance in `unit`.
+///
+/// Uses `GEOSEARCH`, which requires Redis 6.2 or later.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "GEOSEARCH", redis.latency_ms)
+))]
+pub async fn geo_search_radius<M, C>(
+    key: String,
+    longitude: f64,
+    latitude: f64,
+    radius: f64,
+    unit: GeoUnit,
+    mut conn: C,
+) -> AnyResult<Vec<(M, f64)>>
+where
+    M: redis::FromRedisValue,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let members = redis::cmd("GEOSEARCH")
+        .arg(key)
+        .arg("FROMLONLAT")
+        .arg(longitude)
+        .arg(latitude)
+        .arg("BYRADIUS")
+        .arg(radius)
+        .arg(unit.as_arg())
+        .arg("ASC")
+        .arg("WITHDIST")
+        .query_async(&mut conn)
+        .await?;
+    record_latency(start);
+
+    Ok(members)
+}
+
+/// A member's `(longitude, latitude)`, or `None` if it is not in the set
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "GEOPOS", redis.latency_ms)
+))]
+pub async fn geo_pos<M, C>(key: String, member: M, mut conn: C) -> AnyResult<Option<(f64, f64)>>
+where
+    M: redis::ToRedisArgs + Send + Sync,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let positions: Vec<Option<(f64, f64)>> = redis::cmd("GEOPOS")
+        .arg(key)
+        .arg(member)
+        .query_async(&mut conn)
+        .await?;
+    record_latency(start);
+
+    Ok(positions.into_iter().next().flatten())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::live_conn;
+    use redis::AsyncCommands;
+
+    #[test]
+    fn units_use_the_redis_abbreviations() {
+        let units = [GeoUnit::Meters, GeoUnit::Kilometers, GeoUnit::Miles, GeoUnit::Feet];
+        assert_eq!(units.map(|unit| unit.as_arg()), ["m", "km", "mi", "ft"]);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn radius_search_returns_nearby_members_nearest_first() {
+        let (mut conn, prefix) = live_conn().await;
+        let key = format!("{}:drivers", prefix);
+        for (driver, longitude, latitude) in [
+            ("kota", 106.8133, -6.1352),
+            ("monas", 106.8272, -6.1754),
+            ("bandung", 107.6191, -6.9175),
+            ("thamrin", 106.8230, -6.1950),
+        ] {
+            assert_eq!(geo_add(key.clone(), longitude, latitude, driver, conn.clone()).await.unwrap(), 1);
+        }
+
+        let nearby = geo_search_radius::<String, _>(key.clone(), 106.8272, -6.1754, 5.0, GeoUnit::Kilometers, conn.clone()).await.unwrap();
+        let names: Vec<&str> = nearby.iter().map(|(driver, _)| driver.as_str()).collect();
+        assert_eq!(names, ["monas", "thamrin", "kota"]);
+        assert!(nearby[0].1 < 0.01);
+        assert!((2.0..2.5).contains(&nearby[1].1), "{}", nearby[1].1);
+        assert!((4.4..4.9).contains(&nearby[2].1), "{}", nearby[2].1);
+
+        let (longitude, latitude) = geo_pos(key.clone(), "bandung", conn.clone()).await.unwrap().unwrap();
+        assert!((longitude - 107.6191).abs() < 1e-4 && (latitude + 6.9175).abs() < 1e-4);
+        assert_eq!(geo_pos(key.clone(), "missing", conn.clone()).await.unwrap(), None);
+        let _: () = conn.del(&key).await.unwrap();
+    }
+}
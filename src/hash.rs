@@ -0,0 +1,210 @@
+use crate::trace::record_latency;
+use anyhow::Result as AnyResult;
+use redis::aio::ConnectionLike;
+use redis::AsyncCommands;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Set a single field of a hash. Returns `true` if the field was newly created.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.field = %field, redis.operation = "HSET", redis.latency_ms)
+))]
+pub async fn hset<T, C>(key: String, field: String, value: T, mut conn: C) -> AnyResult<bool>
+where
+    T: redis::ToRedisArgs + Send + Sync,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let created: u32 = conn.hset(key, field, value).await?;
+    record_latency(start);
+
+    Ok(created > 0)
+}
+
+/// Get a single field of a hash.
+///
+/// Returns `Ok(None)` both when the field is missing and when the key itself does not
+/// exist; use `hgetall` or `exists` to tell the two apart.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.field = %field, redis.operation = "HGET", redis.latency_ms)
+))]
+pub async fn hget<T, C>(key: String, field: String, mut conn: C) -> AnyResult<Option<T>>
+where
+    T: redis::FromRedisValue,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let res = conn.hget(key, field).await?;
+    record_latency(start);
+
+    Ok(res)
+}
+
+/// Get several fields of a hash in one round trip, in the order they were requested.
+///
+/// Missing fields, and every field when the key does not exist, come back as `None`.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "HMGET", redis.latency_ms)
+))]
+pub async fn hmget<T, C>(key: String, fields: &[&str], mut conn: C) -> AnyResult<Vec<Option<T>>>
+where
+    T: redis::FromRedisValue,
+    C: ConnectionLike + Send,
+{
+    if fields.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let start = Instant::now();
+    let values = redis::cmd("HMGET").arg(key).arg(fields).query_async(&mut conn).await?;
+    record_latency(start);
+
+    Ok(values)
+}
+
+/// Get every field of a hash and deserialize them into `T`, with field names mapping to
+/// struct fields.
+///
+/// Each value is read as JSON when it parses as JSON (numbers, booleans, nested objects)
+/// and as a plain string otherwise. Returns `Ok(None)` when the key does not exist.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "HGETALL", redis.latency_ms)
+))]
+pub async fn hgetall<T, C>(key: String, mut conn: C) -> AnyResult<Option<T>>
+where
+    T: DeserializeOwned,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let fields: HashMap<String, String> = conn.hgetall(key).await?;
+    record_latency(start);
+
+    if fields.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(serde_json::from_value(fields_to_json(fields))?))
+}
+
+/// JSON object with one member per hash field, each read as JSON when it parses as JSON
+/// and kept as a string otherwise
+fn fields_to_json(fields: HashMap<String, String>) -> serde_json::Value {
+    let object = fields
+        .into_iter()
+        .map(|(name, raw)| {
+            let value = serde_json::from_str(&raw).unwrap_or(serde_json::Value::String(raw));
+            (name, value)
+        })
+        .collect::<serde_json::Map<String, serde_json::Value>>();
+    serde_json::Value::Object(object)
+}
+
+/// Delete a single field of a hash. Returns `true` if the field existed.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.field = %field, redis.operation = "HDEL", redis.latency_ms)
+))]
+pub async fn hdel<C>(key: String, field: String, mut conn: C) -> AnyResult<bool>
+where
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let deleted: u32 = conn.hdel(key, field).await?;
+    record_latency(start);
+
+    Ok(deleted > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::live_conn;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct User {
+        name: String,
+        age: u32,
+        active: bool,
+        tags: Vec<String>,
+    }
+
+    #[test]
+    fn fields_are_read_as_json_or_as_strings() {
+        let fields = HashMap::from([
+            ("name".to_string(), "Ayu".to_string()),
+            ("zip".to_string(), "01234".to_string()),
+            ("quoted".to_string(), r#""7""#.to_string()),
+            ("age".to_string(), "31".to_string()),
+            ("active".to_string(), "true".to_string()),
+            ("tags".to_string(), r#"["admin"]"#.to_string()),
+        ]);
+        assert_eq!(
+            fields_to_json(fields),
+            json!({"name": "Ayu", "zip": "01234", "quoted": "7", "age": 31, "active": true, "tags": ["admin"]})
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn fields_are_set_read_and_deleted_individually() {
+        let (mut conn, prefix) = live_conn().await;
+        let key = format!("{}:user", prefix);
+
+        assert!(hset(key.clone(), "name".into(), "Ayu", conn.clone()).await.unwrap());
+        assert!(hset(key.clone(), "age".into(), 30, conn.clone()).await.unwrap());
+        assert!(!hset(key.clone(), "age".into(), 31, conn.clone()).await.unwrap());
+        hset(key.clone(), "active".into(), "true", conn.clone()).await.unwrap();
+        hset(key.clone(), "tags".into(), r#"["admin"]"#, conn.clone()).await.unwrap();
+
+        assert_eq!(hget::<u32, _>(key.clone(), "age".into(), conn.clone()).await.unwrap(), Some(31));
+        assert_eq!(hget::<String, _>(key.clone(), "email".into(), conn.clone()).await.unwrap(), None);
+        assert_eq!(hget::<String, _>(format!("{}:missing", prefix), "name".into(), conn.clone()).await.unwrap(), None);
+        assert_eq!(
+            hmget::<String, _>(key.clone(), &["name", "email"], conn.clone()).await.unwrap(),
+            [Some("Ayu".to_string()), None]
+        );
+        assert_eq!(
+            hgetall::<User, _>(key.clone(), conn.clone()).await.unwrap(),
+            Some(User {
+                name: "Ayu".into(),
+                age: 31,
+                active: true,
+                tags: vec!["admin".into()],
+            })
+        );
+
+        assert!(hdel(key.clone(), "tags".into(), conn.clone()).await.unwrap());
+        assert!(!hdel(key.clone(), "tags".into(), conn.clone()).await.unwrap());
+        assert!(hgetall::<User, _>(key.clone(), conn.clone()).await.is_err());
+        assert_eq!(hgetall::<User, _>(format!("{}:missing", prefix), conn.clone()).await.unwrap(), None);
+        let _: () = conn.del(&key).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn hmget_returns_the_requested_fields_in_order() {
+        let (mut conn, prefix) = live_conn().await;
+        let key = format!("{}:order", prefix);
+        for (field, value) in [("id", "17"), ("status", "paid"), ("total", "42"), ("currency", "EUR"), ("note", "gift")] {
+            hset(key.clone(), field.into(), value, conn.clone()).await.unwrap();
+        }
+
+        assert_eq!(
+            hmget::<String, _>(key.clone(), &["total", "coupon", "id"], conn.clone()).await.unwrap(),
+            [Some("42".to_string()), None, Some("17".to_string())]
+        );
+        assert_eq!(
+            hmget::<String, _>(format!("{}:missing", prefix), &["id", "total"], conn.clone()).await.unwrap(),
+            [None, None]
+        );
+        let _: () = conn.del(&key).await.unwrap();
+    }
+}
+
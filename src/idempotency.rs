@@ -0,0 +1,124 @@
+use crate::operations::{px_millis, set_nx_with_expiry};
+use anyhow::Result as AnyResult;
+use redis::aio::ConnectionLike;
+use redis::AsyncCommands;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::time::Duration;
+
+/// Stored under an idempotency key while the first request is still being handled.
+/// It is not valid JSON, so it cannot be confused with a stored response.
+const PENDING_MARKER: &str = "pending";
+
+/// Result of claiming an idempotency key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdempotencyOutcome {
+    /// No request with this key was seen within the window; the caller should handle it
+    FirstSeen,
+    /// A request with this key is in progress or already completed
+    Duplicate,
+}
+
+/// Guard for safely retried requests, such as POSTs carrying an `Idempotency-Key` header.
+///
+/// The first request claims the key with `try_begin`, does its work, and stores its
+/// response with `complete`. Retries of the same request see `Duplicate` and can return
+/// the stored response from `fetch` instead of repeating the work. If the work fails,
+/// `release` frees the key so the client can try again.
+#[derive(Clone)]
+pub struct Idempotency<C> {
+    conn: C,
+}
+
+impl<C> Idempotency<C>
+where
+    C: ConnectionLike + Send + Clone,
+{
+    pub fn new(conn: C) -> Self {
+        Self { conn }
+    }
+
+    /// Claim `key` for `ttl`. Only one caller per window sees `FirstSeen`. A `ttl` under 1ms
+    /// is an error.
+    pub async fn try_begin(&mut self, key: &str, ttl: Duration) -> AnyResult<IdempotencyOutcome> {
+        let claimed = set_nx_with_expiry(idempotency_key(key), PENDING_MARKER, ttl, self.conn.clone()).await?;
+        Ok(if claimed { IdempotencyOutcome::FirstSeen } else { IdempotencyOutcome::Duplicate })
+    }
+
+    /// Store the response of the completed operation, keeping it for `ttl`. A `ttl` under
+    /// 1ms is an error.
+    pub async fn complete<T: Serialize>(&mut self, key: &str, response: &T, ttl: Duration) -> AnyResult<()> {
+        let millis = px_millis(ttl)?;
+        let payload = serde_json::to_string(response)?;
+        redis::cmd("SET")
+            .arg(idempotency_key(key))
+            .arg(payload)
+            .arg("PX")
+            .arg(millis)
+            .query_async::<_, ()>(&mut self.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Fetch the stored response. Returns `None` while the first request is still in
+    /// progress, or once the key has expired.
+    pub async fn fetch<T: DeserializeOwned>(&mut self, key: &str) -> AnyResult<Option<T>> {
+        let stored: Option<String> = self.conn.get(idempotency_key(key)).await?;
+        match stored {
+            Some(payload) if payload != PENDING_MARKER => Ok(Some(serde_json::from_str(&payload)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Drop the claim on `key`, e.g. after the operation failed and may be retried
+    pub async fn release(&mut self, key: &str) -> AnyResult<()> {
+        let _: u32 = self.conn.del(idempotency_key(key)).await?;
+        Ok(())
+    }
+}
+
+fn idempotency_key(key: &str) -> String {
+    format!("idempotency:{}", key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AsyncConnManager;
+    use crate::testing::FakeRedis;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Receipt {
+        order_id: u32,
+    }
+
+    #[tokio::test]
+    async fn a_retried_request_is_a_duplicate_and_sees_the_first_response() {
+        let server = FakeRedis::start().await;
+        let conn = AsyncConnManager::new(redis::Client::open(server.url()).unwrap()).await.unwrap();
+        let mut first = Idempotency::new(conn.clone());
+        let mut retry = Idempotency::new(conn);
+        let ttl = Duration::from_secs(60);
+
+        assert_eq!(first.try_begin("req-1", ttl).await.unwrap(), IdempotencyOutcome::FirstSeen);
+        assert_eq!(retry.try_begin("req-1", ttl).await.unwrap(), IdempotencyOutcome::Duplicate);
+        assert_eq!(retry.fetch::<Receipt>("req-1").await.unwrap(), None);
+
+        first.complete("req-1", &Receipt { order_id: 7 }, ttl).await.unwrap();
+        assert_eq!(retry.try_begin("req-1", ttl).await.unwrap(), IdempotencyOutcome::Duplicate);
+        assert_eq!(retry.fetch::<Receipt>("req-1").await.unwrap(), Some(Receipt { order_id: 7 }));
+
+        // A failed request gives up its claim so the client can try again
+        assert_eq!(first.try_begin("req-2", ttl).await.unwrap(), IdempotencyOutcome::FirstSeen);
+        first.release("req-2").await.unwrap();
+        assert_eq!(retry.try_begin("req-2", ttl).await.unwrap(), IdempotencyOutcome::FirstSeen);
+
+        // Redis rejects `PX 0`, so a zero window is refused before anything is sent
+        let sets = server.calls("SET");
+        assert!(first.try_begin("req-3", Duration::ZERO).await.is_err());
+        assert!(first.complete("req-1", &Receipt { order_id: 8 }, Duration::ZERO).await.is_err());
+        assert_eq!(server.calls("SET"), sets);
+        assert_eq!(retry.fetch::<Receipt>("req-1").await.unwrap(), Some(Receipt { order_id: 7 }));
+    }
+}
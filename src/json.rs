@@ -0,0 +1,108 @@
+use crate::trace::record_latency;
+use anyhow::Result as AnyResult;
+use redis::aio::ConnectionLike;
+use redis::RedisError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::time::Instant;
+
+/// Replace a clear error for a missing RedisJSON module, pass everything else through
+fn module_error(e: RedisError) -> anyhow::Error {
+    if e.to_string().to_lowercase().contains("unknown command") {
+        anyhow::anyhow!("RedisJSON module is not loaded on this server: {}", e)
+    } else {
+        e.into()
+    }
+}
+
+/// Set the value at `path` in a JSON document, creating the document when `path` is the root.
+///
+/// Only the addressed field is rewritten, so a nested field can be updated without reading
+/// and writing back the whole value. Returns `false` if the parent of `path` does not exist.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "JSON.SET", redis.latency_ms)
+))]
+pub async fn json_set<T, C>(key: String, path: &str, value: &T, mut conn: C) -> AnyResult<bool>
+where
+    T: Serialize,
+    C: ConnectionLike + Send,
+{
+    let payload = serde_json::to_string(value)?;
+    let start = Instant::now();
+    let res: Option<String> = redis::cmd("JSON.SET")
+        .arg(key)
+        .arg(path)
+        .arg(payload)
+        .query_async(&mut conn)
+        .await
+        .map_err(module_error)?;
+    record_latency(start);
+
+    Ok(res.is_some())
+}
+
+/// Read the value at `path` in a JSON document, or `None` if the key does not exist.
+///
+/// Legacy paths such as `.profile.name` return the value itself. JSONPath expressions
+/// starting with `$` return an array of matches, so read them into a `Vec`.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "JSON.GET", redis.latency_ms)
+))]
+pub async fn json_get<T, C>(key: String, path: &str, mut conn: C) -> AnyResult<Option<T>>
+where
+    T: DeserializeOwned,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let res: Option<String> = redis::cmd("JSON.GET")
+        .arg(key)
+        .arg(path)
+        .query_async(&mut conn)
+        .await
+        .map_err(module_error)?;
+    record_latency(start);
+
+    match res {
+        Some(payload) => Ok(Some(serde_json::from_str(&payload)?)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AsyncConnManager;
+    use crate::testing::{live_conn, FakeRedis};
+    use serde_json::{json, Value};
+
+    #[tokio::test]
+    async fn a_missing_module_is_reported_clearly() {
+        let server = FakeRedis::start().await;
+        let conn = AsyncConnManager::new(redis::Client::open(server.url()).unwrap()).await.unwrap();
+
+        let error = json_set("doc".to_string(), ".", &json!({}), conn.clone()).await.unwrap_err();
+        assert!(error.to_string().starts_with("RedisJSON module is not loaded"), "{}", error);
+        let error = json_get::<Value, _>("doc".to_string(), ".", conn).await.unwrap_err();
+        assert!(error.to_string().starts_with("RedisJSON module is not loaded"), "{}", error);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server with the RedisJSON module at REDIS_URL"]
+    async fn a_nested_field_is_updated_in_place() {
+        let (conn, prefix) = live_conn().await;
+        let key = format!("{}:user", prefix);
+        let user = json!({"name": "alice", "profile": {"city": "Oslo", "age": 30}});
+
+        assert!(json_set(key.clone(), ".", &user, conn.clone()).await.unwrap());
+        assert!(json_set(key.clone(), ".profile.city", &"Bergen", conn.clone()).await.unwrap());
+        assert!(!json_set(key.clone(), "$.missing.city", &"Bergen", conn.clone()).await.unwrap());
+
+        let city: Option<String> = json_get(key.clone(), ".profile.city", conn.clone()).await.unwrap();
+        assert_eq!(city.as_deref(), Some("Bergen"));
+        let read: Option<Value> = json_get(key.clone(), ".", conn.clone()).await.unwrap();
+        assert_eq!(read, Some(json!({"name": "alice", "profile": {"city": "Bergen", "age": 30}})));
+        assert_eq!(json_get::<Value, _>(format!("{}:absent", prefix), ".", conn).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::config::get_shared_conn_manager;
+use crate::operations::psubscribe_data;
+use anyhow::Result as AnyResult;
+use futures::{Stream, StreamExt};
+use log::warn;
+
+/// What happened to a key, from the channel a keyevent notification arrived on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyEvent {
+    Set,
+    Del,
+    /// A TTL was set or changed
+    Expire,
+    /// The key reached its TTL and was removed
+    Expired,
+    /// The key was removed under `maxmemory` pressure
+    Evicted,
+    /// Any other event, such as `hset` or `rename_to`, by its Redis name
+    Other(String),
+}
+
+impl KeyEvent {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "set" => KeyEvent::Set,
+            "del" => KeyEvent::Del,
+            "expire" => KeyEvent::Expire,
+            "expired" => KeyEvent::Expired,
+            "evicted" => KeyEvent::Evicted,
+            other => KeyEvent::Other(other.to_string()),
+        }
+    }
+
+    /// The event name Redis uses in the channel
+    pub fn name(&self) -> &str {
+        match self {
+            KeyEvent::Set => "set",
+            KeyEvent::Del => "del",
+            KeyEvent::Expire => "expire",
+            KeyEvent::Expired => "expired",
+            KeyEvent::Evicted => "evicted",
+            KeyEvent::Other(name) => name,
+        }
+    }
+}
+
+/// The server's `notify-keyspace-events` flags. Empty means notifications are off.
+pub async fn keyspace_events_config() -> AnyResult<String> {
+    let mut conn = get_shared_conn_manager().await?;
+    let reply: Vec<String> = redis::cmd("CONFIG")
+        .arg("GET")
+        .arg("notify-keyspace-events")
+        .query_async(&mut conn)
+        .await?;
+    Ok(reply.into_iter().nth(1).unwrap_or_default())
+}
+
+/// Set `notify-keyspace-events`, e.g. to `"Ex"` for expirations or `"Egx"` to add deletes.
+///
+/// This changes a server-wide setting and is often blocked on managed Redis; set it in the
+/// server configuration there instead.
+pub async fn enable_keyspace_events(flags: &str) -> AnyResult<()> {
+    let mut conn = get_shared_conn_manager().await?;
+    redis::cmd("CONFIG")
+        .arg("SET")
+        .arg("notify-keyspace-events")
+        .arg(flags)
+        .query_async::<_, ()>(&mut conn)
+        .await?;
+    Ok(())
+}
+
+/// Subscribe to keyevent notifications for database `db`, yielding each event with the key
+/// it happened to. With an empty `events` every event is yielded, otherwise only those listed.
+///
+/// Redis only publishes these when `notify-keyspace-events` includes `E` plus the classes
+/// wanted: `x` for `Expired`, `e` for `Evicted`, `g` for `Del` and `Expire`, `$` for `Set`,
+/// or `A` for all. This fails when the flags lack `E`, and only warns when they cannot be
+/// read because `CONFIG` is disabled. Keys arrive as stored, including any namespace prefix.
+/// Notifications are fire-and-forget: events are lost while the subscriber is disconnected,
+/// and an expiry fires when Redis notices it, which can lag the TTL under light access.
+pub async fn subscribe_key_events(db: u32, events: &[KeyEvent]) -> AnyResult<impl Stream<Item = (KeyEvent, String)>> {
+    match keyspace_events_config().await {
+        Ok(flags) if !flags.contains('E') => {
+            return Err(anyhow::anyhow!(
+                "Keyevent notifications are disabled (notify-keyspace-events is \"{}\"); enable them with enable_keyspace_events or in the server configuration",
+                flags
+            ));
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Could not read notify-keyspace-events, assuming keyevent notifications are enabled: {}", e),
+    }
+
+    let pubsub = psubscribe_data(format!("__keyevent@{}__:*", db)).await?;
+    let wanted = events.to_vec();
+    let stream = pubsub.into_on_message().filter_map(move |msg| {
+        let event = msg
+            .get_channel_name()
+            .split_once("__:")
+            .map(|(_, name)| KeyEvent::from_name(name));
+        let item = match (event, msg.get_payload::<String>()) {
+            (Some(event), Ok(key)) if wanted.is_empty() || wanted.contains(&event) => Some((event, key)),
+            _ => None,
+        };
+        futures::future::ready(item)
+    });
+
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{block_on_shared, live_prefix};
+    use redis::aio::ConnectionLike;
+    use std::time::Duration;
+
+    #[test]
+    fn event_names_round_trip() {
+        for name in ["set", "del", "expire", "expired", "evicted", "rename_to"] {
+            assert_eq!(KeyEvent::from_name(name).name(), name);
+        }
+        assert_eq!(KeyEvent::from_name("expired"), KeyEvent::Expired);
+        assert_eq!(KeyEvent::from_name("hset"), KeyEvent::Other("hset".to_string()));
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    fn an_expiring_key_yields_an_expired_event() {
+        block_on_shared(async {
+            enable_keyspace_events("Ex").await.unwrap();
+            let mut conn = get_shared_conn_manager().await.unwrap();
+            let db = conn.get_db() as u32;
+            let mut events = Box::pin(subscribe_key_events(db, &[KeyEvent::Expired]).await.unwrap());
+
+            let key = format!("{}:session", live_prefix());
+            redis::cmd("SET").arg(&key).arg("alice").arg("EX").arg(1).query_async::<_, ()>(&mut conn).await.unwrap();
+
+            let expired = async {
+                loop {
+                    match events.next().await {
+                        Some((event, expired)) if expired == key => return event,
+                        Some(_) => continue,
+                        None => panic!("the subscription ended"),
+                    }
+                }
+            };
+            let event = tokio::time::timeout(Duration::from_secs(5), expired).await.expect("no expired event within 5s");
+            assert_eq!(event, KeyEvent::Expired);
+        })
+    }
+}
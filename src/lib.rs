@@ -2,3 +2,32 @@ pub mod cli;
 pub mod config;
 pub mod operations;
 pub mod cache;
+pub mod bitmap;
+pub mod breaker;
+pub mod codec;
+pub mod expiring_set;
+pub mod geo;
+pub mod hash;
+pub mod idempotency;
+#[cfg(feature = "redisjson")]
+pub mod json;
+pub mod keyspace;
+pub mod list;
+#[cfg(any(test, feature = "test-util"))]
+pub mod memory;
+pub mod metrics;
+pub mod pipeline;
+pub mod ratelimit;
+pub mod refresh;
+pub mod retry;
+pub mod set;
+pub mod shard;
+pub mod sortedset;
+pub mod stream;
+pub mod transaction;
+pub mod typed;
+mod local;
+mod otel;
+#[cfg(test)]
+mod testing;
+mod trace;
@@ -0,0 +1,220 @@
+use crate::trace::record_latency;
+use anyhow::Result as AnyResult;
+use redis::aio::ConnectionLike;
+use redis::AsyncCommands;
+use std::time::{Duration, Instant};
+
+/// Push a value onto the head of a list. Returns the new list length.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "LPUSH", redis.latency_ms)
+))]
+pub async fn lpush<T, C>(key: String, value: T, mut conn: C) -> AnyResult<u64>
+where
+    T: redis::ToRedisArgs + Send + Sync,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let len = conn.lpush(key, value).await?;
+    record_latency(start);
+
+    Ok(len)
+}
+
+/// Push a value onto the tail of a list. Returns the new list length.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "RPUSH", redis.latency_ms)
+))]
+pub async fn rpush<T, C>(key: String, value: T, mut conn: C) -> AnyResult<u64>
+where
+    T: redis::ToRedisArgs + Send + Sync,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let len = conn.rpush(key, value).await?;
+    record_latency(start);
+
+    Ok(len)
+}
+
+/// Pop a value from the head of a list, or `None` if it is empty.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "LPOP", redis.latency_ms)
+))]
+pub async fn lpop<T, C>(key: String, mut conn: C) -> AnyResult<Option<T>>
+where
+    T: redis::FromRedisValue,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let res = conn.lpop(key, None).await?;
+    record_latency(start);
+
+    Ok(res)
+}
+
+/// Pop a value from the tail of a list, or `None` if it is empty.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "RPOP", redis.latency_ms)
+))]
+pub async fn rpop<T, C>(key: String, mut conn: C) -> AnyResult<Option<T>>
+where
+    T: redis::FromRedisValue,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let res = conn.rpop(key, None).await?;
+    record_latency(start);
+
+    Ok(res)
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "LLEN", redis.latency_ms)
+))]
+pub async fn llen<C>(key: String, mut conn: C) -> AnyResult<u64>
+where
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let len = conn.llen(key).await?;
+    record_latency(start);
+
+    Ok(len)
+}
+
+/// Get the elements between `start` and `stop` inclusive. Negative indexes count from the tail.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "LRANGE", redis.latency_ms)
+))]
+pub async fn lrange<T, C>(key: String, start: isize, stop: isize, mut conn: C) -> AnyResult<Vec<T>>
+where
+    T: redis::FromRedisValue,
+    C: ConnectionLike + Send,
+{
+    let started = Instant::now();
+    let res = conn.lrange(key, start, stop).await?;
+    record_latency(started);
+
+    Ok(res)
+}
+
+/// Index of the first occurrence of `element`, counting from the head, or `None` if it is
+/// not in the list. Scans the list, so it is O(n). Needs Redis 6.0.6+.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "LPOS", redis.latency_ms)
+))]
+pub async fn lpos<T, C>(key: String, element: T, mut conn: C) -> AnyResult<Option<usize>>
+where
+    T: redis::ToRedisArgs + Send + Sync,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let res = redis::cmd("LPOS").arg(key).arg(element).query_async(&mut conn).await?;
+    record_latency(start);
+
+    Ok(res)
+}
+
+/// Remove occurrences of `element`: the first `count` from the head when positive, the last
+/// `-count` from the tail when negative, or all of them when 0. Returns how many were removed.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "LREM", redis.latency_ms)
+))]
+pub async fn lrem<T, C>(key: String, count: isize, element: T, mut conn: C) -> AnyResult<u32>
+where
+    T: redis::ToRedisArgs + Send + Sync,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let removed = conn.lrem(key, count, element).await?;
+    record_latency(start);
+
+    Ok(removed)
+}
+
+/// Pop a value from the head of a list, waiting up to `timeout` for one to arrive.
+///
+/// Returns `Ok(None)` when the timeout expires. The wait holds the connection, so use a
+/// dedicated connection (e.g. from `get_redis_pool`) rather than a shared `ConnectionManager`,
+/// which would stall every other command multiplexed over it.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "BLPOP", redis.latency_ms)
+))]
+pub async fn blpop<T, C>(key: String, timeout: Duration, mut conn: C) -> AnyResult<Option<T>>
+where
+    T: redis::FromRedisValue,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let res: Option<(String, T)> = conn.blpop(key, timeout.as_secs_f64()).await?;
+    record_latency(start);
+
+    Ok(res.map(|(_, value)| value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::live_conn;
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn a_list_used_as_a_queue_is_first_in_first_out() {
+        let (mut conn, prefix) = live_conn().await;
+        let key = format!("{}:jobs", prefix);
+
+        for (pushed, job) in ["job-1", "job-2", "job-3"].into_iter().enumerate() {
+            assert_eq!(rpush(key.clone(), job, conn.clone()).await.unwrap(), pushed as u64 + 1);
+        }
+        assert_eq!(llen(key.clone(), conn.clone()).await.unwrap(), 3);
+        assert_eq!(lrange::<String, _>(key.clone(), 0, -1, conn.clone()).await.unwrap(), ["job-1", "job-2", "job-3"]);
+
+        assert_eq!(lpop::<String, _>(key.clone(), conn.clone()).await.unwrap().as_deref(), Some("job-1"));
+        assert_eq!(lpop::<String, _>(key.clone(), conn.clone()).await.unwrap().as_deref(), Some("job-2"));
+        lpush(key.clone(), "urgent", conn.clone()).await.unwrap();
+        assert_eq!(rpop::<String, _>(key.clone(), conn.clone()).await.unwrap().as_deref(), Some("job-3"));
+        assert_eq!(lpop::<String, _>(key.clone(), conn.clone()).await.unwrap().as_deref(), Some("urgent"));
+        assert_eq!(lpop::<String, _>(key.clone(), conn.clone()).await.unwrap(), None);
+        let _: () = conn.del(&key).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn blpop_on_an_empty_list_returns_none_after_the_timeout() {
+        let (conn, prefix) = live_conn().await;
+        let started = Instant::now();
+
+        let popped = blpop::<String, _>(format!("{}:empty", prefix), Duration::from_millis(200), conn).await.unwrap();
+        assert_eq!(popped, None);
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn elements_are_located_and_removed_by_value() {
+        let (mut conn, prefix) = live_conn().await;
+        let key = format!("{}:jobs", prefix);
+        for job in ["a", "b", "retry", "c", "retry", "retry"] {
+            rpush(key.clone(), job, conn.clone()).await.unwrap();
+        }
+
+        assert_eq!(lpos(key.clone(), "c", conn.clone()).await.unwrap(), Some(3));
+        assert_eq!(lpos(key.clone(), "retry", conn.clone()).await.unwrap(), Some(2));
+        assert_eq!(lpos(key.clone(), "missing", conn.clone()).await.unwrap(), None);
+
+        assert_eq!(lrem(key.clone(), 2, "retry", conn.clone()).await.unwrap(), 2);
+        assert_eq!(lrange::<String, _>(key.clone(), 0, -1, conn.clone()).await.unwrap(), ["a", "b", "c", "retry"]);
+        assert_eq!(lrem(key.clone(), 0, "missing", conn.clone()).await.unwrap(), 0);
+        let _: () = conn.del(&key).await.unwrap();
+    }
+}
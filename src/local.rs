@@ -0,0 +1,189 @@
+use crate::config::Client;
+use anyhow::Result as AnyResult;
+use futures::StreamExt;
+use log::{debug, warn};
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// Channel Redis publishes tracking invalidations on for RESP2 clients
+const INVALIDATE_CHANNEL: &str = "__redis__:invalidate";
+
+/// Backoff between attempts to re-establish client tracking
+const TRACKING_RETRY_MIN: Duration = Duration::from_millis(100);
+const TRACKING_RETRY_MAX: Duration = Duration::from_secs(5);
+
+struct LocalEntry {
+    value: Vec<u8>,
+    expires_at: Instant,
+}
+
+/// In-process L1 in front of Redis, holding encoded values exactly as stored in Redis.
+///
+/// Bounded by entry count with least-recently-used eviction, and every entry expires after
+/// `ttl` at most. Only writes made through this process invalidate it, so another instance
+/// can serve a value up to `ttl` old after the key changed elsewhere, unless `Invalidations`
+/// is listening for changes on its behalf.
+pub(crate) struct LocalCache {
+    entries: Mutex<LruCache<String, LocalEntry>>,
+    ttl: Duration,
+}
+
+impl LocalCache {
+    /// `None` when `capacity` or `ttl` is zero, which disables the L1
+    pub(crate) fn new(capacity: usize, ttl: Duration) -> Option<Self> {
+        let capacity = NonZeroUsize::new(capacity)?;
+        if ttl.is_zero() {
+            return None;
+        }
+        Some(Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            ttl,
+        })
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.get(key) {
+            Some(entry) if entry.expires_at > Instant::now() => Some(entry.value.clone()),
+            Some(_) => {
+                entries.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Store `value`, expiring no later than the Redis entry would (`ttl_seconds` of 0 means none)
+    pub(crate) fn insert(&self, key: &str, value: Vec<u8>, ttl_seconds: u64) {
+        let ttl = match ttl_seconds {
+            0 => self.ttl,
+            seconds => self.ttl.min(Duration::from_secs(seconds)),
+        };
+        let entry = LocalEntry {
+            value,
+            expires_at: Instant::now() + ttl,
+        };
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).put(key.to_string(), entry);
+    }
+
+    pub(crate) fn remove(&self, key: &str) {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).pop(key);
+    }
+
+    /// Drop every entry, e.g. after invalidations may have been missed
+    pub(crate) fn clear(&self) {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+/// Server-assisted invalidation of a `LocalCache` through `CLIENT TRACKING`.
+///
+/// Tracking runs in broadcasting mode on a dedicated connection, optionally limited to keys
+/// starting with a prefix, and redirects invalidations to a second connection subscribed to
+/// `__redis__:invalidate`, which works over RESP2. Every key written by any client is
+/// removed from the L1 as soon as the message arrives. Whenever the listener (re)connects
+/// the whole L1 is dropped, as messages may have been missed. A read racing a write can
+/// still put an old value back, so the L1 TTL remains the upper bound on staleness.
+///
+/// The background task stops when this is dropped.
+pub(crate) struct Invalidations {
+    task: JoinHandle<()>,
+}
+
+impl Invalidations {
+    /// Start listening for changes to keys starting with `prefix` (all keys when `None`) on
+    /// the server `client` points at. Must be called within a Tokio runtime.
+    pub(crate) fn spawn(client: Client, prefix: Option<String>, local: Arc<LocalCache>) -> Self {
+        let task = tokio::spawn(async move {
+            let mut delay = TRACKING_RETRY_MIN;
+            loop {
+                match track(&client, prefix.as_deref(), &local, &mut delay).await {
+                    Ok(()) => warn!("Client tracking invalidations stopped, reconnecting"),
+                    Err(e) => warn!("Client tracking unavailable, retrying in {:?}: {}", delay, e),
+                }
+                local.clear();
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(TRACKING_RETRY_MAX);
+            }
+        });
+        Self { task }
+    }
+}
+
+impl Drop for Invalidations {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Enable tracking and apply invalidations until either connection drops
+async fn track(client: &Client, prefix: Option<&str>, local: &LocalCache, delay: &mut Duration) -> AnyResult<()> {
+    let mut listener = client.get_async_connection().await?;
+    let listener_id: i64 = redis::cmd("CLIENT").arg("ID").query_async(&mut listener).await?;
+    let mut pubsub = listener.into_pubsub();
+    pubsub.subscribe(INVALIDATE_CHANNEL).await?;
+
+    // Tracking state belongs to this connection, so it is held until the listener stops
+    let mut tracked = client.get_async_connection().await?;
+    let mut cmd = redis::cmd("CLIENT");
+    cmd.arg("TRACKING").arg("ON").arg("REDIRECT").arg(listener_id).arg("BCAST");
+    if let Some(prefix) = prefix {
+        cmd.arg("PREFIX").arg(prefix);
+    }
+    cmd.query_async::<_, ()>(&mut tracked).await?;
+    local.clear();
+    *delay = TRACKING_RETRY_MIN;
+    debug!("Client tracking enabled, redirecting invalidations to client {}", listener_id);
+
+    let mut messages = pubsub.on_message();
+    while let Some(msg) = messages.next().await {
+        // A nil payload means the whole database was flushed
+        match msg.get_payload::<Option<Vec<String>>>() {
+            Ok(Some(keys)) => keys.iter().for_each(|key| local.remove(key)),
+            Ok(None) => local.clear(),
+            Err(e) => {
+                warn!("Unreadable invalidation message, dropping the local cache: {}", e);
+                local.clear();
+            }
+        }
+    }
+    drop(tracked);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::FakeRedis;
+
+    #[tokio::test]
+    async fn an_invalidation_message_evicts_only_the_named_keys() {
+        let server = FakeRedis::start().await;
+        let local = Arc::new(LocalCache::new(16, Duration::from_secs(60)).unwrap());
+        let _invalidations = Invalidations::spawn(Client::open(server.url()).unwrap(), None, local.clone());
+        // Tracking clears the L1 once it is on, so fill it only after that
+        for _ in 0..100 {
+            if server.calls("CLIENT TRACKING") == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(server.calls("CLIENT TRACKING"), 1);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        local.insert("user:1", b"ayu".to_vec(), 0);
+        local.insert("user:2", b"bima".to_vec(), 0);
+
+        assert_eq!(server.publish(INVALIDATE_CHANNEL, &["user:1"]).await, 1);
+        for _ in 0..100 {
+            if local.get("user:1").is_none() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(local.get("user:1"), None);
+        assert_eq!(local.get("user:2"), Some(b"bima".to_vec()));
+    }
+}
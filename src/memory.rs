@@ -0,0 +1,114 @@
+use crate::cache::{Cache, CacheError, CachedResponse};
+use anyhow::Result as AnyResult;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// `HashMap`-backed `Cache` for unit tests of code that is generic over `Cache`.
+///
+/// Entries are stored as JSON, so a value read back as the wrong type fails with
+/// `CacheError::Serialization` just as it would from Redis. Entries never expire. Clones
+/// share the same map, so a test can keep a handle to inspect what the code under test wrote.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryCache {
+    entries: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+}
+
+impl InMemoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of stored entries
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove every entry
+    pub fn clear(&self) {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+#[async_trait]
+impl Cache for InMemoryCache {
+    async fn get<T>(&mut self, key: &str) -> Result<Option<CachedResponse<T>>, CacheError>
+    where
+        T: for<'de> Deserialize<'de> + Send,
+    {
+        let stored = self.entries.lock().unwrap_or_else(|e| e.into_inner()).get(key).cloned();
+        match stored {
+            Some(stored) => Ok(Some(serde_json::from_slice(&stored).map_err(|e| CacheError::Serialization(e.into()))?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn set<T>(&mut self, key: &str, data: &CachedResponse<T>) -> Result<bool, CacheError>
+    where
+        T: Serialize + Sync,
+    {
+        let stored = serde_json::to_vec(data).map_err(|e| CacheError::Serialization(e.into()))?;
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).insert(key.to_string(), stored);
+        Ok(true)
+    }
+
+    async fn delete(&mut self, key: &str) -> AnyResult<bool> {
+        Ok(self.entries.lock().unwrap_or_else(|e| e.into_inner()).remove(key).is_some())
+    }
+
+    async fn exists(&mut self, key: &str) -> AnyResult<bool> {
+        Ok(self.entries.lock().unwrap_or_else(|e| e.into_inner()).contains_key(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Session {
+        user_id: u32,
+    }
+
+    /// Business logic written against `Cache`, as an application would
+    async fn load_session<C: Cache>(cache: &mut C, token: &str) -> AnyResult<Session> {
+        let key = format!("session:{}", token);
+        if let Some(cached) = cache.get::<Session>(&key).await? {
+            return Ok(cached.data);
+        }
+        let session = Session { user_id: 7 };
+        cache.set(&key, &CachedResponse::new(session.clone(), key.clone())).await?;
+        Ok(session)
+    }
+
+    #[tokio::test]
+    async fn generic_code_reads_through_the_cache() {
+        let mut cache = InMemoryCache::new();
+        let inspector = cache.clone();
+
+        assert_eq!(load_session(&mut cache, "abc").await.unwrap(), Session { user_id: 7 });
+        assert_eq!(inspector.len(), 1);
+        assert!(cache.exists("session:abc").await.unwrap());
+
+        assert_eq!(load_session(&mut cache, "abc").await.unwrap(), Session { user_id: 7 });
+        assert_eq!(inspector.len(), 1);
+
+        assert!(cache.delete("session:abc").await.unwrap());
+        assert!(!cache.delete("session:abc").await.unwrap());
+        assert!(inspector.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reading_the_wrong_type_is_a_serialization_error() {
+        let mut cache = InMemoryCache::new();
+        cache.set("count", &CachedResponse::new("not a session", "count".to_string())).await.unwrap();
+        let result = cache.get::<Session>("count").await;
+        assert!(matches!(result, Err(CacheError::Serialization(_))));
+    }
+}
+
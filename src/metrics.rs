@@ -0,0 +1,110 @@
+use crate::otel;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Point-in-time snapshot of cache counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheMetrics {
+    pub hits: u64,
+    pub misses: u64,
+    pub errors: u64,
+    pub sets: u64,
+    pub deletes: u64,
+}
+
+impl CacheMetrics {
+    /// Fraction of lookups that were hits, or 0.0 if there were no lookups
+    pub fn hit_ratio(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / lookups as f64
+    }
+}
+
+/// Atomic counters backing `CacheMetrics`.
+#[derive(Debug, Default)]
+pub(crate) struct CacheCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    errors: AtomicU64,
+    sets: AtomicU64,
+    deletes: AtomicU64,
+}
+
+impl CacheCounters {
+    pub(crate) fn record_hit(&self, operation: &'static str) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        otel::record_operation(operation, "hit");
+    }
+
+    pub(crate) fn record_miss(&self, operation: &'static str) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        otel::record_operation(operation, "miss");
+    }
+
+    pub(crate) fn record_error(&self, operation: &'static str) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+        otel::record_operation(operation, "error");
+    }
+
+    pub(crate) fn record_sets(&self, count: u64) {
+        self.sets.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_deletes(&self, count: u64) {
+        self.deletes.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> CacheMetrics {
+        CacheMetrics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            sets: self.sets.load(Ordering::Relaxed),
+            deletes: self.deletes.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+        self.errors.store(0, Ordering::Relaxed);
+        self.sets.store(0, Ordering::Relaxed);
+        self.deletes.store(0, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_accumulate_until_reset() {
+        let counters = CacheCounters::default();
+        counters.record_hit("get");
+        counters.record_hit("get");
+        counters.record_hit("get_many");
+        counters.record_miss("get");
+        counters.record_error("set");
+        counters.record_sets(4);
+        counters.record_deletes(2);
+
+        let metrics = counters.snapshot();
+        assert_eq!(
+            metrics,
+            CacheMetrics {
+                hits: 3,
+                misses: 1,
+                errors: 1,
+                sets: 4,
+                deletes: 2,
+            }
+        );
+        assert_eq!(metrics.hit_ratio(), 0.75);
+
+        counters.reset();
+        assert_eq!(counters.snapshot(), CacheMetrics::default());
+        assert_eq!(counters.snapshot().hit_ratio(), 0.0);
+    }
+}
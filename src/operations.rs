@@ -1,61 +1,1348 @@
-use crate::config::{get_redis_pool, AsyncConnManager};
+use crate::config::{get_idempotent_expiry, get_redis_pool, get_shared_conn_manager, shared_config, AsyncConnection};
+use crate::retry::RetryPolicy;
+use crate::trace::record_latency;
 use anyhow::Result as AnyResult;
-use redis::aio::PubSub;
+use futures::{Stream, StreamExt};
+use log::{info, warn};
+use redis::aio::{ConnectionLike, PubSub};
 use redis::AsyncCommands;
 use redis::{ExistenceCheck, SetOptions};
-use std::env;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::marker::{Send, Sync};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
 
+const RESILIENT_BUFFER_SIZE: usize = 1024;
+const RESILIENT_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+const RESILIENT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Pub/sub payload wrapped with delivery metadata.
+///
+/// The id lets subscribers drop duplicates, and `published_at` lets them measure
+/// end-to-end latency. `version` is the envelope layout, so subscribers can tell layouts
+/// apart once it changes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Message<T> {
+    /// Envelopes published before the field existed have the first layout
+    #[serde(default = "first_envelope_version")]
+    pub version: u32,
+    pub id: Uuid,
+    pub published_at: chrono::DateTime<chrono::Utc>,
+    pub channel: String,
+    pub payload: T,
+}
+
+/// Layout of the `Message` envelopes this version publishes
+pub const ENVELOPE_VERSION: u32 = 1;
+
+fn first_envelope_version() -> u32 {
+    1
+}
+
+impl<T> Message<T> {
+    pub fn new(channel: &str, payload: T) -> Self {
+        Self {
+            version: ENVELOPE_VERSION,
+            id: Uuid::new_v4(),
+            published_at: chrono::Utc::now(),
+            channel: channel.to_string(),
+            payload,
+        }
+    }
+
+    /// Time between publishing and now, or zero if the publisher's clock is ahead
+    pub fn latency(&self) -> Duration {
+        chrono::Utc::now()
+            .signed_duration_since(self.published_at)
+            .to_std()
+            .unwrap_or_default()
+    }
+}
+
+/// Request published by `request_reply`, naming the channel the responder must answer on.
+///
+/// Responders subscribe with `subscribe_typed::<RpcRequest<Req>>` and answer with `reply`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcRequest<T> {
+    pub reply_to: String,
+    pub payload: T,
+}
+
+/// Error yielded by a typed subscription stream for a single message.
+#[derive(Debug)]
+pub enum SubError {
+    /// The message payload could not be read as a string
+    Payload(redis::RedisError),
+    /// The payload was read but could not be deserialized into the target type
+    Decode { payload: String, source: serde_json::Error },
+}
+
+impl fmt::Display for SubError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubError::Payload(e) => write!(f, "failed to read message payload: {}", e),
+            SubError::Decode { source, .. } => write!(f, "failed to decode message payload: {}", source),
+        }
+    }
+}
+
+impl std::error::Error for SubError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SubError::Payload(e) => Some(e),
+            SubError::Decode { source, .. } => Some(source),
+        }
+    }
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.channel = %db_channel, redis.operation = "PUBLISH", redis.latency_ms)
+))]
 pub async fn broadcasting_data(db_channel: String, data: String) -> AnyResult<()> {
-    let mut connection = get_redis_pool().await.unwrap();
-    let _: () = connection.publish(db_channel, data).await.unwrap();
+    let mut connection = get_shared_conn_manager().await?;
+    publish(&mut connection, db_channel, data).await
+}
+
+async fn publish<C: ConnectionLike + Send>(conn: &mut C, db_channel: String, data: String) -> AnyResult<()> {
+    let start = Instant::now();
+    let _: () = conn.publish(db_channel, data).await?;
+    record_latency(start);
     Ok(())
 }
 
+/// Publish each `(channel, data)` pair in one pipelined round trip on the shared connection.
+///
+/// Returns the number of subscribers that received each message, in input order.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.operation = "PUBLISH", redis.commands = messages.len(), redis.latency_ms)
+))]
+pub async fn broadcast_many(messages: &[(String, String)]) -> AnyResult<Vec<i64>> {
+    if messages.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut connection = get_shared_conn_manager().await?;
+    let mut pipe = redis::pipe();
+    for (db_channel, data) in messages {
+        pipe.publish(db_channel, data);
+    }
+    let start = Instant::now();
+    let receivers = pipe.query_async(&mut connection).await?;
+    record_latency(start);
+    Ok(receivers)
+}
+
+/// Serialize `value` as JSON and publish it to `db_channel`.
+pub async fn broadcast_json<T>(db_channel: &str, value: &T) -> AnyResult<()>
+where
+    T: Serialize,
+{
+    let data = serde_json::to_string(value)?;
+    broadcasting_data(db_channel.to_string(), data).await
+}
+
+/// Publish `payload` wrapped in a `Message` envelope with a fresh id and timestamp.
+///
+/// Returns the envelope that was sent, so the caller can log or correlate its id.
+pub async fn broadcast_enveloped<T>(db_channel: &str, payload: T) -> AnyResult<Message<T>>
+where
+    T: Serialize,
+{
+    let message = Message::new(db_channel, payload);
+    broadcast_json(db_channel, &message).await?;
+    Ok(message)
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.channel = %db_channel, redis.operation = "SUBSCRIBE", redis.latency_ms)
+))]
 pub async fn subscribe_data(db_channel: String) -> AnyResult<PubSub> {
-    let connection = get_redis_pool().await.unwrap();
+    let connection = get_redis_pool().await?;
+    let mut pubsub = connection.into_pubsub();
+    let start = Instant::now();
+    pubsub.subscribe(db_channel).await?;
+    record_latency(start);
+    Ok(pubsub)
+}
+
+/// Like `subscribe_data`, but fails if Redis has not acknowledged the `SUBSCRIBE` within
+/// `timeout`, so the caller knows the subscription is live before waiting for messages.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.channel = %db_channel, redis.operation = "SUBSCRIBE", redis.latency_ms)
+))]
+pub async fn subscribe_data_timeout(db_channel: String, timeout: Duration) -> AnyResult<PubSub> {
+    subscribe_confirmed(get_redis_pool().await?, &db_channel, timeout).await
+}
+
+async fn subscribe_confirmed(connection: AsyncConnection, db_channel: &str, timeout: Duration) -> AnyResult<PubSub> {
+    let mut pubsub = connection.into_pubsub();
+    let start = Instant::now();
+    // The reply to SUBSCRIBE is the subscription confirmation, so awaiting it confirms the channel is live
+    tokio::time::timeout(timeout, pubsub.subscribe(db_channel))
+        .await
+        .map_err(|_| anyhow::anyhow!("Redis did not confirm subscription to {} within {}ms", db_channel, timeout.as_millis()))??;
+    record_latency(start);
+    Ok(pubsub)
+}
+
+/// Subscribe to several channels with a single `SUBSCRIBE` on one connection.
+///
+/// Use `Msg::get_channel_name` to tell which channel a message arrived on.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.channel = ?channels, redis.operation = "SUBSCRIBE", redis.latency_ms)
+))]
+pub async fn subscribe_many(channels: Vec<String>) -> AnyResult<PubSub> {
+    if channels.is_empty() || channels.iter().any(|channel| channel.is_empty()) {
+        return Err(anyhow::anyhow!("Channel names must not be empty"));
+    }
+
+    let connection = get_redis_pool().await?;
+    let mut pubsub = connection.into_pubsub();
+    let start = Instant::now();
+    pubsub.subscribe(channels).await?;
+    record_latency(start);
+    Ok(pubsub)
+}
+
+/// Subscribe to several channels on one connection and deserialize each JSON message
+/// into `T`, paired with the channel it was published to.
+pub async fn subscribe_many_typed<T>(channels: Vec<String>) -> AnyResult<impl Stream<Item = Result<(String, T), SubError>>>
+where
+    T: DeserializeOwned,
+{
+    let pubsub = subscribe_many(channels).await?;
+    let stream = pubsub.into_on_message().map(|msg| {
+        let value = decode_message::<T>(&msg)?;
+        Ok((msg.get_channel_name().to_string(), value))
+    });
+
+    Ok(stream)
+}
+
+/// Subscribe to a channel and deserialize each JSON message into `T`.
+///
+/// The returned stream owns the subscription, so it stays active for as long as the
+/// stream is alive. Messages that fail to decode are yielded as `Err(SubError)` instead
+/// of being dropped.
+pub async fn subscribe_typed<T>(db_channel: String) -> AnyResult<impl Stream<Item = Result<T, SubError>>>
+where
+    T: DeserializeOwned,
+{
+    if db_channel.is_empty() {
+        return Err(anyhow::anyhow!("Channel name must not be empty"));
+    }
+
+    let pubsub = subscribe_data(db_channel).await?;
+    let stream = pubsub.into_on_message().map(|msg| decode_message::<T>(&msg));
+
+    Ok(stream)
+}
+
+/// Subscribe to a channel carrying `Message<T>` envelopes published with
+/// `broadcast_enveloped`.
+pub async fn subscribe_enveloped<T>(db_channel: String) -> AnyResult<impl Stream<Item = Result<Message<T>, SubError>>>
+where
+    T: DeserializeOwned,
+{
+    subscribe_typed::<Message<T>>(db_channel).await
+}
+
+/// Publish `request` to `db_channel` as an `RpcRequest` and wait up to `timeout` for the
+/// JSON response sent to its reply channel.
+///
+/// Each call subscribes to its own `<db_channel>:reply:<uuid>` channel before publishing, so
+/// the reply cannot be missed and concurrent calls never see each other's replies. The reply
+/// subscription uses a dedicated connection that is closed when the call returns, including
+/// on timeout. Fails immediately when nothing is subscribed to `db_channel`.
+pub async fn request_reply<Req, Resp>(db_channel: &str, request: Req, timeout: Duration) -> AnyResult<Resp>
+where
+    Req: Serialize,
+    Resp: DeserializeOwned,
+{
+    let reply_to = format!("{}:reply:{}", db_channel, Uuid::new_v4());
+    let mut replies = Box::pin(subscribe_typed::<Resp>(reply_to.clone()).await?);
+
+    let data = serde_json::to_string(&RpcRequest { reply_to, payload: request })?;
+    let mut connection = get_shared_conn_manager().await?;
+    let receivers: i64 = connection.publish(db_channel, data).await?;
+    if receivers == 0 {
+        return Err(anyhow::anyhow!("No responder is subscribed to {}", db_channel));
+    }
+
+    match tokio::time::timeout(timeout, replies.next()).await {
+        Ok(Some(reply)) => Ok(reply?),
+        Ok(None) => Err(anyhow::anyhow!("Reply subscription for {} closed before a reply arrived", db_channel)),
+        Err(_) => Err(anyhow::anyhow!("No reply to request on {} within {}ms", db_channel, timeout.as_millis())),
+    }
+}
+
+/// Answer an `RpcRequest` received by a responder
+pub async fn reply<T, Resp>(request: &RpcRequest<T>, response: &Resp) -> AnyResult<()>
+where
+    Resp: Serialize,
+{
+    broadcast_json(&request.reply_to, response).await
+}
+
+/// Subscribe to a channel and forward decoded messages into a bounded channel from a
+/// background task.
+///
+/// The subscription is established before this returns, so connection errors surface
+/// here. When the receiver falls `buffer` messages behind, the task stops reading from
+/// Redis until it catches up; `buffer` must be at least 1. Messages that fail to decode
+/// are logged and skipped. The task ends when the receiver is dropped or the subscription
+/// closes.
+pub async fn spawn_subscriber<T>(db_channel: String, buffer: usize) -> AnyResult<(JoinHandle<()>, mpsc::Receiver<T>)>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    if buffer == 0 {
+        return Err(anyhow::anyhow!("Subscriber buffer for {} must hold at least one message", db_channel));
+    }
+    let mut messages = Box::pin(subscribe_typed::<T>(db_channel.clone()).await?);
+    let (sender, receiver) = mpsc::channel(buffer);
+    let handle = tokio::spawn(async move {
+        while let Some(result) = messages.next().await {
+            match result {
+                Ok(value) => {
+                    if sender.send(value).await.is_err() {
+                        break;
+                    }
+                }
+                Err(e) => warn!("Skipping undecodable message on channel {}: {}", db_channel, e),
+            }
+        }
+    });
+
+    Ok((handle, receiver))
+}
+
+/// Subscriber that reports how far the consumer is behind the publisher.
+///
+/// A background task reads from Redis into a bounded buffer and stamps each message on
+/// arrival. `lag` is how long the last message returned by `recv` waited in that buffer, and
+/// `queued` is how many are still waiting. A warning is logged when the lag first exceeds the
+/// threshold, and again after it has recovered and crossed it once more. When the buffer is
+/// full the task stops reading, so a consumer that stays behind eventually makes Redis drop
+/// the connection once its output buffer limit is hit.
+pub struct MonitoredSubscriber<T> {
+    receiver: mpsc::Receiver<(Instant, T)>,
+    // Counted once `send` returns, so `recv` can briefly take it below zero
+    queued: Arc<AtomicIsize>,
+    task: JoinHandle<()>,
+    channel: String,
+    lag_threshold: Duration,
+    lag: Duration,
+    lagging: bool,
+}
+
+impl<T> MonitoredSubscriber<T> {
+    /// Next decoded message, or `None` once the subscription has closed
+    pub async fn recv(&mut self) -> Option<T> {
+        let (received_at, value) = self.receiver.recv().await?;
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+        self.lag = received_at.elapsed();
+        if self.lag > self.lag_threshold && !self.lagging {
+            warn!(
+                "Slow consumer on channel {}: {:?} behind with {} messages queued",
+                self.channel,
+                self.lag,
+                self.queued()
+            );
+        }
+        self.lagging = self.lag > self.lag_threshold;
+        Some(value)
+    }
+
+    /// How long the last message returned by `recv` waited before being consumed
+    pub fn lag(&self) -> Duration {
+        self.lag
+    }
+
+    /// Messages received from Redis but not yet consumed
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed).max(0) as usize
+    }
+}
+
+impl<T> Drop for MonitoredSubscriber<T> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Subscribe to a channel through a `MonitoredSubscriber` holding up to `buffer` messages,
+/// warning when the consumer falls more than `lag_threshold` behind. `buffer` must be at
+/// least 1.
+pub async fn spawn_monitored_subscriber<T>(db_channel: String, buffer: usize, lag_threshold: Duration) -> AnyResult<MonitoredSubscriber<T>>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    if buffer == 0 {
+        return Err(anyhow::anyhow!("Subscriber buffer for {} must hold at least one message", db_channel));
+    }
+    let mut messages = Box::pin(subscribe_typed::<T>(db_channel.clone()).await?);
+    let (sender, receiver) = mpsc::channel(buffer);
+    let queued = Arc::new(AtomicIsize::new(0));
+    let task_queued = queued.clone();
+    let task_channel = db_channel.clone();
+    let task = tokio::spawn(async move {
+        while let Some(result) = messages.next().await {
+            match result {
+                Ok(value) => {
+                    if sender.send((Instant::now(), value)).await.is_err() {
+                        break;
+                    }
+                    task_queued.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(e) => warn!("Skipping undecodable message on channel {}: {}", task_channel, e),
+            }
+        }
+    });
+
+    Ok(MonitoredSubscriber {
+        receiver,
+        queued,
+        task,
+        channel: db_channel,
+        lag_threshold,
+        lag: Duration::ZERO,
+        lagging: false,
+    })
+}
+
+/// A subscription that sends `UNSUBSCRIBE` when it ends instead of just closing the socket.
+///
+/// Call `shutdown().await` for a clean exit that waits for Redis to acknowledge. Dropping
+/// without it cannot await, so `Drop` hands the `UNSUBSCRIBE` to a background task on the
+/// current Tokio runtime on a best-effort basis, and outside a runtime the connection is
+/// simply closed.
+pub struct Subscription {
+    pubsub: Option<PubSub>,
+    channels: Vec<String>,
+}
+
+impl Subscription {
+    /// Channels this subscription is still listening on
+    pub fn channels(&self) -> &[String] {
+        &self.channels
+    }
+
+    /// Stream of messages from all subscribed channels. Empty after `shutdown`.
+    pub fn on_message(&mut self) -> impl Stream<Item = redis::Msg> + '_ {
+        futures::stream::iter(self.pubsub.as_mut())
+            .flat_map(|pubsub| pubsub.on_message())
+    }
+
+    /// Stop listening on `channel` while keeping the others
+    pub async fn unsubscribe(&mut self, channel: &str) -> AnyResult<()> {
+        if let Some(pubsub) = self.pubsub.as_mut() {
+            pubsub.unsubscribe(channel).await?;
+        }
+        self.channels.retain(|subscribed| subscribed != channel);
+        Ok(())
+    }
+
+    /// Unsubscribe from every channel and close the connection
+    pub async fn shutdown(mut self) -> AnyResult<()> {
+        if let Some(mut pubsub) = self.pubsub.take() {
+            if !self.channels.is_empty() {
+                pubsub.unsubscribe(std::mem::take(&mut self.channels)).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let Some(mut pubsub) = self.pubsub.take() else {
+            return;
+        };
+        if self.channels.is_empty() {
+            return;
+        }
+        let channels = std::mem::take(&mut self.channels);
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn(async move {
+                if let Err(e) = pubsub.unsubscribe(&channels).await {
+                    warn!("Failed to unsubscribe from {:?} on drop: {}", channels, e);
+                }
+            });
+        }
+    }
+}
+
+/// Subscribe to `channels` on one connection, returning a `Subscription` that
+/// unsubscribes when it is shut down or dropped.
+pub async fn subscribe_managed(channels: Vec<String>) -> AnyResult<Subscription> {
+    let pubsub = subscribe_many(channels.clone()).await?;
+    Ok(Subscription {
+        pubsub: Some(pubsub),
+        channels,
+    })
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.pattern = %pattern, redis.operation = "PSUBSCRIBE", redis.latency_ms)
+))]
+pub async fn psubscribe_data(pattern: String) -> AnyResult<PubSub> {
+    let connection = get_redis_pool().await?;
     let mut pubsub = connection.into_pubsub();
-    pubsub.subscribe(db_channel).await.unwrap();
+    let start = Instant::now();
+    pubsub.psubscribe(pattern).await?;
+    record_latency(start);
     Ok(pubsub)
 }
 
-pub async fn set_if_not_exist<T>(key: String, data: T, mut conn: AsyncConnManager) -> AnyResult<bool>
+/// Subscribe to a channel pattern (e.g. `user_events:*`) and deserialize each JSON
+/// message into `T`, paired with the concrete channel it was published to.
+pub async fn psubscribe_typed<T>(pattern: String) -> AnyResult<impl Stream<Item = Result<(String, T), SubError>>>
+where
+    T: DeserializeOwned,
+{
+    if pattern.is_empty() {
+        return Err(anyhow::anyhow!("Channel pattern must not be empty"));
+    }
+
+    let pubsub = psubscribe_data(pattern).await?;
+    let stream = pubsub.into_on_message().map(|msg| {
+        let value = decode_message::<T>(&msg)?;
+        Ok((msg.get_channel_name().to_string(), value))
+    });
+
+    Ok(stream)
+}
+
+fn decode_message<T>(msg: &redis::Msg) -> Result<T, SubError>
+where
+    T: DeserializeOwned,
+{
+    let payload: String = msg.get_payload().map_err(SubError::Payload)?;
+    serde_json::from_str::<T>(&payload).map_err(|source| SubError::Decode { payload, source })
+}
+
+/// A subscription that survives Redis restarts.
+///
+/// A background task owns the underlying `PubSub`. When its message stream ends or the
+/// connection cannot be established, it reconnects and re-subscribes with exponential
+/// backoff. Messages are yielded as a `Stream` of `redis::Msg`; messages published while
+/// disconnected are lost, as with any Redis pub/sub subscriber. Dropping the subscriber
+/// stops the background task.
+pub struct ResilientSubscriber {
+    receiver: mpsc::Receiver<redis::Msg>,
+    task: JoinHandle<()>,
+}
+
+impl Stream for ResilientSubscriber {
+    type Item = redis::Msg;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl Drop for ResilientSubscriber {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Subscribe to a channel and keep the subscription alive across reconnects.
+pub fn subscribe_resilient(db_channel: String) -> ResilientSubscriber {
+    let (sender, receiver) = mpsc::channel(RESILIENT_BUFFER_SIZE);
+    let task = tokio::spawn(run_resilient_subscription(db_channel, sender));
+    ResilientSubscriber { receiver, task }
+}
+
+async fn run_resilient_subscription(db_channel: String, sender: mpsc::Sender<redis::Msg>) {
+    let mut backoff = RESILIENT_INITIAL_BACKOFF;
+
+    loop {
+        match subscribe_data(db_channel.clone()).await {
+            Ok(pubsub) => {
+                info!("Subscribed to channel: {}", db_channel);
+                backoff = RESILIENT_INITIAL_BACKOFF;
+
+                let mut messages = pubsub.into_on_message();
+                while let Some(msg) = messages.next().await {
+                    if sender.send(msg).await.is_err() {
+                        // Receiver dropped, nobody is listening anymore
+                        return;
+                    }
+                }
+                warn!("Subscription to channel {} ended, reconnecting", db_channel);
+            }
+            Err(e) => {
+                warn!("Failed to subscribe to channel {}: {}. Retrying in {:?}", db_channel, e, backoff);
+            }
+        }
+
+        if sender.is_closed() {
+            return;
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(RESILIENT_MAX_BACKOFF);
+    }
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "SETNX", redis.latency_ms)
+))]
+pub async fn set_if_not_exist<T, C>(key: String, data: T, mut conn: C) -> AnyResult<bool>
 where
     T: 'static + Clone + Sync + Send + redis::ToRedisArgs,
+    C: ConnectionLike + Send,
 {
-    let res = conn.set_nx(key, data).await.unwrap();
+    let start = Instant::now();
+    let res = conn.set_nx(key, data).await?;
+    record_latency(start);
 
     Ok(res)
 }
 
-pub async fn get_data<T>(key: String, mut conn: AsyncConnManager) -> AnyResult<Option<T>>
+/// Read a string value.
+///
+/// A missing key is `Ok(None)`. A key holding another data structure (a hash or list
+/// reused under the same name) fails with the `WRONGTYPE` error instead of reading as a
+/// miss. Retried on transient failures according to the retry settings of `shared_config`.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "GET", redis.latency_ms)
+))]
+pub async fn get_data<T, C>(key: String, mut conn: C) -> AnyResult<Option<T>>
 where
     T: redis::FromRedisValue,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let res: Option<T> = RetryPolicy::from_config(shared_config()).query(redis::cmd("GET").arg(key), &mut conn).await?;
+    record_latency(start);
+    Ok(res)
+}
+
+/// Retried on transient failures according to the retry settings of `shared_config`.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "SET", redis.latency_ms)
+))]
+pub async fn set_data<T, C>(key: String, data: T, mut conn: C) -> AnyResult<bool>
+where
+    T: 'static + Clone + Sync + Send + redis::ToRedisArgs,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let res = RetryPolicy::from_config(shared_config()).query(redis::cmd("SET").arg(key).arg(data), &mut conn).await?;
+    record_latency(start);
+
+    Ok(res)
+}
+
+/// Store `data` with an expiry, without the NX condition of `set_with_options`.
+///
+/// A `ttl_seconds` of 0 stores the key without expiry, like `set_data`. Retried on
+/// transient failures according to the retry settings of `shared_config`.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "SET EX", redis.latency_ms)
+))]
+pub async fn set_data_ex<T, C>(key: String, data: T, ttl_seconds: u64, mut conn: C) -> AnyResult<bool>
+where
+    T: 'static + Clone + Sync + Send + redis::ToRedisArgs,
+    C: ConnectionLike + Send,
 {
-    let res = conn.get(key).await.ok();
-    let result = match res {
-        Some(res) => Some(res),
-        None => None,
-    };
-    Ok(result)
+    let mut cmd = redis::cmd("SET");
+    cmd.arg(key).arg(data);
+    if ttl_seconds > 0 {
+        cmd.arg("EX").arg(ttl_seconds);
+    }
+    let start = Instant::now();
+    let res = RetryPolicy::from_config(shared_config()).query(&cmd, &mut conn).await?;
+    record_latency(start);
+
+    Ok(res)
 }
 
-pub async fn set_data<T>(key: String, data: T, mut conn: AsyncConnManager) -> AnyResult<bool>
+/// An expiry as the milliseconds `PX` takes. Redis rejects `PX 0`, so an expiry under one
+/// millisecond is an error rather than a failed command.
+pub(crate) fn px_millis(expiry: Duration) -> AnyResult<usize> {
+    if expiry < Duration::from_millis(1) {
+        return Err(anyhow::anyhow!("Expiry must be at least 1ms, got {:?}", expiry));
+    }
+    Ok(usize::try_from(expiry.as_millis()).unwrap_or(usize::MAX))
+}
+
+/// Store `data` only if the key is absent, expiring it after `expiry`.
+///
+/// Returns `true` when the key was set. Sub-second expiries are kept, since the expiry is
+/// sent in milliseconds; an expiry under 1ms is an error.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "SET NX PX", redis.latency_ms)
+))]
+pub async fn set_nx_with_expiry<T, C>(key: String, data: T, expiry: Duration, mut conn: C) -> AnyResult<bool>
 where
     T: 'static + Clone + Sync + Send + redis::ToRedisArgs,
+    C: ConnectionLike + Send,
 {
-    let res = conn.set(key, data).await.unwrap();
+    let opts = SetOptions::default()
+        .conditional_set(ExistenceCheck::NX)
+        .with_expiration(redis::SetExpiry::PX(px_millis(expiry)?));
+    let start = Instant::now();
+    let res = conn.set_options(key, data, opts).await?;
+    record_latency(start);
 
     Ok(res)
 }
 
-pub async fn set_with_options<T>(key: String, data: T, mut conn: AsyncConnManager) -> AnyResult<bool>
+/// `set_nx_with_expiry` using the `idempotent_expiry` window of `shared_config`
+pub async fn set_with_options<T, C>(key: String, data: T, conn: C) -> AnyResult<bool>
+where
+    T: 'static + Clone + Sync + Send + redis::ToRedisArgs,
+    C: ConnectionLike + Send,
+{
+    set_nx_with_expiry(key, data, get_idempotent_expiry(), conn).await
+}
+
+/// Store `data` and return the value it replaced, atomically
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "GETSET", redis.latency_ms)
+))]
+pub async fn getset<T, V, C>(key: String, data: T, mut conn: C) -> AnyResult<Option<V>>
 where
     T: 'static + Clone + Sync + Send + redis::ToRedisArgs,
+    V: redis::FromRedisValue,
+    C: ConnectionLike + Send,
 {
-    let expiry_in_sec = env::var("IDEMPOTENT_EXPIRY_IN_SEC").unwrap_or("120".to_string()).parse().unwrap_or(120);
-    let opts = SetOptions::default().conditional_set(ExistenceCheck::NX).with_expiration(redis::SetExpiry::EX(expiry_in_sec));
-    let res = conn.set_options(key, data, opts).await.unwrap();
+    let start = Instant::now();
+    let res = conn.getset(key, data).await?;
+    record_latency(start);
 
     Ok(res)
 }
+
+/// Read a value and delete the key, atomically. Suited to one-time tokens. Needs Redis 6.2+.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "GETDEL", redis.latency_ms)
+))]
+pub async fn getdel<T, C>(key: String, mut conn: C) -> AnyResult<Option<T>>
+where
+    T: redis::FromRedisValue,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let res = redis::cmd("GETDEL").arg(key).query_async(&mut conn).await?;
+    record_latency(start);
+
+    Ok(res)
+}
+
+/// Read bytes `start..=end` of a string value without transferring the rest.
+///
+/// Negative offsets count from the end, so `-1` is the last byte. Out-of-range offsets are
+/// clamped, and a missing key reads as empty.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "GETRANGE", redis.latency_ms)
+))]
+pub async fn get_range<C>(key: String, start: isize, end: isize, mut conn: C) -> AnyResult<Vec<u8>>
+where
+    C: ConnectionLike + Send,
+{
+    let started = Instant::now();
+    let res = conn.getrange(key, start, end).await?;
+    record_latency(started);
+
+    Ok(res)
+}
+
+/// Overwrite part of a string value starting at `offset`, zero-padding a missing or shorter
+/// value as needed. Returns the length of the value after the write.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "SETRANGE", redis.latency_ms)
+))]
+pub async fn set_range<C>(key: String, offset: usize, data: &[u8], mut conn: C) -> AnyResult<u64>
+where
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let len = conn.setrange(key, offset as isize, data).await?;
+    record_latency(start);
+
+    Ok(len)
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "INCRBY", redis.latency_ms)
+))]
+pub async fn incr<C>(key: String, by: i64, mut conn: C) -> AnyResult<i64>
+where
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let res = conn.incr(key, by).await?;
+    record_latency(start);
+
+    Ok(res)
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "DECRBY", redis.latency_ms)
+))]
+pub async fn decr<C>(key: String, by: i64, mut conn: C) -> AnyResult<i64>
+where
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let res = conn.decr(key, by).await?;
+    record_latency(start);
+
+    Ok(res)
+}
+
+/// Increment a counter and set its expiry only when the counter has none yet, so
+/// later increments within the window don't extend it. Returns the new value.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "INCRBY EXPIRE", redis.latency_ms)
+))]
+pub async fn incr_with_expiry<C>(key: String, by: i64, ttl_seconds: u64, mut conn: C) -> AnyResult<i64>
+where
+    C: ConnectionLike + Send,
+{
+    let script = redis::Script::new(
+        r"
+        local value = redis.call('INCRBY', KEYS[1], ARGV[1])
+        if redis.call('TTL', KEYS[1]) == -1 then
+            redis.call('EXPIRE', KEYS[1], ARGV[2])
+        end
+        return value
+        ",
+    );
+    let start = Instant::now();
+    let res = script.key(key).arg(by).arg(ttl_seconds).invoke_async(&mut conn).await?;
+    record_latency(start);
+
+    Ok(res)
+}
+
+/// Add elements to a HyperLogLog. Returns `true` if the estimated cardinality changed.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "PFADD", redis.latency_ms)
+))]
+pub async fn pfadd<E, C>(key: String, elements: E, mut conn: C) -> AnyResult<bool>
+where
+    E: redis::ToRedisArgs + Send + Sync,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let res = conn.pfadd(key, elements).await?;
+    record_latency(start);
+
+    Ok(res)
+}
+
+/// Approximate number of distinct elements across `keys` (standard error about 0.81%).
+/// Missing keys count as empty. In cluster mode all keys must hash to the same slot.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = ?keys, redis.operation = "PFCOUNT", redis.latency_ms)
+))]
+pub async fn pfcount<C>(keys: Vec<String>, mut conn: C) -> AnyResult<u64>
+where
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let res = conn.pfcount(keys).await?;
+    record_latency(start);
+
+    Ok(res)
+}
+
+/// Merge `sources` into `dest`, so `dest` estimates the union of all of them
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %dest, redis.operation = "PFMERGE", redis.latency_ms)
+))]
+pub async fn pfmerge<C>(dest: String, sources: Vec<String>, mut conn: C) -> AnyResult<()>
+where
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    conn.pfmerge::<_, _, ()>(dest, sources).await?;
+    record_latency(start);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{block_on_shared, live_conn, live_prefix, silent_server, FlakyConnection};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Order {
+        id: u32,
+    }
+
+    #[test]
+    fn envelopes_round_trip_with_their_metadata() {
+        let message = Message::new("orders", vec![1, 2, 3]);
+        let decoded: Message<Vec<u32>> = serde_json::from_str(&serde_json::to_string(&message).unwrap()).unwrap();
+        assert_eq!(decoded.version, ENVELOPE_VERSION);
+        assert_eq!(decoded.id, message.id);
+        assert_eq!(decoded.published_at, message.published_at);
+        assert_eq!(decoded.channel, "orders");
+        assert_eq!(decoded.payload, [1, 2, 3]);
+
+        let unversioned = serde_json::json!({
+            "id": message.id,
+            "published_at": message.published_at,
+            "channel": "orders",
+            "payload": [1],
+        });
+        let decoded: Message<Vec<u32>> = serde_json::from_value(unversioned).unwrap();
+        assert_eq!(decoded.version, 1);
+    }
+
+    #[tokio::test]
+    async fn subscribing_times_out_when_redis_never_confirms() {
+        let mut info = redis::IntoConnectionInfo::into_connection_info(silent_server().await).unwrap();
+        info.redis.db = 0;
+        let connection = redis::Client::open(info).unwrap().get_async_connection().await.unwrap();
+        let started = Instant::now();
+
+        let error = subscribe_confirmed(connection, "orders", Duration::from_millis(200)).await.err().unwrap();
+        assert!(error.to_string().contains("did not confirm subscription to orders within 200ms"), "{}", error);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn an_expiry_under_a_millisecond_is_rejected_before_sending() {
+        let conn = FlakyConnection::new(0, redis::Value::Okay);
+        for expiry in [Duration::ZERO, Duration::from_micros(999)] {
+            assert!(set_nx_with_expiry("lock".to_string(), "1", expiry, conn.clone()).await.is_err());
+        }
+        assert_eq!(conn.requests(), 0);
+        assert!(set_nx_with_expiry("lock".to_string(), "1", Duration::from_millis(1), conn.clone()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn publishing_to_unreachable_redis_returns_err() {
+        let mut conn = FlakyConnection::down();
+        let result = publish(&mut conn, "events".to_string(), "payload".to_string()).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn get_data_on_a_list_is_a_wrongtype_error() {
+        let (mut conn, prefix) = live_conn().await;
+        let key = format!("{}:jobs", prefix);
+        let _: () = conn.rpush(&key, "job-1").await.unwrap();
+
+        let error = get_data::<String, _>(key.clone(), conn.clone()).await.unwrap_err();
+        let error = error.downcast_ref::<redis::RedisError>().expect("a Redis error");
+        assert_eq!(error.code(), Some("WRONGTYPE"));
+        assert_eq!(get_data::<String, _>(format!("{}:missing", prefix), conn.clone()).await.unwrap(), None);
+        let _: () = conn.del(&key).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn idempotency_keys_keep_their_own_expiry() {
+        let (mut conn, prefix) = live_conn().await;
+        let short = format!("{}:short", prefix);
+        let long = format!("{}:long", prefix);
+
+        assert!(set_nx_with_expiry(short.clone(), "1", Duration::from_secs(10), conn.clone()).await.unwrap());
+        assert!(set_nx_with_expiry(long.clone(), "1", Duration::from_secs(600), conn.clone()).await.unwrap());
+        assert!(!set_nx_with_expiry(short.clone(), "2", Duration::from_secs(600), conn.clone()).await.unwrap());
+
+        let short_ttl: i64 = conn.pttl(&short).await.unwrap();
+        let long_ttl: i64 = conn.pttl(&long).await.unwrap();
+        assert!((1..=10_000).contains(&short_ttl), "{}", short_ttl);
+        assert!((590_000..=600_000).contains(&long_ttl), "{}", long_ttl);
+        let _: () = conn.del(&[short, long]).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn hyperloglog_counts_overlapping_visitors_within_error_bounds() {
+        let (mut conn, prefix) = live_conn().await;
+        let monday = format!("{}:visitors:mon", prefix);
+        let tuesday = format!("{}:visitors:tue", prefix);
+        let week = format!("{}:visitors:week", prefix);
+        let visitors = |range: std::ops::Range<u32>| range.map(|id| format!("user-{}", id)).collect::<Vec<_>>();
+
+        assert!(pfadd(monday.clone(), visitors(0..6_000), conn.clone()).await.unwrap());
+        assert!(pfadd(tuesday.clone(), visitors(4_000..10_000), conn.clone()).await.unwrap());
+        assert!(!pfadd(monday.clone(), "user-1", conn.clone()).await.unwrap());
+
+        // 3% is almost four standard errors
+        let within_bounds = |estimate: u64, actual: f64| (estimate as f64 - actual).abs() <= actual * 0.03;
+        let single = pfcount(vec![monday.clone()], conn.clone()).await.unwrap();
+        assert!(within_bounds(single, 6_000.0), "{}", single);
+        let union = pfcount(vec![monday.clone(), tuesday.clone()], conn.clone()).await.unwrap();
+        assert!(within_bounds(union, 10_000.0), "{}", union);
+
+        pfmerge(week.clone(), vec![monday.clone(), tuesday.clone()], conn.clone()).await.unwrap();
+        assert_eq!(pfcount(vec![week.clone()], conn.clone()).await.unwrap(), union);
+        let _: () = conn.del(&[monday, tuesday, week]).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_spawned_subscriber_needs_room_for_a_message() {
+        let error = spawn_subscriber::<Order>("orders".to_string(), 0).await.unwrap_err();
+        assert!(error.to_string().contains("at least one message"), "{}", error);
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    fn a_spawned_subscriber_forwards_every_message() {
+        block_on_shared(async {
+            let channel = format!("{}:orders", live_prefix());
+            // A buffer smaller than the burst makes the task wait for the consumer
+            let (handle, mut orders) = spawn_subscriber::<Order>(channel.clone(), 2).await.unwrap();
+            broadcasting_data(channel.clone(), "not an order".to_string()).await.unwrap();
+            for id in 0..5 {
+                broadcast_json(&channel, &Order { id }).await.unwrap();
+            }
+
+            for id in 0..5 {
+                let order = tokio::time::timeout(Duration::from_secs(5), orders.recv()).await.unwrap();
+                assert_eq!(order, Some(Order { id }));
+            }
+            drop(orders);
+            broadcast_json(&channel, &Order { id: 5 }).await.unwrap();
+            tokio::time::timeout(Duration::from_secs(5), handle).await.unwrap().unwrap();
+        })
+    }
+
+    /// Channels matching `pattern` that have at least one subscriber
+    async fn active_channels(pattern: &str) -> Vec<String> {
+        let mut conn = get_shared_conn_manager().await.unwrap();
+        redis::cmd("PUBSUB").arg("CHANNELS").arg(pattern).query_async(&mut conn).await.unwrap()
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    fn shutting_down_or_dropping_a_subscription_unsubscribes() {
+        block_on_shared(async {
+            let prefix = live_prefix();
+            let pattern = format!("{}:*", prefix);
+            let orders = format!("{}:orders", prefix);
+            let invoices = format!("{}:invoices", prefix);
+
+            let subscription = subscribe_managed(vec![orders.clone()]).await.unwrap();
+            assert_eq!(active_channels(&pattern).await, [orders.as_str()]);
+            subscription.shutdown().await.unwrap();
+            assert!(active_channels(&pattern).await.is_empty());
+
+            // Dropping unsubscribes from a background task, so give it a moment
+            let subscription = subscribe_managed(vec![invoices.clone()]).await.unwrap();
+            assert_eq!(active_channels(&pattern).await, [invoices]);
+            drop(subscription);
+            let mut remaining = active_channels(&pattern).await;
+            for _ in 0..50 {
+                if remaining.is_empty() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                remaining = active_channels(&pattern).await;
+            }
+            assert!(remaining.is_empty(), "{:?}", remaining);
+        })
+    }
+
+    #[tokio::test]
+    async fn a_monitored_subscriber_needs_room_for_a_message() {
+        let error = spawn_monitored_subscriber::<Order>("orders".to_string(), 0, Duration::from_secs(1)).await.err().unwrap();
+        assert!(error.to_string().contains("at least one message"), "{}", error);
+    }
+
+    async fn recv(orders: &mut MonitoredSubscriber<Order>) -> Order {
+        tokio::time::timeout(Duration::from_secs(5), orders.recv()).await.unwrap().unwrap()
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    fn a_slow_consumer_sees_its_lag_rise() {
+        block_on_shared(async {
+            let channel = format!("{}:orders", live_prefix());
+            let mut orders = spawn_monitored_subscriber::<Order>(channel.clone(), 10, Duration::from_millis(100)).await.unwrap();
+            broadcast_json(&channel, &Order { id: 0 }).await.unwrap();
+            assert_eq!(recv(&mut orders).await, Order { id: 0 });
+            assert!(orders.lag() < Duration::from_millis(100), "{:?}", orders.lag());
+
+            for id in 1..4 {
+                broadcast_json(&channel, &Order { id }).await.unwrap();
+            }
+            // Busy with something else while the messages queue up
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            assert_eq!(orders.queued(), 3);
+            assert_eq!(recv(&mut orders).await, Order { id: 1 });
+            assert!(orders.lag() >= Duration::from_millis(200), "{:?}", orders.lag());
+            assert_eq!(orders.queued(), 2);
+        })
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    fn a_message_waiting_for_buffer_space_is_not_counted_as_queued() {
+        block_on_shared(async {
+            let channel = format!("{}:orders", live_prefix());
+            let mut orders = spawn_monitored_subscriber::<Order>(channel.clone(), 1, Duration::from_secs(1)).await.unwrap();
+            for id in 0..3 {
+                broadcast_json(&channel, &Order { id }).await.unwrap();
+            }
+            // One message fills the buffer and the next waits in `send`
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            assert_eq!(orders.queued(), 1);
+            for id in 0..3 {
+                assert_eq!(recv(&mut orders).await, Order { id });
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            assert_eq!(orders.queued(), 0);
+        })
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    fn broadcasting_to_several_channels_reports_each_receiver_count() {
+        block_on_shared(async {
+            let prefix = live_prefix();
+            let channels = ["orders", "invoices", "refunds"].map(|name| format!("{}:{}", prefix, name));
+            let _orders = subscribe_data(channels[0].clone()).await.unwrap();
+            let _refunds = subscribe_many(vec![channels[2].clone()]).await.unwrap();
+            let _refunds_again = subscribe_data(channels[2].clone()).await.unwrap();
+
+            let messages = channels.iter().map(|channel| (channel.clone(), "event".to_string())).collect::<Vec<_>>();
+            assert_eq!(broadcast_many(&messages).await.unwrap(), [1, 0, 2]);
+            assert!(broadcast_many(&[]).await.unwrap().is_empty());
+        })
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    fn a_request_gets_the_reply_of_its_responder() {
+        block_on_shared(async {
+            let channel = format!("{}:double", live_prefix());
+            let no_responder = request_reply::<_, u32>(&channel, 1, Duration::from_secs(1)).await.unwrap_err();
+            assert!(no_responder.to_string().starts_with("No responder is subscribed"), "{}", no_responder);
+
+            let mut requests = Box::pin(subscribe_typed::<RpcRequest<u32>>(channel.clone()).await.unwrap());
+            let responder = tokio::spawn(async move {
+                while let Some(Ok(request)) = requests.next().await {
+                    if request.payload > 0 {
+                        reply(&request, &(request.payload * 2)).await.unwrap();
+                    }
+                }
+            });
+
+            assert_eq!(request_reply::<_, u32>(&channel, 21, Duration::from_secs(5)).await.unwrap(), 42);
+            // The responder ignores 0, so this one times out
+            let started = Instant::now();
+            let timeout = request_reply::<_, u32>(&channel, 0, Duration::from_millis(200)).await.unwrap_err();
+            assert!(timeout.to_string().starts_with("No reply to request"), "{}", timeout);
+            assert!(started.elapsed() < Duration::from_secs(2));
+            responder.abort();
+        })
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn only_the_first_increment_sets_the_expiry() {
+        let (mut conn, prefix) = live_conn().await;
+        let key = format!("{}:requests", prefix);
+
+        assert_eq!(incr_with_expiry(key.clone(), 1, 100, conn.clone()).await.unwrap(), 1);
+        let ttl: i64 = conn.ttl(&key).await.unwrap();
+        assert!((1..=100).contains(&ttl), "{}", ttl);
+
+        // Shorten the window; a later increment must not stretch it back to 100s
+        let _: () = conn.expire(&key, 50).await.unwrap();
+        assert_eq!(incr_with_expiry(key.clone(), 2, 100, conn.clone()).await.unwrap(), 3);
+        let ttl: i64 = conn.ttl(&key).await.unwrap();
+        assert!((1..=50).contains(&ttl), "{}", ttl);
+
+        assert_eq!(incr(key.clone(), 5, conn.clone()).await.unwrap(), 8);
+        assert_eq!(decr(key.clone(), 10, conn.clone()).await.unwrap(), -2);
+        let _: () = conn.del(&key).await.unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    fn a_published_struct_arrives_through_a_typed_subscriber() {
+        block_on_shared(async {
+            let channel = format!("{}:orders", live_prefix());
+            let mut orders = Box::pin(subscribe_typed::<Order>(channel.clone()).await.unwrap());
+
+            broadcast_json(&channel, &Order { id: 7 }).await.unwrap();
+            broadcasting_data(channel.clone(), "not json".to_string()).await.unwrap();
+
+            let order = tokio::time::timeout(Duration::from_secs(5), orders.next()).await.unwrap();
+            assert_eq!(order.unwrap().unwrap(), Order { id: 7 });
+            match tokio::time::timeout(Duration::from_secs(5), orders.next()).await.unwrap().unwrap() {
+                Err(SubError::Decode { payload, .. }) => assert_eq!(payload, "not json"),
+                other => panic!("expected a decode error, got {:?}", other),
+            }
+        })
+    }
+
+    /// Ids of the clients connected in pub/sub mode
+    async fn pubsub_client_ids() -> std::collections::HashSet<String> {
+        let mut conn = get_shared_conn_manager().await.unwrap();
+        let clients: String = redis::cmd("CLIENT").arg("LIST").arg("TYPE").arg("pubsub").query_async(&mut conn).await.unwrap();
+        clients
+            .lines()
+            .filter_map(|client| client.split(' ').find_map(|field| field.strip_prefix("id=")))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Publish `payload` until the subscriber sees it, as a fresh subscription may not be live yet
+    async fn publish_until_received(subscriber: &mut ResilientSubscriber, channel: &str, payload: &str) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            broadcasting_data(channel.to_string(), payload.to_string()).await.unwrap();
+            while let Ok(Some(msg)) = tokio::time::timeout(Duration::from_millis(100), subscriber.next()).await {
+                if msg.get_payload::<String>().unwrap() == payload {
+                    return;
+                }
+            }
+        }
+        panic!("{} was never received on {}", payload, channel);
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    fn a_resilient_subscriber_resumes_after_its_connection_is_killed() {
+        block_on_shared(async {
+            let channel = format!("{}:orders", live_prefix());
+            let before = pubsub_client_ids().await;
+            let mut subscriber = subscribe_resilient(channel.clone());
+            publish_until_received(&mut subscriber, &channel, "before").await;
+
+            let ours: Vec<String> = pubsub_client_ids().await.difference(&before).cloned().collect();
+            assert_eq!(ours.len(), 1, "cannot tell which subscriber is ours: {:?}", ours);
+            let mut conn = get_shared_conn_manager().await.unwrap();
+            let killed: u32 = redis::cmd("CLIENT").arg("KILL").arg("ID").arg(&ours[0]).query_async(&mut conn).await.unwrap();
+            assert_eq!(killed, 1);
+
+            publish_until_received(&mut subscriber, &channel, "after").await;
+            assert!(!pubsub_client_ids().await.contains(&ours[0]));
+        })
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    fn a_pattern_subscriber_sees_which_channel_each_message_came_from() {
+        block_on_shared(async {
+            let prefix = live_prefix();
+            let mut events = Box::pin(psubscribe_typed::<Order>(format!("{}:user_events:*", prefix)).await.unwrap());
+
+            broadcast_json(&format!("{}:user_events:1", prefix), &Order { id: 1 }).await.unwrap();
+            broadcast_json(&format!("{}:other_events:1", prefix), &Order { id: 0 }).await.unwrap();
+            broadcast_json(&format!("{}:user_events:2", prefix), &Order { id: 2 }).await.unwrap();
+
+            for id in 1..=2 {
+                let (channel, order) = tokio::time::timeout(Duration::from_secs(5), events.next()).await.unwrap().unwrap().unwrap();
+                assert_eq!(channel, format!("{}:user_events:{}", prefix, id));
+                assert_eq!(order, Order { id });
+            }
+        })
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn getset_swaps_and_getdel_consumes_a_value() {
+        let (conn, prefix) = live_conn().await;
+        let token = format!("{}:token", prefix);
+
+        assert_eq!(getset::<_, String, _>(token.clone(), "first", conn.clone()).await.unwrap(), None);
+        assert_eq!(getset::<_, String, _>(token.clone(), "second", conn.clone()).await.unwrap().as_deref(), Some("first"));
+        assert_eq!(getdel::<String, _>(token.clone(), conn.clone()).await.unwrap().as_deref(), Some("second"));
+        assert_eq!(getdel::<String, _>(token.clone(), conn.clone()).await.unwrap(), None);
+        assert_eq!(get_data::<String, _>(token, conn).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn set_data_ex_expires_unless_the_ttl_is_zero() {
+        let (mut conn, prefix) = live_conn().await;
+        let session = format!("{}:session", prefix);
+        let setting = format!("{}:setting", prefix);
+
+        assert!(set_data_ex(session.clone(), "alice", 60, conn.clone()).await.unwrap());
+        assert!(set_data_ex(setting.clone(), "dark", 0, conn.clone()).await.unwrap());
+        let session_ttl: i64 = conn.ttl(&session).await.unwrap();
+        assert!((1..=60).contains(&session_ttl), "{}", session_ttl);
+        let setting_ttl: i64 = conn.ttl(&setting).await.unwrap();
+        assert_eq!(setting_ttl, -1);
+        let _: () = conn.del(&[session, setting]).await.unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    fn one_connection_receives_from_several_channels() {
+        block_on_shared(async {
+            let prefix = live_prefix();
+            let user_events = format!("{}:user_events", prefix);
+            let notifications = format!("{}:notifications", prefix);
+            let mut messages = Box::pin(subscribe_many_typed::<Order>(vec![user_events.clone(), notifications.clone()]).await.unwrap());
+
+            broadcast_json(&user_events, &Order { id: 1 }).await.unwrap();
+            broadcast_json(&notifications, &Order { id: 2 }).await.unwrap();
+
+            for (channel, id) in [(&user_events, 1), (&notifications, 2)] {
+                let received = tokio::time::timeout(Duration::from_secs(5), messages.next()).await.unwrap().unwrap().unwrap();
+                assert_eq!(received, (channel.clone(), Order { id }));
+            }
+            assert!(subscribe_many(vec![user_events, String::new()]).await.is_err());
+        })
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn ranges_of_a_string_are_read_and_overwritten_in_place() {
+        let (mut conn, prefix) = live_conn().await;
+        let key = format!("{}:blob", prefix);
+        assert!(set_data(key.clone(), "HEADERbody-of-the-blob", conn.clone()).await.unwrap());
+
+        assert_eq!(get_range(key.clone(), 0, 5, conn.clone()).await.unwrap(), b"HEADER");
+        assert_eq!(get_range(key.clone(), -4, -1, conn.clone()).await.unwrap(), b"blob");
+        assert_eq!(get_range(key.clone(), 18, 100, conn.clone()).await.unwrap(), b"blob");
+        assert_eq!(set_range(key.clone(), 0, b"header", conn.clone()).await.unwrap(), 22);
+        assert_eq!(get_range(key.clone(), 0, 9, conn.clone()).await.unwrap(), b"headerbody");
+
+        // Writing past the end zero-pads the gap
+        let padded = format!("{}:padded", prefix);
+        assert_eq!(set_range(padded.clone(), 3, b"xy", conn.clone()).await.unwrap(), 5);
+        assert_eq!(get_range(padded.clone(), 0, -1, conn.clone()).await.unwrap(), b"\0\0\0xy");
+        assert!(get_range(format!("{}:missing", prefix), 0, -1, conn.clone()).await.unwrap().is_empty());
+        let _: () = conn.del(&[key, padded]).await.unwrap();
+    }
+}
+
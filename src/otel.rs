@@ -0,0 +1,55 @@
+//! OpenTelemetry instruments for cache operations. Without the `opentelemetry` feature these
+//! are no-ops.
+//!
+//! Instruments are created from the global meter provider on first use, so install the
+//! provider with `opentelemetry::global::set_meter_provider` before the first cache call.
+
+use std::time::Instant;
+
+#[cfg(feature = "opentelemetry")]
+struct Instruments {
+    operations: opentelemetry::metrics::Counter<u64>,
+    duration: opentelemetry::metrics::Histogram<f64>,
+}
+
+#[cfg(feature = "opentelemetry")]
+fn instruments() -> &'static Instruments {
+    static INSTRUMENTS: std::sync::OnceLock<Instruments> = std::sync::OnceLock::new();
+    INSTRUMENTS.get_or_init(|| {
+        let meter = opentelemetry::global::meter("shared-redis");
+        Instruments {
+            operations: meter
+                .u64_counter("cache.operations")
+                .with_description("Cache lookups and failed writes by outcome")
+                .build(),
+            duration: meter
+                .f64_histogram("cache.duration")
+                .with_description("Redis round-trip time of cache operations")
+                .with_unit("ms")
+                .build(),
+        }
+    })
+}
+
+/// Count a cache operation with its outcome (`hit`, `miss` or `error`)
+#[allow(unused_variables)]
+pub(crate) fn record_operation(operation: &'static str, result: &'static str) {
+    #[cfg(feature = "opentelemetry")]
+    instruments().operations.add(
+        1,
+        &[
+            opentelemetry::KeyValue::new("operation", operation),
+            opentelemetry::KeyValue::new("result", result),
+        ],
+    );
+}
+
+/// Record the Redis round-trip time of a cache operation since `start`
+#[allow(unused_variables)]
+pub(crate) fn record_duration(operation: &'static str, start: Instant) {
+    #[cfg(feature = "opentelemetry")]
+    instruments().duration.record(
+        start.elapsed().as_secs_f64() * 1000.0,
+        &[opentelemetry::KeyValue::new("operation", operation)],
+    );
+}
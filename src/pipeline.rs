@@ -0,0 +1,105 @@
+use crate::config::RedisConn;
+use crate::trace::record_latency;
+use anyhow::Result as AnyResult;
+use redis::aio::ConnectionLike;
+use redis::{Cmd, FromRedisValue, Pipeline};
+use std::time::Instant;
+
+/// General-purpose command pipeline bound to a connection.
+///
+/// Queued commands are sent together and their replies read back in one round trip.
+/// Without `atomic()` other clients' commands may interleave with the pipeline; with it
+/// the commands run inside `MULTI`/`EXEC`.
+pub struct CommandPipeline<C> {
+    conn: C,
+    pipe: Pipeline,
+}
+
+/// Start a pipeline on any connection
+pub fn pipeline<C>(conn: C) -> CommandPipeline<C>
+where
+    C: ConnectionLike + Send,
+{
+    CommandPipeline { conn, pipe: redis::pipe() }
+}
+
+impl RedisConn {
+    /// Start a pipeline on a clone of this connection
+    pub fn pipeline(&self) -> CommandPipeline<RedisConn> {
+        pipeline(self.clone())
+    }
+}
+
+impl<C> CommandPipeline<C>
+where
+    C: ConnectionLike + Send,
+{
+    /// Queue a command whose reply is included in the result
+    pub fn add(&mut self, cmd: &Cmd) -> &mut Self {
+        self.pipe.add_command(cmd.clone());
+        self
+    }
+
+    /// Queue a command whose reply is dropped from the result
+    pub fn add_ignored(&mut self, cmd: &Cmd) -> &mut Self {
+        self.pipe.add_command(cmd.clone()).ignore();
+        self
+    }
+
+    /// Wrap the queued commands in `MULTI`/`EXEC`
+    pub fn atomic(&mut self) -> &mut Self {
+        self.pipe.atomic();
+        self
+    }
+
+    /// Number of queued commands
+    pub fn len(&self) -> usize {
+        self.pipe.cmd_iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Send the queued commands and read the replies.
+    ///
+    /// `T` is typically a tuple with one element per non-ignored command, or a `Vec` when
+    /// all replies share a type.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        skip_all,
+        fields(redis.operation = "PIPELINE", redis.commands = self.len(), redis.latency_ms)
+    ))]
+    pub async fn execute<T: FromRedisValue>(&mut self) -> AnyResult<T> {
+        let start = Instant::now();
+        let res = self.pipe.query_async(&mut self.conn).await?;
+        record_latency(start);
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AsyncConnManager;
+    use crate::testing::FakeRedis;
+
+    #[tokio::test]
+    async fn queued_commands_come_back_in_one_reply() {
+        let server = FakeRedis::start().await;
+        server.insert("greeting", b"hello");
+        let conn = AsyncConnManager::new(redis::Client::open(server.url()).unwrap()).await.unwrap();
+
+        let mut pipe = pipeline(conn.clone());
+        pipe.add(redis::cmd("INCR").arg("visits"))
+            .add_ignored(&redis::cmd("PING"))
+            .add(&Cmd::get("greeting"));
+        assert_eq!(pipe.len(), 3);
+        let (visits, greeting): (u64, String) = pipe.execute().await.unwrap();
+        assert_eq!((visits, greeting.as_str()), (1, "hello"));
+
+        let mut pipe = pipeline(conn);
+        pipe.atomic().add(redis::cmd("INCR").arg("visits")).add(&Cmd::get("visits"));
+        let replies: Vec<u64> = pipe.execute().await.unwrap();
+        assert_eq!(replies, [2, 2]);
+    }
+}
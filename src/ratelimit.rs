@@ -0,0 +1,118 @@
+use crate::cache::log_key;
+use crate::config::{get_shared_conn_manager, is_log_redact_enabled};
+use anyhow::Result as AnyResult;
+use log::debug;
+use redis::aio::ConnectionLike;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Sliding-window check executed atomically. Uses the Redis server clock so that
+/// services with skewed clocks share a consistent window.
+const SLIDING_WINDOW_SCRIPT: &str = r"
+local key = KEYS[1]
+local max = tonumber(ARGV[1])
+local window = tonumber(ARGV[2])
+local nonce = ARGV[3]
+
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+
+redis.call('ZREMRANGEBYSCORE', key, 0, now - window)
+local count = redis.call('ZCARD', key)
+local allowed = 0
+if count < max then
+    redis.call('ZADD', key, now, now .. '-' .. nonce)
+    count = count + 1
+    allowed = 1
+end
+redis.call('PEXPIRE', key, window)
+
+local reset_after = window
+local oldest = redis.call('ZRANGE', key, 0, 0, 'WITHSCORES')
+if oldest[2] then
+    reset_after = tonumber(oldest[2]) + window - now
+end
+
+return {allowed, max - count, reset_after}
+";
+
+/// Outcome of a rate limit check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitResult {
+    /// Whether this request is within the limit
+    pub allowed: bool,
+    /// Requests still allowed in the current window
+    pub remaining: u32,
+    /// Time until the oldest request in the window expires and frees a slot
+    pub reset_after: Duration,
+}
+
+/// Check and record a request against a sliding-window limit of `max` requests per `window`.
+///
+/// Each allowed request is stored in a sorted set under `key`, scored by its timestamp.
+/// Rejected requests are not recorded, so they don't count against the limit. A `window`
+/// under 1ms is an error, since the script works in whole milliseconds.
+pub async fn check_rate_limit(key: &str, max: u32, window: Duration) -> AnyResult<RateLimitResult> {
+    let mut conn = get_shared_conn_manager().await?;
+    check(&mut conn, key, max, window).await
+}
+
+async fn check<C: ConnectionLike + Send>(conn: &mut C, key: &str, max: u32, window: Duration) -> AnyResult<RateLimitResult> {
+    if window < Duration::from_millis(1) {
+        return Err(anyhow::anyhow!("Rate limit window must be at least 1ms, got {:?}", window));
+    }
+    // Unique across every process and host sharing the limit, so two requests in the same
+    // millisecond never collapse into one sorted set member
+    let nonce = Uuid::new_v4().to_string();
+
+    let (allowed, remaining, reset_after_ms): (i64, i64, i64) = redis::Script::new(SLIDING_WINDOW_SCRIPT)
+        .key(key)
+        .arg(max)
+        .arg(u64::try_from(window.as_millis()).unwrap_or(u64::MAX))
+        .arg(nonce)
+        .invoke_async(conn)
+        .await?;
+
+    let result = RateLimitResult {
+        allowed: allowed == 1,
+        remaining: remaining.max(0) as u32,
+        reset_after: Duration::from_millis(reset_after_ms.max(0) as u64),
+    };
+    debug!("Rate limit check for key {}: {:?}", log_key(key, is_log_redact_enabled()), result);
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{live_conn, FlakyConnection};
+
+    #[tokio::test]
+    async fn a_window_under_a_millisecond_is_rejected_before_sending() {
+        let mut conn = FlakyConnection::new(0, redis::Value::Okay);
+        for window in [Duration::ZERO, Duration::from_micros(999)] {
+            assert!(check(&mut conn, "login", 5, window).await.is_err());
+        }
+        assert_eq!(conn.requests(), 0);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn only_the_request_over_the_limit_is_rejected() {
+        let (mut conn, prefix) = live_conn().await;
+        let key = format!("{}:login", prefix);
+        let max = 5;
+
+        // Fired together so several land in the same millisecond
+        let checks = (0..=max).map(|_| {
+            let mut conn = conn.clone();
+            let key = key.clone();
+            async move { check(&mut conn, &key, max, Duration::from_secs(60)).await.unwrap() }
+        });
+        let results = futures::future::join_all(checks).await;
+        assert_eq!(results.iter().filter(|result| !result.allowed).count(), 1);
+        assert!(results.iter().all(|result| result.remaining < max));
+        let _: () = redis::AsyncCommands::del(&mut conn, &key).await.unwrap();
+    }
+}
@@ -0,0 +1,191 @@
+use crate::cache::{log_key, CacheManager};
+use anyhow::Result as AnyResult;
+use futures::future::BoxFuture;
+use log::{debug, warn};
+use serde::Serialize;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+type Refresh = Arc<dyn Fn(CacheManager) -> BoxFuture<'static, AnyResult<()>> + Send + Sync>;
+
+struct RefreshEntry {
+    cache_key: String,
+    ttl_seconds: u64,
+    refresh_below: f64,
+    refresh: Refresh,
+}
+
+/// Recomputes registered entries shortly before they expire, so hot keys that are
+/// expensive to regenerate never go cold.
+///
+/// A background task checks each key's `remaining_ttl` every `check_interval` and reloads
+/// it once less than `refresh_below` of its TTL is left, or when it is missing. Readers keep
+/// using `get_or_set` with the same prefix and request, which covers the window before the
+/// first check. The check interval must be well below the refresh window, otherwise keys
+/// can expire between checks. The task stops when the `RefreshAhead` is dropped.
+pub struct RefreshAhead {
+    manager: CacheManager,
+    entries: Arc<Mutex<Vec<Arc<RefreshEntry>>>>,
+    task: JoinHandle<()>,
+}
+
+impl RefreshAhead {
+    /// Start the background task on the current Tokio runtime. Fails if `check_interval`
+    /// is zero.
+    pub fn start(manager: CacheManager, check_interval: Duration) -> AnyResult<Self> {
+        if check_interval.is_zero() {
+            return Err(anyhow::anyhow!("Refresh-ahead check interval must be greater than zero"));
+        }
+        let entries: Arc<Mutex<Vec<Arc<RefreshEntry>>>> = Arc::new(Mutex::new(Vec::new()));
+        let task_entries = entries.clone();
+        let mut task_manager = manager.clone();
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(check_interval);
+            loop {
+                ticker.tick().await;
+                let registered = task_entries.lock().unwrap_or_else(|e| e.into_inner()).clone();
+                for entry in registered {
+                    refresh_if_due(&mut task_manager, &entry).await;
+                }
+            }
+        });
+
+        Ok(Self { manager, entries, task })
+    }
+
+    /// Keep the entry for `request_data` under `cache_prefix` warm, reloading it with
+    /// `loader` and storing it for `ttl_seconds` whenever less than `refresh_below` (a
+    /// fraction such as `0.2`, clamped to `0.0..=1.0`) of that TTL remains. Fails if
+    /// `refresh_below` is NaN.
+    pub fn register<T, R, F, Fut>(
+        &self,
+        cache_prefix: &str,
+        request_data: R,
+        ttl_seconds: u64,
+        refresh_below: f64,
+        loader: F,
+    ) -> AnyResult<()>
+    where
+        T: Serialize + Clone + Send + Sync + 'static,
+        R: Serialize + Send + Sync + 'static,
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = AnyResult<T>> + Send + 'static,
+    {
+        if refresh_below.is_nan() {
+            return Err(anyhow::anyhow!("Refresh-ahead fraction for {} must be a number", cache_prefix));
+        }
+        let cache_key = self.manager.cache_key_for(cache_prefix, &request_data)?;
+        let cache_prefix = cache_prefix.to_string();
+        let request_data = Arc::new(request_data);
+        let loader = Arc::new(loader);
+        let refresh: Refresh = Arc::new(move |mut manager: CacheManager| {
+            let cache_prefix = cache_prefix.clone();
+            let request_data = request_data.clone();
+            let loader = loader.clone();
+            Box::pin(async move {
+                let response_data = loader().await?;
+                manager
+                    .cache_response_with_ttl(&cache_prefix, &*request_data, response_data, ttl_seconds)
+                    .await?;
+                Ok(())
+            })
+        });
+
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).push(Arc::new(RefreshEntry {
+            cache_key,
+            ttl_seconds,
+            refresh_below: refresh_below.clamp(0.0, 1.0),
+            refresh,
+        }));
+        Ok(())
+    }
+
+    /// Number of registered keys
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Drop for RefreshAhead {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn refresh_if_due(manager: &mut CacheManager, entry: &RefreshEntry) {
+    if !manager.is_available() {
+        return;
+    }
+    let key = log_key(&entry.cache_key, manager.redacts_logs());
+    let window = Duration::try_from_secs_f64(entry.ttl_seconds as f64 * entry.refresh_below).unwrap_or(Duration::MAX);
+    match manager.remaining_ttl(&entry.cache_key).await {
+        Ok(Some(remaining)) if remaining > window => return,
+        Ok(_) => debug!("Refreshing cache key {} ahead of expiry", key),
+        Err(e) => {
+            warn!("Failed to read TTL of {}, skipping refresh: {}", key, e);
+            return;
+        }
+    }
+    if let Err(e) = (entry.refresh)(manager.clone()).await {
+        warn!("Failed to refresh cache key {}: {}", key, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{live_prefix, FakeRedis};
+    use redis::Client;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn a_zero_interval_or_nan_fraction_is_rejected_up_front() {
+        let server = FakeRedis::start().await;
+        let manager = CacheManager::from_client(Client::open(server.url()).unwrap()).await.unwrap();
+        assert!(RefreshAhead::start(manager.clone(), Duration::ZERO).is_err());
+
+        let refresh_ahead = RefreshAhead::start(manager, Duration::from_secs(60)).unwrap();
+        assert!(refresh_ahead.register("report", "daily", 60, f64::NAN, || async { Ok(0) }).is_err());
+        assert!(refresh_ahead.is_empty());
+        refresh_ahead.register("report", "daily", 60, 0.2, || async { Ok(0) }).unwrap();
+        assert_eq!(refresh_ahead.len(), 1);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn registered_keys_are_refreshed_before_readers_miss() {
+        let mut manager = CacheManager::new().await.with_namespace(&live_prefix()).with_local_cache(0, Duration::ZERO);
+        assert!(manager.is_available(), "these tests need a Redis server at REDIS_URL");
+        let refresh_ahead = RefreshAhead::start(manager.clone(), Duration::from_millis(100)).unwrap();
+        let refreshes = Arc::new(AtomicUsize::new(0));
+        let counter = refreshes.clone();
+        refresh_ahead
+            .register("report", "daily", 2, 0.5, move || {
+                let counter = counter.clone();
+                async move { Ok(counter.fetch_add(1, Ordering::SeqCst)) }
+            })
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        // Read for well past the TTL; every read must find the entry
+        let misses = AtomicUsize::new(0);
+        for _ in 0..40 {
+            manager
+                .get_or_set("report", &"daily", || async {
+                    misses.fetch_add(1, Ordering::SeqCst);
+                    Ok(usize::MAX)
+                })
+                .await
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(misses.load(Ordering::SeqCst), 0);
+        assert!(refreshes.load(Ordering::SeqCst) >= 3, "refreshed {} times", refreshes.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,165 @@
+use crate::config::{get_max_retries, get_retry_base_delay, RedisConfig};
+use log::warn;
+use redis::aio::ConnectionLike;
+use redis::{Cmd, FromRedisValue, Pipeline, RedisError, RedisResult};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Upper bound on a single backoff sleep, however many retries are configured
+const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(2);
+
+/// Retry policy for transient command failures.
+///
+/// Only connection-level errors (IO, dropped connection, refused connection, timeout) are
+/// retried. Logical errors such as `WRONGTYPE` are returned on the first attempt. Delays grow
+/// exponentially from `base_delay` up to `max_delay`, with jitter so clients recovering from
+/// the same outage do not retry in lockstep.
+///
+/// Callers only wrap commands whose effect is the same when sent twice, such as GET, SET,
+/// DEL and EXPIRE. The reply to a resent command can still differ: a `RENAME` whose first
+/// reply was lost fails because the key already moved. Counters and compare-and-set scripts
+/// are sent once, since a retry after a lost reply would apply them twice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(50),
+            max_delay: DEFAULT_MAX_DELAY,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Read `REDIS_MAX_RETRIES` and `REDIS_RETRY_BASE_DELAY_MS`
+    pub fn from_env() -> Self {
+        Self {
+            max_retries: get_max_retries(),
+            base_delay: get_retry_base_delay(),
+            max_delay: DEFAULT_MAX_DELAY,
+        }
+    }
+
+    /// Use the retry settings from `config`
+    pub fn from_config(config: &RedisConfig) -> Self {
+        Self {
+            max_retries: config.max_retries,
+            base_delay: config.retry_base_delay,
+            max_delay: DEFAULT_MAX_DELAY,
+        }
+    }
+
+    /// A policy that never retries
+    pub fn none() -> Self {
+        Self { max_retries: 0, ..Self::default() }
+    }
+
+    /// Whether an error is worth retrying
+    pub fn is_retryable(error: &RedisError) -> bool {
+        error.is_io_error() || error.is_connection_dropped() || error.is_connection_refusal() || error.is_timeout()
+    }
+
+    /// Delay before retry number `attempt` (starting at 0), with jitter in `[delay / 2, delay]`
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let half = delay / 2;
+        let jitter_range = (delay - half).as_nanos() as u64;
+        if jitter_range == 0 {
+            return delay;
+        }
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().subsec_nanos() as u64;
+        half + Duration::from_nanos(seed % (jitter_range + 1))
+    }
+
+    /// Run a command, retrying on transient failures
+    pub async fn query<C, T>(&self, cmd: &Cmd, conn: &mut C) -> RedisResult<T>
+    where
+        C: ConnectionLike + Send,
+        T: FromRedisValue,
+    {
+        let mut attempt = 0;
+        loop {
+            match cmd.query_async(conn).await {
+                Err(e) if attempt < self.max_retries && Self::is_retryable(&e) => {
+                    let delay = self.backoff(attempt);
+                    warn!("Transient Redis error, retrying in {:?} ({}/{}): {}", delay, attempt + 1, self.max_retries, e);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Run a pipeline, retrying on transient failures.
+    ///
+    /// The whole pipeline is resent on retry, so it should only contain idempotent commands.
+    pub async fn query_pipeline<C, T>(&self, pipe: &Pipeline, conn: &mut C) -> RedisResult<T>
+    where
+        C: ConnectionLike + Send,
+        T: FromRedisValue,
+    {
+        let mut attempt = 0;
+        loop {
+            match pipe.query_async(conn).await {
+                Err(e) if attempt < self.max_retries && Self::is_retryable(&e) => {
+                    let delay = self.backoff(attempt);
+                    warn!("Transient Redis error, retrying pipeline in {:?} ({}/{}): {}", delay, attempt + 1, self.max_retries, e);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::FlakyConnection;
+    use redis::Value;
+
+    fn policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+        }
+    }
+
+    #[tokio::test]
+    async fn transient_failures_are_retried_until_the_command_succeeds() {
+        let mut conn = FlakyConnection::new(2, Value::Okay);
+        let result: RedisResult<bool> = policy(3).query(redis::cmd("SET").arg("k").arg("v"), &mut conn).await;
+        assert!(result.unwrap());
+        assert_eq!(conn.requests(), 3);
+
+        let mut conn = FlakyConnection::new(2, Value::Okay);
+        let result: RedisResult<Vec<bool>> = policy(3).query_pipeline(redis::pipe().set("k", "v"), &mut conn).await;
+        assert_eq!(result.unwrap(), [true]);
+        assert_eq!(conn.requests(), 3);
+    }
+
+    #[tokio::test]
+    async fn retries_stop_after_max_retries() {
+        let mut conn = FlakyConnection::down();
+        let result: RedisResult<()> = policy(2).query(redis::cmd("GET").arg("k"), &mut conn).await;
+        assert!(RetryPolicy::is_retryable(&result.unwrap_err()));
+        assert_eq!(conn.requests(), 3);
+
+        let mut conn = FlakyConnection::down();
+        let result: RedisResult<()> = RetryPolicy::none().query(redis::cmd("GET").arg("k"), &mut conn).await;
+        assert!(result.is_err());
+        assert_eq!(conn.requests(), 1);
+    }
+}
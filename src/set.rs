@@ -0,0 +1,156 @@
+use crate::trace::record_latency;
+use anyhow::Result as AnyResult;
+use redis::aio::ConnectionLike;
+use redis::AsyncCommands;
+use std::time::Instant;
+
+/// Add one member, or a `Vec` of members. Returns how many were not already in the set.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "SADD", redis.latency_ms)
+))]
+pub async fn sadd<M, C>(key: String, members: M, mut conn: C) -> AnyResult<u64>
+where
+    M: redis::ToRedisArgs + Send + Sync,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let added = conn.sadd(key, members).await?;
+    record_latency(start);
+
+    Ok(added)
+}
+
+/// Remove one member, or a `Vec` of members. Returns how many were in the set.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "SREM", redis.latency_ms)
+))]
+pub async fn srem<M, C>(key: String, members: M, mut conn: C) -> AnyResult<u64>
+where
+    M: redis::ToRedisArgs + Send + Sync,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let removed = conn.srem(key, members).await?;
+    record_latency(start);
+
+    Ok(removed)
+}
+
+/// Every member, in no particular order. Empty when the key does not exist.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "SMEMBERS", redis.latency_ms)
+))]
+pub async fn smembers<M, C>(key: String, mut conn: C) -> AnyResult<Vec<M>>
+where
+    M: redis::FromRedisValue,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let members = conn.smembers(key).await?;
+    record_latency(start);
+
+    Ok(members)
+}
+
+/// Number of members
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "SCARD", redis.latency_ms)
+))]
+pub async fn scard<C>(key: String, mut conn: C) -> AnyResult<u64>
+where
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let count = conn.scard(key).await?;
+    record_latency(start);
+
+    Ok(count)
+}
+
+// The store variants compute the result on the server and overwrite `dest` with it, so
+// members never travel to the client. Missing source keys count as empty sets, and an empty
+// result deletes `dest`. In cluster mode all keys must hash to the same slot.
+
+/// Store the members common to all `keys` in `dest`. Returns the size of the result.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %dest, redis.operation = "SINTERSTORE", redis.latency_ms)
+))]
+pub async fn sinterstore<C>(dest: String, keys: Vec<String>, mut conn: C) -> AnyResult<u64>
+where
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let count = conn.sinterstore(dest, keys).await?;
+    record_latency(start);
+
+    Ok(count)
+}
+
+/// Store the members of any of `keys` in `dest`. Returns the size of the result.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %dest, redis.operation = "SUNIONSTORE", redis.latency_ms)
+))]
+pub async fn sunionstore<C>(dest: String, keys: Vec<String>, mut conn: C) -> AnyResult<u64>
+where
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let count = conn.sunionstore(dest, keys).await?;
+    record_latency(start);
+
+    Ok(count)
+}
+
+/// Store the members of the first of `keys` that are in none of the others in `dest`.
+/// Returns the size of the result.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %dest, redis.operation = "SDIFFSTORE", redis.latency_ms)
+))]
+pub async fn sdiffstore<C>(dest: String, keys: Vec<String>, mut conn: C) -> AnyResult<u64>
+where
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let count = conn.sdiffstore(dest, keys).await?;
+    record_latency(start);
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::live_conn;
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn segments_are_combined_on_the_server() {
+        let (mut conn, prefix) = live_conn().await;
+        // A shared hash tag keeps the keys in one slot, as the store commands need in a cluster
+        let key = |name: &str| format!("{{{}}}:{}", prefix, name);
+
+        assert_eq!(sadd(key("buyers"), vec!["ayu", "bima", "citra"], conn.clone()).await.unwrap(), 3);
+        assert_eq!(sadd(key("subscribers"), vec!["bima", "citra", "dewi"], conn.clone()).await.unwrap(), 3);
+        assert_eq!(sadd(key("buyers"), "ayu", conn.clone()).await.unwrap(), 0);
+
+        let sources = vec![key("buyers"), key("subscribers")];
+        assert_eq!(sinterstore(key("both"), sources.clone(), conn.clone()).await.unwrap(), 2);
+        let mut both: Vec<String> = smembers(key("both"), conn.clone()).await.unwrap();
+        both.sort();
+        assert_eq!(both, ["bima", "citra"]);
+        assert_eq!(sunionstore(key("either"), sources.clone(), conn.clone()).await.unwrap(), 4);
+        assert_eq!(sdiffstore(key("buyers_only"), sources, conn.clone()).await.unwrap(), 1);
+
+        assert_eq!(srem(key("either"), vec!["ayu", "eka"], conn.clone()).await.unwrap(), 1);
+        assert_eq!(scard(key("either"), conn.clone()).await.unwrap(), 3);
+        let keys = ["buyers", "subscribers", "both", "either", "buyers_only"].map(key);
+        let _: () = conn.del(&keys).await.unwrap();
+    }
+}
@@ -0,0 +1,267 @@
+use crate::cache::{CacheError, CacheManager, CachedResponse};
+use crate::config::RedisConfig;
+use anyhow::Result as AnyResult;
+use log::info;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Points each shard gets on the hash ring. More points spread keys more evenly.
+const VIRTUAL_NODES: usize = 160;
+
+fn ring_hash(data: &str) -> u64 {
+    let digest = Sha256::digest(data.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(bytes)
+}
+
+/// Consistent-hash ring mapping keys to shard indexes
+struct HashRing {
+    points: Vec<(u64, usize)>,
+}
+
+impl HashRing {
+    fn new<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut points = Vec::new();
+        for (index, name) in names.into_iter().enumerate() {
+            for point in 0..VIRTUAL_NODES {
+                points.push((ring_hash(&format!("{}#{}", name, point)), index));
+            }
+        }
+        points.sort_unstable();
+        Self { points }
+    }
+
+    fn shard_for(&self, key: &str) -> usize {
+        let hash = ring_hash(key);
+        let position = self.points.partition_point(|(point, _)| *point < hash);
+        self.points.get(position).or_else(|| self.points.first()).map_or(0, |(_, index)| *index)
+    }
+}
+
+/// Spreads cache entries across independent Redis instances by consistent hashing.
+///
+/// Each shard is placed on a hash ring at 160 points derived from its name, and
+/// a key belongs to the first point at or after its own hash. Adding or removing a shard
+/// only moves the keys next to that shard's points, about `1 / shards` of them. Positions
+/// depend on shard names, not their order, so keep names stable across deployments.
+///
+/// Single-key operations go to the owning shard; `clear_pattern` and `count_keys` run on
+/// every shard and add up the results. This is not Redis Cluster: there is no failover or
+/// resharding, and a shard that is down simply misses.
+pub struct ShardedCacheManager {
+    shards: Vec<CacheManager>,
+    ring: HashRing,
+}
+
+impl ShardedCacheManager {
+    /// One shard per URL in `REDIS_SHARD_URLS`, or a single shard at `REDIS_URL` when unset
+    pub async fn new() -> Self {
+        Self::from_config(RedisConfig::from_env()).await
+    }
+
+    /// One shard per URL in `config.shard_urls`, each using the rest of `config`. Falls back
+    /// to a single shard at `config.redis_url` when no shard URLs are set.
+    pub async fn from_config(config: RedisConfig) -> Self {
+        if config.shard_urls.is_empty() {
+            let name = config.redis_url.clone().unwrap_or_default();
+            return Self::from_shards(vec![(name, CacheManager::from_config(config).await)]);
+        }
+
+        let mut shards = Vec::with_capacity(config.shard_urls.len());
+        for url in &config.shard_urls {
+            let shard_config = RedisConfig {
+                redis_url: Some(url.clone()),
+                cluster_enabled: false,
+                sentinel_master: None,
+                replica_urls: Vec::new(),
+                shard_urls: Vec::new(),
+                fallback_url: None,
+                ..config.clone()
+            };
+            shards.push((url.clone(), CacheManager::from_config(shard_config).await));
+        }
+        info!("Sharding cache across {} Redis instances", shards.len());
+        Self::from_shards(shards)
+    }
+
+    /// Build the ring from already configured managers, each with a stable name such as its
+    /// URL. Panics if `shards` is empty.
+    pub fn from_shards(shards: Vec<(String, CacheManager)>) -> Self {
+        assert!(!shards.is_empty(), "ShardedCacheManager needs at least one shard");
+        let ring = HashRing::new(shards.iter().map(|(name, _)| name.as_str()));
+        Self {
+            shards: shards.into_iter().map(|(_, manager)| manager).collect(),
+            ring,
+        }
+    }
+
+    /// Number of shards
+    pub fn len(&self) -> usize {
+        self.shards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards.is_empty()
+    }
+
+    /// Index of the shard that owns `key`
+    pub fn shard_for(&self, key: &str) -> usize {
+        self.ring.shard_for(key)
+    }
+
+    /// The manager for the shard that owns `key`, for operations not forwarded here
+    pub fn shard(&mut self, key: &str) -> &mut CacheManager {
+        let index = self.shard_for(key);
+        &mut self.shards[index]
+    }
+
+    pub async fn get<T>(&mut self, key: &str) -> Result<Option<CachedResponse<T>>, CacheError>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        self.shard(key).get(key).await
+    }
+
+    pub async fn set<T>(&mut self, key: &str, data: &CachedResponse<T>) -> Result<bool, CacheError>
+    where
+        T: Serialize,
+    {
+        self.shard(key).set(key, data).await
+    }
+
+    pub async fn set_with_ttl<T>(&mut self, key: &str, data: &CachedResponse<T>, ttl_seconds: u64) -> Result<bool, CacheError>
+    where
+        T: Serialize,
+    {
+        self.shard(key).set_with_ttl(key, data, ttl_seconds).await
+    }
+
+    pub async fn delete(&mut self, key: &str) -> AnyResult<bool> {
+        self.shard(key).delete(key).await
+    }
+
+    /// `clear_pattern` on every shard, returning the total number of keys removed
+    pub async fn clear_pattern(&mut self, pattern: &str) -> AnyResult<u32> {
+        let mut cleared = 0;
+        for shard in &mut self.shards {
+            cleared += shard.clear_pattern(pattern).await?;
+        }
+        Ok(cleared)
+    }
+
+    /// `count_keys` on every shard, returning the total
+    pub async fn count_keys(&mut self, pattern: &str) -> AnyResult<u64> {
+        let mut count = 0;
+        for shard in &mut self.shards {
+            count += shard.count_keys(pattern).await?;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::RetryPolicy;
+    use crate::testing::FakeRedis;
+    use redis::aio::ConnectionManager;
+    use redis::Client;
+    use std::time::Duration;
+
+    const SHARDS: [&str; 3] = ["redis://cache-a:6379", "redis://cache-b:6379", "redis://cache-c:6379"];
+
+    fn keys() -> impl Iterator<Item = String> {
+        (0..30_000).map(|i| format!("user_profile:{}", i))
+    }
+
+    #[test]
+    fn keys_are_balanced_across_shards() {
+        let ring = HashRing::new(SHARDS);
+        let mut counts = [0usize; 3];
+        for key in keys() {
+            counts[ring.shard_for(&key)] += 1;
+        }
+        // Each shard should own roughly a third of the keys
+        for count in counts {
+            assert!((7_000..13_000).contains(&count), "unbalanced shards: {:?}", counts);
+        }
+    }
+
+    #[test]
+    fn assignment_is_stable_and_ignores_shard_order() {
+        let ring = HashRing::new(SHARDS);
+        let again = HashRing::new(SHARDS);
+        let reversed = HashRing::new(SHARDS.iter().rev().copied());
+        for key in keys().take(1_000) {
+            let shard = ring.shard_for(&key);
+            assert_eq!(again.shard_for(&key), shard);
+            assert_eq!(SHARDS[2 - reversed.shard_for(&key)], SHARDS[shard]);
+        }
+    }
+
+    #[test]
+    fn adding_a_shard_moves_only_its_share_of_keys() {
+        let ring = HashRing::new(SHARDS);
+        let grown = HashRing::new(SHARDS.into_iter().chain(["redis://cache-d:6379"]));
+        let mut moved = 0;
+        for key in keys() {
+            let after = grown.shard_for(&key);
+            if after != ring.shard_for(&key) {
+                assert_eq!(after, 3, "key moved between existing shards");
+                moved += 1;
+            }
+        }
+        assert!((4_500..10_500).contains(&moved), "{} keys moved", moved);
+    }
+
+    #[tokio::test]
+    async fn single_key_operations_reach_only_the_owning_shard_and_scans_fan_out() {
+        let mut servers = Vec::new();
+        let mut shards = Vec::new();
+        for name in SHARDS {
+            let server = FakeRedis::start().await;
+            let conn = ConnectionManager::new(Client::open(server.url()).unwrap()).await.unwrap();
+            let manager = CacheManager::with_connection(conn)
+                .with_namespace("")
+                .with_local_cache(0, Duration::ZERO)
+                .with_retry_policy(RetryPolicy::none());
+            shards.push((name.to_string(), manager));
+            servers.push(server);
+        }
+        let mut sharded = ShardedCacheManager::from_shards(shards);
+
+        let keys: Vec<String> = (0..30).map(|i| format!("user:{}", i)).collect();
+        let mut owned = [0usize; 3];
+        for key in &keys {
+            let response = CachedResponse::new(key.clone(), key.clone());
+            assert!(sharded.set(key, &response).await.unwrap());
+            owned[sharded.shard_for(key)] += 1;
+        }
+        for (index, server) in servers.iter().enumerate() {
+            assert_eq!(server.calls("SET"), owned[index], "shard {} got another shard's writes", index);
+            for key in &keys {
+                assert_eq!(server.contains(key), sharded.shard_for(key) == index, "{} on shard {}", key, index);
+            }
+        }
+        assert!(owned.iter().all(|&count| count > 0), "every shard should own a key: {:?}", owned);
+
+        let key = &keys[0];
+        let owner = sharded.shard_for(key);
+        let cached = sharded.get::<String>(key).await.unwrap().expect("written above");
+        assert_eq!(cached.data, *key);
+        for (index, server) in servers.iter().enumerate() {
+            assert_eq!(server.calls("GET"), usize::from(index == owner));
+        }
+
+        assert_eq!(sharded.count_keys("user:*").await.unwrap(), 30);
+        assert!(sharded.delete(key).await.unwrap());
+        assert!(!servers[owner].contains(key));
+        assert_eq!(sharded.count_keys("user:*").await.unwrap(), 29);
+        assert_eq!(sharded.clear_pattern("user:1*").await.unwrap(), 11);
+        assert_eq!(sharded.count_keys("user:*").await.unwrap(), 18);
+        for server in &servers {
+            assert_eq!(server.calls("SCAN"), 4, "every shard is scanned for each fan-out call");
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::trace::record_latency;
+use anyhow::Result as AnyResult;
+use redis::aio::ConnectionLike;
+use redis::AsyncCommands;
+use std::time::Instant;
+
+/// Add a member with a score, or update its score. Returns 1 if the member is new.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "ZADD", redis.latency_ms)
+))]
+pub async fn zadd<M, C>(key: String, member: M, score: f64, mut conn: C) -> AnyResult<u64>
+where
+    M: redis::ToRedisArgs + Send + Sync,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let added = conn.zadd(key, member, score).await?;
+    record_latency(start);
+
+    Ok(added)
+}
+
+/// Add `delta` to a member's score, creating it at `delta` if absent. Returns the new score.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "ZINCRBY", redis.latency_ms)
+))]
+pub async fn zincrby<M, C>(key: String, member: M, delta: f64, mut conn: C) -> AnyResult<f64>
+where
+    M: redis::ToRedisArgs + Send + Sync,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let score = conn.zincr(key, member, delta).await?;
+    record_latency(start);
+
+    Ok(score)
+}
+
+/// Members ranked `start..=stop` by ascending score, with their scores. Negative indexes
+/// count from the highest score, so `0, -1` returns the whole set.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "ZRANGE", redis.latency_ms)
+))]
+pub async fn zrange_withscores<M, C>(key: String, start: isize, stop: isize, mut conn: C) -> AnyResult<Vec<(M, f64)>>
+where
+    M: redis::FromRedisValue,
+    C: ConnectionLike + Send,
+{
+    let started = Instant::now();
+    let members = conn.zrange_withscores(key, start, stop).await?;
+    record_latency(started);
+
+    Ok(members)
+}
+
+/// Members ranked `start..=stop` by descending score, with their scores. `0, n - 1`
+/// returns the top `n`, as for a leaderboard.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "ZREVRANGE", redis.latency_ms)
+))]
+pub async fn zrevrange_withscores<M, C>(key: String, start: isize, stop: isize, mut conn: C) -> AnyResult<Vec<(M, f64)>>
+where
+    M: redis::FromRedisValue,
+    C: ConnectionLike + Send,
+{
+    let started = Instant::now();
+    let members = conn.zrevrange_withscores(key, start, stop).await?;
+    record_latency(started);
+
+    Ok(members)
+}
+
+/// A member's score, or `None` if it is not in the set.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    skip_all,
+    fields(redis.key = %key, redis.operation = "ZSCORE", redis.latency_ms)
+))]
+pub async fn zscore<M, C>(key: String, member: M, mut conn: C) -> AnyResult<Option<f64>>
+where
+    M: redis::ToRedisArgs + Send + Sync,
+    C: ConnectionLike + Send,
+{
+    let start = Instant::now();
+    let score = conn.zscore(key, member).await?;
+    record_latency(start);
+
+    Ok(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::live_conn;
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn a_leaderboard_reads_back_the_top_players_in_order() {
+        let (mut conn, prefix) = live_conn().await;
+        let key = format!("{}:leaderboard", prefix);
+        for (player, score) in [("ayu", 30.0), ("bima", 50.0), ("citra", 10.0), ("dewi", 40.0)] {
+            assert_eq!(zadd(key.clone(), player, score, conn.clone()).await.unwrap(), 1);
+        }
+        assert_eq!(zadd(key.clone(), "ayu", 35.0, conn.clone()).await.unwrap(), 0);
+        assert_eq!(zincrby(key.clone(), "citra", 45.0, conn.clone()).await.unwrap(), 55.0);
+
+        let top = zrevrange_withscores::<String, _>(key.clone(), 0, 2, conn.clone()).await.unwrap();
+        assert_eq!(top, [("citra".to_string(), 55.0), ("bima".to_string(), 50.0), ("dewi".to_string(), 40.0)]);
+        let bottom = zrange_withscores::<String, _>(key.clone(), 0, 0, conn.clone()).await.unwrap();
+        assert_eq!(bottom, [("ayu".to_string(), 35.0)]);
+        assert_eq!(zscore(key.clone(), "dewi", conn.clone()).await.unwrap(), Some(40.0));
+        assert_eq!(zscore(key.clone(), "eka", conn.clone()).await.unwrap(), None);
+        let _: () = conn.del(&key).await.unwrap();
+    }
+}
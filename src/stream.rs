@@ -0,0 +1,183 @@
+use crate::config::get_shared_conn_manager;
+use crate::operations::SubError;
+use anyhow::Result as AnyResult;
+use log::{debug, info};
+use redis::streams::{StreamId, StreamReadOptions, StreamReadReply};
+use redis::AsyncCommands;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Field under which the JSON payload of each entry is stored
+const PAYLOAD_FIELD: &str = "data";
+
+/// A message read from a stream through a consumer group.
+#[derive(Debug)]
+pub struct StreamMessage<T> {
+    /// Entry ID, passed to `xack` once the message has been processed
+    pub id: String,
+    /// Decoded payload, or the error that prevented decoding it
+    pub data: Result<T, SubError>,
+}
+
+/// Serialize `value` as JSON and append it to `stream`, returning the new entry ID.
+pub async fn xadd<T>(stream: &str, value: &T) -> AnyResult<String>
+where
+    T: Serialize,
+{
+    let payload = serde_json::to_string(value)?;
+    let mut conn = get_shared_conn_manager().await?;
+    let id: String = conn.xadd(stream, "*", &[(PAYLOAD_FIELD, payload)]).await?;
+    debug!("Appended entry {} to stream: {}", id, stream);
+    Ok(id)
+}
+
+/// Create a consumer group reading `stream` from the beginning, creating the stream if
+/// it does not exist yet. Succeeds if the group already exists.
+pub async fn create_group(stream: &str, group: &str) -> AnyResult<()> {
+    let mut conn = get_shared_conn_manager().await?;
+    let created: Result<(), redis::RedisError> = conn.xgroup_create_mkstream(stream, group, "0").await;
+    match created {
+        Ok(()) => {
+            info!("Created consumer group {} on stream: {}", group, stream);
+            Ok(())
+        }
+        Err(e) if e.code() == Some("BUSYGROUP") => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Read up to `count` messages for `consumer` in `group`, creating the group if needed.
+///
+/// Messages previously delivered to this consumer but not yet acknowledged are returned
+/// first, so a consumer that restarts under the same name replays its unfinished work
+/// before receiving new entries. Call `xack` after processing each message. The read does
+/// not block; an empty vector means there is nothing to process right now.
+pub async fn xread_group<T>(stream: &str, group: &str, consumer: &str, count: usize) -> AnyResult<Vec<StreamMessage<T>>>
+where
+    T: DeserializeOwned,
+{
+    let pending = read_group::<T>(stream, group, consumer, count, "0").await?;
+    if !pending.is_empty() {
+        debug!("Redelivering {} pending messages to consumer {} on stream: {}", pending.len(), consumer, stream);
+        return Ok(pending);
+    }
+
+    read_group::<T>(stream, group, consumer, count, ">").await
+}
+
+/// Acknowledge processed messages so they are not redelivered. Returns the number acknowledged.
+pub async fn xack(stream: &str, group: &str, ids: &[&str]) -> AnyResult<u64> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    let mut conn = get_shared_conn_manager().await?;
+    let acked: u64 = conn.xack(stream, group, ids).await?;
+    Ok(acked)
+}
+
+async fn read_group<T>(stream: &str, group: &str, consumer: &str, count: usize, id: &str) -> AnyResult<Vec<StreamMessage<T>>>
+where
+    T: DeserializeOwned,
+{
+    let mut conn = get_shared_conn_manager().await?;
+    let opts = StreamReadOptions::default().group(group, consumer).count(count);
+
+    let reply: Option<StreamReadReply> = match conn.xread_options(&[stream], &[id], &opts).await {
+        Ok(reply) => reply,
+        Err(e) if e.code() == Some("NOGROUP") => {
+            create_group(stream, group).await?;
+            conn.xread_options(&[stream], &[id], &opts).await?
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let messages = reply
+        .map(|reply| reply.keys)
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(|key| key.ids)
+        .map(decode_entry::<T>)
+        .collect();
+
+    Ok(messages)
+}
+
+fn decode_entry<T>(entry: StreamId) -> StreamMessage<T>
+where
+    T: DeserializeOwned,
+{
+    let data = entry
+        .get::<String>(PAYLOAD_FIELD)
+        .ok_or_else(|| {
+            SubError::Payload(redis::RedisError::from((
+                redis::ErrorKind::TypeError,
+                "Stream entry has no payload field",
+            )))
+        })
+        .and_then(|payload| {
+            serde_json::from_str::<T>(&payload).map_err(|source| SubError::Decode { payload, source })
+        });
+
+    StreamMessage { id: entry.id, data }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{block_on_shared, live_prefix};
+    use redis::Value;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Order {
+        id: u32,
+    }
+
+    fn entry(fields: &[(&str, &str)]) -> StreamId {
+        StreamId {
+            id: "1-0".to_string(),
+            map: fields
+                .iter()
+                .map(|(field, value)| (field.to_string(), Value::Data(value.as_bytes().to_vec())))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn entries_decode_their_payload_field() {
+        let message = decode_entry::<Order>(entry(&[(PAYLOAD_FIELD, r#"{"id":7}"#)]));
+        assert_eq!(message.id, "1-0");
+        assert_eq!(message.data.unwrap(), Order { id: 7 });
+
+        let missing = decode_entry::<Order>(entry(&[("other", "{}")]));
+        assert!(matches!(missing.data, Err(SubError::Payload(_))));
+
+        let malformed = decode_entry::<Order>(entry(&[(PAYLOAD_FIELD, "not json")]));
+        assert!(matches!(malformed.data, Err(SubError::Decode { payload, .. }) if payload == "not json"));
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    fn unacknowledged_messages_are_redelivered() {
+        block_on_shared(async {
+            let stream = format!("{}:orders", live_prefix());
+            let first = xadd(&stream, &Order { id: 1 }).await.unwrap();
+            xadd(&stream, &Order { id: 2 }).await.unwrap();
+
+            let delivered = xread_group::<Order>(&stream, "billing", "worker-1", 10).await.unwrap();
+            assert_eq!(delivered.len(), 2);
+            assert_eq!(xack(&stream, "billing", &[&first]).await.unwrap(), 1);
+
+            // The same consumer coming back gets the unacknowledged message again
+            let redelivered = xread_group::<Order>(&stream, "billing", "worker-1", 10).await.unwrap();
+            assert_eq!(redelivered.len(), 1);
+            assert_eq!(redelivered[0].data.as_ref().unwrap(), &Order { id: 2 });
+
+            xack(&stream, "billing", &[&redelivered[0].id]).await.unwrap();
+            assert!(xread_group::<Order>(&stream, "billing", "worker-1", 10).await.unwrap().is_empty());
+            let _: () = get_shared_conn_manager().await.unwrap().del(&stream).await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,476 @@
+//! Doubles for tests that must not need a Redis server, and helpers for the live tests that
+//! do. Live tests are ignored by default; run them with `cargo test -- --ignored` against a
+//! disposable server at `REDIS_URL`.
+
+use crate::config::{RedisConfig, RedisConn};
+use redis::aio::ConnectionLike;
+use redis::{Cmd, Pipeline, RedisError, RedisFuture, Value};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Once, OnceLock};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream};
+
+/// A key prefix of its own for a live test, so runs never see each other's keys
+pub(crate) fn live_prefix() -> String {
+    format!("shared-redis-test:{}", uuid::Uuid::new_v4())
+}
+
+/// Connection to the server at `REDIS_URL`, and a key prefix of its own
+pub(crate) async fn live_conn() -> (RedisConn, String) {
+    let conn = RedisConfig::from_env().connect().await.expect("these tests need a Redis server at REDIS_URL");
+    (conn, live_prefix())
+}
+
+/// Run a live test of the free functions built on the shared connection manager. Its
+/// connection is driven by the runtime that opened it, so these tests share one runtime
+/// instead of each getting a short-lived one from `#[tokio::test]`.
+pub(crate) fn block_on_shared<F: Future>(test: F) -> F::Output {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+    RUNTIME
+        .get_or_init(|| tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap())
+        .block_on(test)
+}
+
+thread_local! {
+    static CAPTURED_LOGS: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// Records log lines for the threads currently inside `capture_logs`
+struct CapturingLogger;
+
+impl log::Log for CapturingLogger {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        CAPTURED_LOGS.with(|captured| {
+            if let Some(lines) = captured.borrow_mut().as_mut() {
+                lines.push(record.args().to_string());
+            }
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+/// Run `test`, returning its output and every line it logged at any level. Lines are
+/// captured per thread, so this suits `#[tokio::test]` on its default current-thread
+/// runtime and ignores what other tests log meanwhile.
+pub(crate) async fn capture_logs<F: Future>(test: F) -> (F::Output, Vec<String>) {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        log::set_logger(&CapturingLogger).expect("no other logger is installed in tests");
+        log::set_max_level(log::LevelFilter::Trace);
+    });
+    CAPTURED_LOGS.with(|captured| *captured.borrow_mut() = Some(Vec::new()));
+    let output = test.await;
+    let lines = CAPTURED_LOGS.with(|captured| captured.borrow_mut().take()).unwrap_or_default();
+    (output, lines)
+}
+
+/// Accepts connections and holds them open, replying to nothing but the client's connection
+/// setup. The URL selects database 1, so connecting already waits forever; connect to
+/// database 0 instead to get a connection whose commands never complete.
+pub(crate) async fn silent_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut socket = BufReader::new(socket);
+                while let Some(args) = read_command(&mut socket).await {
+                    // The client ignores the reply to its `CLIENT SETINFO` calls
+                    if args[0].eq_ignore_ascii_case(b"CLIENT") && socket.get_mut().write_all(b"-ERR unknown command\r\n").await.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    format!("redis://{}/1", addr)
+}
+
+/// Accepts connections but closes each one when the first command after the client's
+/// connection setup arrives, so every command fails with a dropped connection
+pub(crate) async fn dropping_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut socket = BufReader::new(socket);
+                // The client ignores the reply to its `CLIENT SETINFO` calls
+                while let Some(args) = read_command(&mut socket).await {
+                    if !args[0].eq_ignore_ascii_case(b"CLIENT") {
+                        return;
+                    }
+                    if socket.get_mut().write_all(b"-ERR unknown command\r\n").await.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    format!("redis://{}/", addr)
+}
+
+/// In-memory server speaking just enough RESP for `CacheManager` string commands: `PING`,
+/// `GET`, `MGET`, `SET` (only `NX` is honoured), `SETEX`, `DEL`, `UNLINK`, `EXISTS`, the
+/// `EXPIRE` family (a TTL of zero deletes the key, otherwise nothing ever expires), `INCR`,
+/// `SADD`, `SMEMBERS`, `COPY`, `SCAN` (all matches in one reply) and `MULTI`/`EXEC`, plus `SUBSCRIBE`, `CLIENT ID` and
+/// `CLIENT TRACKING` (accepted, with messages sent only through `publish`).
+/// Other commands get an error reply, as does any command switched off with `disable`. Counts
+/// every command it receives by name, and `CLIENT` by subcommand such as `CLIENT TRACKING`.
+/// While `set_down` is on it drops connections like `dropping_server`, keeping its data.
+#[derive(Clone, Default)]
+pub(crate) struct FakeRedis {
+    url: String,
+    data: Arc<Mutex<HashMap<Vec<u8>, Entry>>>,
+    calls: Arc<Mutex<HashMap<String, usize>>>,
+    disabled: Arc<Mutex<Vec<String>>>,
+    down: Arc<AtomicBool>,
+    subscribers: Arc<Mutex<HashMap<Vec<u8>, Vec<Writer>>>>,
+    client_ids: Arc<AtomicUsize>,
+}
+
+type Writer = Arc<tokio::sync::Mutex<OwnedWriteHalf>>;
+
+impl FakeRedis {
+    pub(crate) async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = Self {
+            url: format!("redis://{}/", listener.local_addr().unwrap()),
+            ..Self::default()
+        };
+        let serving = server.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(serving.clone().serve(socket));
+            }
+        });
+        server
+    }
+
+    pub(crate) fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// How many times `command` (upper case) was received
+    pub(crate) fn calls(&self, command: &str) -> usize {
+        self.calls.lock().unwrap().get(command).copied().unwrap_or(0)
+    }
+
+    /// Store a raw value, bypassing any client
+    pub(crate) fn insert(&self, key: &str, value: &[u8]) {
+        self.data.lock().unwrap().insert(key.as_bytes().to_vec(), Entry::String(value.to_vec()));
+    }
+
+    /// Whether `key` is stored, checked without going through a client
+    pub(crate) fn contains(&self, key: &str) -> bool {
+        self.data.lock().unwrap().contains_key(key.as_bytes())
+    }
+
+    /// Answer `command` (upper case) like a server that predates it
+    pub(crate) fn disable(&self, command: &str) {
+        self.disabled.lock().unwrap().push(command.to_string());
+    }
+
+    /// Send a message whose payload is the array `items`, the shape of a tracking
+    /// invalidation, to every connection subscribed to `channel`. Returns how many got it.
+    pub(crate) async fn publish(&self, channel: &str, items: &[&str]) -> usize {
+        let mut message = b"*3\r\n$7\r\nmessage\r\n".to_vec();
+        message.extend(bulk(Some(&channel.as_bytes().to_vec())));
+        message.extend(format!("*{}\r\n", items.len()).into_bytes());
+        items.iter().for_each(|item| message.extend(bulk(Some(&item.as_bytes().to_vec()))));
+
+        let subscribers = self.subscribers.lock().unwrap().get(channel.as_bytes()).cloned().unwrap_or_default();
+        let mut delivered = 0;
+        for subscriber in subscribers {
+            if subscriber.lock().await.write_all(&message).await.is_ok() {
+                delivered += 1;
+            }
+        }
+        delivered
+    }
+
+    /// Close every connection on its next command other than the client's connection setup,
+    /// until switched back off
+    pub(crate) fn set_down(&self, down: bool) {
+        self.down.store(down, Ordering::SeqCst);
+    }
+
+    async fn serve(self, socket: TcpStream) {
+        let (reader, writer) = socket.into_split();
+        let mut socket = BufReader::new(reader);
+        let writer: Writer = Arc::new(tokio::sync::Mutex::new(writer));
+        // Replies of the commands queued since `MULTI`. They run straight away, which is
+        // atomic enough for one test at a time.
+        let mut transaction: Option<Vec<Vec<u8>>> = None;
+        while let Some(args) = read_command(&mut socket).await {
+            if self.down.load(Ordering::SeqCst) && !args[0].eq_ignore_ascii_case(b"CLIENT") {
+                return;
+            }
+            let reply = match (args[0].to_ascii_uppercase().as_slice(), transaction.as_mut()) {
+                (b"MULTI", None) => {
+                    transaction = Some(Vec::new());
+                    b"+OK\r\n".to_vec()
+                }
+                (b"EXEC", Some(replies)) => {
+                    let mut reply = format!("*{}\r\n", replies.len()).into_bytes();
+                    replies.iter().for_each(|queued| reply.extend(queued));
+                    transaction = None;
+                    reply
+                }
+                (_, Some(replies)) => {
+                    replies.push(self.execute(&args));
+                    b"+QUEUED\r\n".to_vec()
+                }
+                (b"SUBSCRIBE", None) => self.subscribe(&args[1..], &writer),
+                (_, None) => self.execute(&args),
+            };
+            if writer.lock().await.write_all(&reply).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Register `writer` for messages on `channels`, replying with one confirmation each
+    fn subscribe(&self, channels: &[Vec<u8>], writer: &Writer) -> Vec<u8> {
+        self.record_call("SUBSCRIBE".to_string());
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let mut reply = Vec::new();
+        for (subscribed, channel) in channels.iter().enumerate() {
+            subscribers.entry(channel.clone()).or_default().push(writer.clone());
+            reply.extend(b"*3\r\n$9\r\nsubscribe\r\n");
+            reply.extend(bulk(Some(channel)));
+            reply.extend(format!(":{}\r\n", subscribed + 1).into_bytes());
+        }
+        reply
+    }
+
+    fn record_call(&self, name: String) {
+        *self.calls.lock().unwrap().entry(name).or_default() += 1;
+    }
+
+    fn execute(&self, args: &[Vec<u8>]) -> Vec<u8> {
+        let name = String::from_utf8_lossy(&args[0]).to_uppercase();
+        match (name.as_str(), args.get(1)) {
+            ("CLIENT", Some(subcommand)) => self.record_call(format!("CLIENT {}", String::from_utf8_lossy(subcommand).to_uppercase())),
+            _ => self.record_call(name.clone()),
+        }
+        if self.disabled.lock().unwrap().contains(&name) {
+            return format!("-ERR unknown command '{}'\r\n", name).into_bytes();
+        }
+        let mut data = self.data.lock().unwrap();
+        match (name.as_str(), &args[1..]) {
+            ("PING", _) => b"+PONG\r\n".to_vec(),
+            ("CLIENT", [subcommand]) if subcommand.eq_ignore_ascii_case(b"ID") => {
+                format!(":{}\r\n", self.client_ids.fetch_add(1, Ordering::SeqCst) + 1).into_bytes()
+            }
+            ("CLIENT", [subcommand, ..]) if subcommand.eq_ignore_ascii_case(b"TRACKING") => b"+OK\r\n".to_vec(),
+            ("GET", [key]) => match data.get(key) {
+                Some(Entry::Set(_)) => WRONGTYPE.to_vec(),
+                entry => bulk(entry.and_then(Entry::string)),
+            },
+            ("MGET", keys) => {
+                let mut reply = format!("*{}\r\n", keys.len()).into_bytes();
+                keys.iter().for_each(|key| reply.extend(bulk(data.get(key).and_then(Entry::string))));
+                reply
+            }
+            ("SET", [key, value, options @ ..]) => {
+                if options.iter().any(|option| option.eq_ignore_ascii_case(b"NX")) && data.contains_key(key) {
+                    return b"$-1\r\n".to_vec();
+                }
+                data.insert(key.clone(), Entry::String(value.clone()));
+                b"+OK\r\n".to_vec()
+            }
+            ("SETEX", [key, _, value]) => {
+                data.insert(key.clone(), Entry::String(value.clone()));
+                b"+OK\r\n".to_vec()
+            }
+            ("INCR", [key]) => {
+                let current = match data.get(key) {
+                    None => 0,
+                    Some(Entry::String(value)) => match std::str::from_utf8(value).ok().and_then(|value| value.parse::<i64>().ok()) {
+                        Some(current) => current,
+                        None => return b"-ERR value is not an integer or out of range\r\n".to_vec(),
+                    },
+                    Some(Entry::Set(_)) => return WRONGTYPE.to_vec(),
+                };
+                data.insert(key.clone(), Entry::String((current + 1).to_string().into_bytes()));
+                format!(":{}\r\n", current + 1).into_bytes()
+            }
+            ("SADD", [key, members @ ..]) => match data.entry(key.clone()).or_insert_with(|| Entry::Set(BTreeSet::new())) {
+                Entry::Set(set) => {
+                    let added = members.iter().filter(|member| set.insert(member.to_vec())).count();
+                    format!(":{}\r\n", added).into_bytes()
+                }
+                Entry::String(_) => WRONGTYPE.to_vec(),
+            },
+            ("SMEMBERS", [key]) => match data.get(key) {
+                Some(Entry::Set(set)) => {
+                    let mut reply = format!("*{}\r\n", set.len()).into_bytes();
+                    set.iter().for_each(|member| reply.extend(bulk(Some(member))));
+                    reply
+                }
+                Some(Entry::String(_)) => WRONGTYPE.to_vec(),
+                None => b"*0\r\n".to_vec(),
+            },
+            ("COPY", [from, to, options @ ..]) => {
+                let replace = options.iter().any(|option| option.eq_ignore_ascii_case(b"REPLACE"));
+                match data.get(from).cloned() {
+                    Some(entry) if replace || !data.contains_key(to) => {
+                        data.insert(to.clone(), entry);
+                        b":1\r\n".to_vec()
+                    }
+                    _ => b":0\r\n".to_vec(),
+                }
+            }
+            ("DEL" | "UNLINK", keys) => {
+                let removed = keys.iter().filter(|key| data.remove(*key).is_some()).count();
+                format!(":{}\r\n", removed).into_bytes()
+            }
+            ("SCAN", [_, options @ ..]) => {
+                let pattern = options
+                    .chunks(2)
+                    .find(|option| option[0].eq_ignore_ascii_case(b"MATCH"))
+                    .and_then(|option| option.get(1))
+                    .map_or(&b"*"[..], Vec::as_slice);
+                let keys: Vec<_> = data.keys().filter(|key| glob_match(pattern, key)).collect();
+                let mut reply = format!("*2\r\n$1\r\n0\r\n*{}\r\n", keys.len()).into_bytes();
+                keys.into_iter().for_each(|key| reply.extend(bulk(Some(key))));
+                reply
+            }
+            ("EXISTS", keys) => {
+                let found = keys.iter().filter(|key| data.contains_key(*key)).count();
+                format!(":{}\r\n", found).into_bytes()
+            }
+            ("EXPIRE" | "PEXPIRE", [key, ttl]) if std::str::from_utf8(ttl).ok().and_then(|ttl| ttl.parse::<i64>().ok()).is_some_and(|ttl| ttl <= 0) => {
+                format!(":{}\r\n", data.remove(key).is_some() as u8).into_bytes()
+            }
+            ("EXPIRE" | "PEXPIRE" | "EXPIREAT" | "PERSIST", [key, ..]) => format!(":{}\r\n", data.contains_key(key) as u8).into_bytes(),
+            _ => format!("-ERR unknown command '{}'\r\n", name).into_bytes(),
+        }
+    }
+}
+
+const WRONGTYPE: &[u8] = b"-WRONGTYPE Operation against a key holding the wrong kind of value\r\n";
+
+#[derive(Clone)]
+enum Entry {
+    String(Vec<u8>),
+    Set(BTreeSet<Vec<u8>>),
+}
+
+impl Entry {
+    /// The value of a string, or `None` for other types as `MGET` reports them
+    fn string(&self) -> Option<&Vec<u8>> {
+        match self {
+            Entry::String(value) => Some(value),
+            Entry::Set(_) => None,
+        }
+    }
+}
+
+/// Read one command sent as an array of bulk strings, or `None` once the client is gone
+async fn read_command<R: AsyncBufRead + Unpin>(socket: &mut R) -> Option<Vec<Vec<u8>>> {
+    let mut line = String::new();
+    socket.read_line(&mut line).await.ok()?;
+    let count: usize = line.trim_end().strip_prefix('*')?.parse().ok()?;
+    let mut args = Vec::with_capacity(count);
+    for _ in 0..count {
+        line.clear();
+        socket.read_line(&mut line).await.ok()?;
+        let len: usize = line.trim_end().strip_prefix('$')?.parse().ok()?;
+        let mut arg = vec![0; len + 2];
+        socket.read_exact(&mut arg).await.ok()?;
+        arg.truncate(len);
+        args.push(arg);
+    }
+    (count > 0).then_some(args)
+}
+
+/// Match `key` against a `SCAN` pattern, supporting `*` and `?` but not character classes
+fn glob_match(pattern: &[u8], key: &[u8]) -> bool {
+    match (pattern.split_first(), key.split_first()) {
+        (None, None) => true,
+        (Some((b'*', rest)), _) => glob_match(rest, key) || (!key.is_empty() && glob_match(pattern, &key[1..])),
+        (Some((b'?', rest)), Some((_, key_rest))) => glob_match(rest, key_rest),
+        (Some((expected, rest)), Some((actual, key_rest))) => expected == actual && glob_match(rest, key_rest),
+        _ => false,
+    }
+}
+
+fn bulk(value: Option<&Vec<u8>>) -> Vec<u8> {
+    match value {
+        Some(value) => {
+            let mut reply = format!("${}\r\n", value.len()).into_bytes();
+            reply.extend_from_slice(value);
+            reply.extend_from_slice(b"\r\n");
+            reply
+        }
+        None => b"$-1\r\n".to_vec(),
+    }
+}
+
+/// Connection failing its first `failures` requests with a dropped-connection error, then
+/// answering every command with `reply`. Clones share the request count.
+#[derive(Clone)]
+pub(crate) struct FlakyConnection {
+    failures: usize,
+    reply: Value,
+    requests: Arc<AtomicUsize>,
+}
+
+impl FlakyConnection {
+    pub(crate) fn new(failures: usize, reply: Value) -> Self {
+        Self {
+            failures,
+            reply,
+            requests: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// A connection that never succeeds
+    pub(crate) fn down() -> Self {
+        Self::new(usize::MAX, Value::Nil)
+    }
+
+    /// How many requests were sent, including failed ones
+    pub(crate) fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+
+    fn respond(&self) -> Result<(), RedisError> {
+        if self.requests.fetch_add(1, Ordering::SeqCst) < self.failures {
+            let error = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "connection reset by test double");
+            return Err(error.into());
+        }
+        Ok(())
+    }
+}
+
+impl ConnectionLike for FlakyConnection {
+    fn req_packed_command<'a>(&'a mut self, _cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        Box::pin(async move {
+            self.respond()?;
+            Ok(self.reply.clone())
+        })
+    }
+
+    fn req_packed_commands<'a>(&'a mut self, _cmd: &'a Pipeline, _offset: usize, count: usize) -> RedisFuture<'a, Vec<Value>> {
+        Box::pin(async move {
+            self.respond()?;
+            Ok(vec![self.reply.clone(); count])
+        })
+    }
+
+    fn get_db(&self) -> i64 {
+        0
+    }
+}
@@ -0,0 +1,17 @@
+//! Helpers for recording `tracing` span fields. Without the `tracing` feature these are no-ops.
+
+use std::time::Instant;
+
+/// Record the outcome of a cache lookup (`hit`, `miss` or `error`) on the current span
+#[allow(unused_variables)]
+pub(crate) fn record_result(result: &'static str) {
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("cache.result", result);
+}
+
+/// Record the Redis round-trip latency since `start` on the current span
+#[allow(unused_variables)]
+pub(crate) fn record_latency(start: Instant) {
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("redis.latency_ms", start.elapsed().as_secs_f64() * 1000.0);
+}
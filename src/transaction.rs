@@ -0,0 +1,87 @@
+use anyhow::Result as AnyResult;
+use futures::future::BoxFuture;
+use log::debug;
+use redis::aio::ConnectionLike;
+use redis::{FromRedisValue, Pipeline, RedisResult};
+
+/// How many times a transaction is re-run after a concurrent write aborts it
+const MAX_TRANSACTION_ATTEMPTS: u32 = 10;
+
+/// Run an optimistic `WATCH`/`MULTI`/`EXEC` transaction over `keys`.
+///
+/// `f` is called after the keys are watched. It can read their current values through the
+/// connection and queue writes into the pipeline, which is sent as a `MULTI`/`EXEC` block.
+/// If another client modifies a watched key before `EXEC`, the block is discarded and `f`
+/// runs again against the new values, up to a fixed number of attempts.
+///
+/// `WATCH` applies to the whole connection, so use a dedicated one such as
+/// `get_redis_pool()` rather than a shared connection manager, which would let unrelated
+/// commands from other tasks run inside the transaction. In cluster mode all keys must
+/// hash to the same slot.
+pub async fn transaction<C, T, F>(conn: &mut C, keys: &[&str], mut f: F) -> AnyResult<T>
+where
+    C: ConnectionLike + Send,
+    T: FromRedisValue,
+    F: for<'a> FnMut(&'a mut C, &'a mut Pipeline) -> BoxFuture<'a, AnyResult<()>>,
+{
+    for attempt in 1..=MAX_TRANSACTION_ATTEMPTS {
+        redis::cmd("WATCH").arg(keys).query_async::<_, ()>(conn).await?;
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        if let Err(e) = f(conn, &mut pipe).await {
+            let _: RedisResult<()> = redis::cmd("UNWATCH").query_async(conn).await;
+            return Err(e);
+        }
+
+        match pipe.query_async::<_, Option<T>>(conn).await? {
+            Some(result) => return Ok(result),
+            None => debug!("Transaction on {:?} aborted by a concurrent write (attempt {})", keys, attempt),
+        }
+    }
+
+    Err(anyhow::anyhow!(
+        "Transaction on {:?} aborted by concurrent writes {} times",
+        keys,
+        MAX_TRANSACTION_ATTEMPTS
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AsyncConnection, RedisConfig};
+    use crate::testing::live_conn;
+    use redis::AsyncCommands;
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server at REDIS_URL"]
+    async fn a_concurrent_write_makes_the_transaction_run_again() {
+        let (mut other, prefix) = live_conn().await;
+        let mut conn = RedisConfig::from_env().connect_dedicated().await.unwrap();
+        let key = format!("{}:balance", prefix);
+        let _: () = other.set(&key, 10).await.unwrap();
+
+        let mut attempts = 0;
+        let (balance,): (i64,) = transaction(&mut conn, &[&key], |conn: &mut AsyncConnection, pipe: &mut Pipeline| {
+            attempts += 1;
+            let interfere = attempts == 1;
+            let key = key.clone();
+            let mut other = other.clone();
+            Box::pin(async move {
+                let balance: i64 = conn.get(&key).await?;
+                if interfere {
+                    let _: () = other.set(&key, 100).await?;
+                }
+                pipe.set(&key, balance + 1).ignore().get(&key);
+                Ok(())
+            })
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(attempts, 2);
+        assert_eq!(balance, 101);
+        let _: () = other.del(&key).await.unwrap();
+    }
+}
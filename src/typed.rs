@@ -0,0 +1,127 @@
+use crate::cache::{CacheError, CachedResponse, CacheManager};
+use anyhow::Result as AnyResult;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Display;
+use std::marker::PhantomData;
+
+/// A cache key family bound to one value type and TTL.
+///
+/// Declare each family once, typically as a `const`, so key construction, the stored type
+/// and the expiry live in one place and a key cannot be read back as the wrong type:
+///
+/// `const USER_PROFILE: TypedKey<UserProfile> = TypedKey::new("user_profile", 300);`
+///
+/// Keys are `<prefix>:<id>` and go through the manager's namespace like any other key.
+pub struct TypedKey<T> {
+    prefix: &'static str,
+    ttl_seconds: u64,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> TypedKey<T> {
+    /// A key family under `prefix` whose entries expire after `ttl_seconds` (0 for no expiry)
+    pub const fn new(prefix: &'static str, ttl_seconds: u64) -> Self {
+        Self {
+            prefix,
+            ttl_seconds,
+            _value: PhantomData,
+        }
+    }
+
+    pub fn prefix(&self) -> &'static str {
+        self.prefix
+    }
+
+    pub fn ttl_seconds(&self) -> u64 {
+        self.ttl_seconds
+    }
+
+    /// The cache key for `id`, without the manager's namespace
+    pub fn key(&self, id: impl Display) -> String {
+        format!("{}:{}", self.prefix, id)
+    }
+}
+
+impl<T> TypedKey<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// Read the value stored for `id`, or `None` on a miss
+    pub async fn get(&self, cache: &mut CacheManager, id: impl Display) -> Result<Option<T>, CacheError> {
+        let cached = cache.get::<T>(&self.key(id)).await?;
+        Ok(cached.map(|response| response.data))
+    }
+
+    /// Store `value` for `id` with this family's TTL. Returns `Ok(false)` when Redis is not configured.
+    pub async fn set(&self, cache: &mut CacheManager, id: impl Display, value: T) -> Result<bool, CacheError> {
+        let key = self.key(id);
+        let response = CachedResponse::new(value, key.clone());
+        cache.set_with_ttl(&key, &response, self.ttl_seconds).await
+    }
+
+    /// Remove the value stored for `id`
+    pub async fn delete(&self, cache: &mut CacheManager, id: impl Display) -> AnyResult<bool> {
+        cache.delete(&self.key(id)).await
+    }
+}
+
+// Manual impls: the derives would require `T: Clone`/`T: Copy`, which the key itself does not need
+impl<T> Clone for TypedKey<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TypedKey<T> {}
+
+impl<T> std::fmt::Debug for TypedKey<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TypedKey")
+            .field("prefix", &self.prefix)
+            .field("ttl_seconds", &self.ttl_seconds)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::breaker::CircuitBreaker;
+    use crate::config::AsyncConnManager;
+    use crate::retry::RetryPolicy;
+    use crate::testing::FakeRedis;
+    use serde::Deserialize;
+    use std::time::Duration;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct UserProfile {
+        name: String,
+        age: u32,
+    }
+
+    const USER_PROFILE: TypedKey<UserProfile> = TypedKey::new("user_profile", 300);
+
+    #[tokio::test]
+    async fn values_round_trip_through_a_typed_key() {
+        let server = FakeRedis::start().await;
+        let conn = AsyncConnManager::new(redis::Client::open(server.url()).unwrap()).await.unwrap();
+        let mut cache = CacheManager::with_connection(conn)
+            .with_namespace("")
+            .with_local_cache(0, Duration::ZERO)
+            .with_retry_policy(RetryPolicy::none())
+            .with_circuit_breaker(CircuitBreaker::disabled());
+        let alice = UserProfile {
+            name: "alice".to_string(),
+            age: 30,
+        };
+
+        assert_eq!(USER_PROFILE.key(42), "user_profile:42");
+        assert_eq!(USER_PROFILE.get(&mut cache, 42).await.unwrap(), None);
+        assert!(USER_PROFILE.set(&mut cache, 42, alice).await.unwrap());
+        let read = USER_PROFILE.get(&mut cache, 42).await.unwrap().unwrap();
+        assert_eq!((read.name.as_str(), read.age), ("alice", 30));
+        assert!(USER_PROFILE.delete(&mut cache, 42).await.unwrap());
+        assert_eq!(USER_PROFILE.get(&mut cache, 42).await.unwrap(), None);
+    }
+}
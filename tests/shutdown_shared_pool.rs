@@ -0,0 +1,24 @@
+//! Publishing through the shared connection after it was shut down. The shutdown is
+//! process-wide, so this runs as its own test binary.
+
+use shared_redis::config::{init_shared_config, shutdown_shared_conn_manager, RedisConfig};
+use shared_redis::operations::broadcasting_data;
+use std::time::Duration;
+
+#[tokio::test]
+async fn broadcasting_after_shutdown_returns_the_closed_error() {
+    // A port that was just free, so a reconnect would fail with a different error
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+    init_shared_config(RedisConfig {
+        redis_url: Some(format!("redis://{}/", addr)),
+        connect_timeout: Duration::from_millis(500),
+        ..RedisConfig::from_env()
+    })
+    .unwrap();
+
+    shutdown_shared_conn_manager();
+    let error = broadcasting_data("events".to_string(), "payload".to_string()).await.unwrap_err();
+    assert!(error.to_string().contains("closed"), "{}", error);
+}
@@ -0,0 +1,23 @@
+//! Publishing through the shared connection when Redis cannot be reached. The shared config
+//! is process-wide, so this runs as its own test binary.
+
+use shared_redis::config::{init_shared_config, RedisConfig};
+use shared_redis::operations::broadcasting_data;
+use std::time::Duration;
+
+#[tokio::test]
+async fn broadcasting_to_unreachable_redis_returns_err() {
+    // A port that was just free, so connecting is refused
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+    init_shared_config(RedisConfig {
+        redis_url: Some(format!("redis://{}/", addr)),
+        connect_timeout: Duration::from_millis(500),
+        ..RedisConfig::from_env()
+    })
+    .unwrap();
+
+    let result = broadcasting_data("events".to_string(), "payload".to_string()).await;
+    assert!(result.is_err());
+}